@@ -0,0 +1,201 @@
+use std::fmt;
+
+use crate::data::{Circuit, Mul};
+use crate::dividers::DivInfo;
+use crate::simulation::{pack, unpack, Simulation};
+
+// dividers with at most this many input bits are simulated for every input
+const EXHAUSTIVE_INPUT_BITS: usize = 20;
+
+// the quotient errors of a divider against exact division over the inputs with a divisor other
+// than zero and a quotient that fits into Q, the error distance of an input is |Q - R_0 / D|,
+// mred is the mean of the error distance relative to the exact quotient, or to 1 if that is 0
+#[derive(Debug, PartialEq, Clone)]
+pub struct ErrorStats {
+    pub samples: usize,
+    pub exhaustive: bool,
+    pub erroneous: usize,
+    pub mean_error_distance: f64,
+    pub max_error: u128,
+    pub mred: f64,
+}
+
+impl ErrorStats {
+    pub fn error_rate(&self) -> f64 {
+        match self.samples {
+            0 => 0.0,
+            samples => self.erroneous as f64 / samples as f64,
+        }
+    }
+}
+
+impl fmt::Display for ErrorStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} inputs: error rate {:.4}%, mean error distance {:.4}, max error {}, mred {:.4}%",
+            if self.exhaustive { "all" } else { "random" },
+            self.samples,
+            100.0 * self.error_rate(),
+            self.mean_error_distance,
+            self.max_error,
+            100.0 * self.mred
+        )
+    }
+}
+
+// size and errors of one approximation of a divider
+#[derive(Debug, PartialEq, Clone)]
+pub struct ApproximationPoint {
+    pub name: String,
+    pub gates: usize,
+    pub levels: usize,
+    pub errors: ErrorStats,
+}
+
+pub struct ApproximationSpace {
+    pub points: Vec<ApproximationPoint>,
+}
+
+impl fmt::Display for ApproximationSpace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self.points.iter().map(|p| p.name.len()).max().unwrap_or(0).max(13);
+        writeln!(f, "{:<width$} {:>8} {:>8} {:>11} {:>10} {:>10} {:>9}", "approximation", "gates", "levels", "error rate", "med", "max error", "mred")?;
+        for p in &self.points {
+            writeln!(
+                f,
+                "{:<width$} {:>8} {:>8} {:>10.4}% {:>10.4} {:>10} {:>8.4}%",
+                p.name,
+                p.gates,
+                p.levels,
+                100.0 * p.errors.error_rate(),
+                p.errors.mean_error_distance,
+                p.errors.max_error,
+                100.0 * p.errors.mred
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Circuit {
+    fn input_width(&self, name: &str) -> usize {
+        self.inputs
+            .iter()
+            .find(|io| io.name == name)
+            .unwrap_or_else(|| panic!("circuit has no input named {name}"))
+            .bits
+            .len()
+    }
+
+    // the Q output for up to 64 pairs of dividend and divisor, an iterative divider is reset,
+    // started and clocked until it is done
+    fn simulate_quotients(&self, pairs: &[(u128, u128)]) -> Vec<u128> {
+        let iterative = self.inputs.iter().any(|io| io.name == "start");
+        let mut sim = Simulation::new(self);
+        if iterative {
+            sim.set_input("rst", &[u64::MAX]);
+            sim.eval();
+            sim.clock();
+            sim.set_input("rst", &[0]);
+            sim.set_input("start", &[u64::MAX]);
+        }
+        let dividends: Vec<u128> = pairs.iter().map(|&(dividend, _)| dividend).collect();
+        let divisors: Vec<u128> = pairs.iter().map(|&(_, divisor)| divisor).collect();
+        sim.set_input("R_0", &pack(&dividends, self.input_width("R_0")));
+        sim.set_input("D", &pack(&divisors, self.input_width("D")));
+        sim.eval();
+        if iterative {
+            let mut cycles = 0;
+            while sim.output("done")[0] == 0 {
+                if cycles > self.registers.len() {
+                    panic!("the divider is not done after {cycles} cycles");
+                }
+                sim.clock();
+                sim.set_input("start", &[0]);
+                sim.eval();
+                cycles += 1;
+            }
+        }
+        unpack(&sim.output("Q"))[..pairs.len()].to_vec()
+    }
+
+    // quotient errors of the divider netlist against exact division, over all inputs if they
+    // have at most EXHAUSTIVE_INPUT_BITS bits and over that many random samples otherwise,
+    // the divider has to be combinational or iterative
+    pub fn error_stats(&self, samples: usize) -> ErrorStats {
+        let iterative = self.inputs.iter().any(|io| io.name == "start");
+        if !iterative && !self.registers.is_empty() {
+            panic!("error statistics need a combinational or an iterative divider, this one has {} registers", self.registers.len());
+        }
+        let (dividend_bits, divisor_bits) = (self.input_width("R_0"), self.input_width("D"));
+        let quotient_bits = self.outputs.iter().find(|io| io.name == "Q").expect("circuit has no output named Q").bits.len();
+        if dividend_bits > 127 || divisor_bits > 127 {
+            panic!("error statistics support operands of at most 127 bits, not {dividend_bits} and {divisor_bits}");
+        }
+        let valid = |(dividend, divisor): (u128, u128)| divisor != 0 && (quotient_bits >= 128 || (dividend / divisor) >> quotient_bits == 0);
+        let exhaustive = dividend_bits + divisor_bits <= EXHAUSTIVE_INPUT_BITS;
+        let pairs: Vec<(u128, u128)> = if exhaustive {
+            (0..1u128 << divisor_bits).flat_map(|divisor| (0..1u128 << dividend_bits).map(move |dividend| (dividend, divisor))).filter(|&pair| valid(pair)).collect()
+        } else {
+            let (dividend_mask, divisor_mask) = ((1u128 << dividend_bits) - 1, (1u128 << divisor_bits) - 1);
+            std::iter::repeat_with(|| (rand::random::<u128>() & dividend_mask, rand::random::<u128>() & divisor_mask))
+                .filter(|&pair| valid(pair))
+                .take(samples)
+                .collect()
+        };
+
+        let (mut erroneous, mut max_error, mut distance_sum, mut relative_sum) = (0, 0, 0.0, 0.0);
+        for chunk in pairs.chunks(64) {
+            for (&(dividend, divisor), q) in chunk.iter().zip(self.simulate_quotients(chunk)) {
+                let exact = dividend / divisor;
+                let distance = q.abs_diff(exact);
+                erroneous += (distance != 0) as usize;
+                max_error = max_error.max(distance);
+                distance_sum += distance as f64;
+                relative_sum += distance as f64 / exact.max(1) as f64;
+            }
+        }
+        let count = pairs.len().max(1) as f64;
+        ErrorStats {
+            samples: pairs.len(),
+            exhaustive,
+            erroneous,
+            mean_error_distance: distance_sum / count,
+            max_error,
+            mred: relative_sum / count,
+        }
+    }
+
+    // builds the divider described by info exactly, with every number of dropped iterations,
+    // with 1, 2, 4, ... truncated columns up to the width if its multiplier is the dadda tree
+    // and without the correction step and measures the size and the errors of each
+    pub fn approximation_space(info: DivInfo, samples: usize) -> ApproximationSpace {
+        let exact = DivInfo { dropped_iterations: 0, truncated_columns: 0, skip_correction: false, ..info };
+        let mut approximations = vec![("exact".to_string(), exact)];
+        let iterations = Circuit::exact_iterations(&exact, info.number_bits);
+        for dropped in 1..=iterations {
+            approximations.push((format!("drop {dropped} of {iterations} iterations"), DivInfo { dropped_iterations: dropped, ..exact }));
+        }
+        let columns = if info.defaultmult == Mul::DadaTree { info.number_bits } else { 0 };
+        for truncated in std::iter::successors(Some(1), |columns| Some(2 * columns)).take_while(|&truncated| truncated <= columns) {
+            approximations.push((format!("truncate {truncated} of {} columns", info.number_bits), DivInfo { truncated_columns: truncated, ..exact }));
+        }
+        approximations.push(("skip correction".to_string(), DivInfo { skip_correction: true, ..exact }));
+
+        let points = approximations
+            .into_iter()
+            .map(|(name, info)| {
+                let mut circuit = Circuit::get_divider_circuit(info);
+                circuit.remove_dead_ends();
+                ApproximationPoint {
+                    name,
+                    gates: circuit.stats.gatter_count,
+                    levels: circuit.stats.level_count,
+                    errors: circuit.error_stats(samples),
+                }
+            })
+            .collect();
+        ApproximationSpace { points }
+    }
+}
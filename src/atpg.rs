@@ -0,0 +1,327 @@
+use std::fmt;
+
+use crate::data::{Bit, Circuit, Gate, Line};
+use crate::faults::Fault;
+use crate::sat::{Cnf, Lit, SatResult, Solver};
+use crate::simulation::{pack, unpack, Simulation};
+
+// the outcome of test pattern generation, redundant faults cannot change any output, so
+// their line can be replaced by the constant, aborted faults hit the conflict limit
+#[derive(Debug, PartialEq, Clone)]
+pub struct Atpg {
+    pub vectors: Vec<Vec<u128>>,
+    pub faults: usize,
+    pub detected: usize,
+    pub redundant: Vec<Fault>,
+    pub aborted: Vec<Fault>,
+    pub sat_calls: usize,
+    // true if the faults the solver gave up on were fault simulated for all inputs
+    pub exhaustive: bool,
+    // path, faults and redundant faults of the components with redundant faults
+    pub components: Vec<(String, usize, usize)>,
+    pub redundant_names: Vec<String>,
+}
+
+impl Atpg {
+    // detected faults of all faults that are not redundant
+    pub fn efficiency(&self) -> f64 {
+        match self.faults - self.redundant.len() {
+            0 => 1.0,
+            detectable => self.detected as f64 / detectable as f64,
+        }
+    }
+}
+
+impl fmt::Display for Atpg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} vectors detect {} of {} stuck-at faults, {} redundant, {} aborted, efficiency {:.2}%",
+            self.vectors.len(),
+            self.detected,
+            self.faults,
+            self.redundant.len(),
+            self.aborted.len(),
+            100.0 * self.efficiency()
+        )?;
+        match self.exhaustive {
+            true => writeln!(f, "{} sat calls, the faults it gave up on were simulated for all inputs", self.sat_calls)?,
+            false => writeln!(f, "{} sat calls", self.sat_calls)?,
+        }
+        if self.components.is_empty() {
+            return Ok(());
+        }
+        let width = self.components.iter().map(|(path, _, _)| path.len()).max().unwrap_or(0).max(9);
+        writeln!(f, "{:width$} {:>8} {:>10}", "component", "faults", "redundant")?;
+        for (path, faults, redundant) in &self.components {
+            writeln!(f, "{path:width$} {faults:>8} {redundant:>10}")?;
+        }
+        let shown = self.redundant_names.len().min(20);
+        write!(f, "redundant: {}", self.redundant_names[..shown].join(", "))?;
+        match self.redundant_names.len() - shown {
+            0 => writeln!(f),
+            more => writeln!(f, " and {more} more"),
+        }
+    }
+}
+
+// inputs of at most that many bits are fault simulated for all values when the sat solver
+// gives up on faults
+const EXHAUSTIVE_INPUT_BITS: usize = 16;
+
+// the literal of a gate output from the literals of its inputs
+fn encode_gate(cnf: &mut Cnf, gate: Gate, lit: impl Fn(Line) -> Lit) -> Lit {
+    match gate {
+        Gate::Not(l) => !lit(l),
+        Gate::And(l1, l2) => cnf.and(lit(l1), lit(l2)),
+        Gate::Or(l1, l2) => !cnf.and(!lit(l1), !lit(l2)),
+        Gate::Xor(l1, l2) => cnf.xor(lit(l1), lit(l2)),
+    }
+}
+
+impl Circuit {
+    // a vector detecting the fault, found by a sat solver on the fault free circuit and a
+    // copy of the gates the faulty line reaches, both restricted to the fanin of the outputs
+    // it reaches, the inputs outside of that fanin are random, Unsat if the fault is redundant
+    // and Unknown if the solver gives up
+    fn test_vector(&self, fault: Fault, first_wire: &[usize], wire_of_line: &[Option<usize>], conflict_limit: usize) -> (SatResult, Option<Vec<u128>>) {
+        let line_count = self.stats.line_count;
+        let mut reached = vec![false; line_count];
+        reached[fault.line.n] = true;
+        for wire in &self.wires[first_wire[fault.line.n]..] {
+            reached[wire.out.n] = wire.gate.inputs().iter().any(|l| reached[l.n]);
+        }
+        let outputs: Vec<Line> = self
+            .outputs
+            .iter()
+            .flat_map(|io| io.bits.iter())
+            .filter_map(|bit| match bit {
+                Bit::Var(l) if reached[l.n] => Some(*l),
+                _ => None,
+            })
+            .collect();
+        if outputs.is_empty() {
+            return (SatResult::Unsat, None);
+        }
+        let mut in_fanin = vec![false; line_count];
+        let mut stack: Vec<Line> = outputs.clone();
+        while let Some(l) = stack.pop() {
+            if in_fanin[l.n] {
+                continue;
+            }
+            in_fanin[l.n] = true;
+            if let Some(idx) = wire_of_line[l.n] {
+                stack.extend(self.wires[idx].gate.inputs());
+            }
+        }
+
+        let mut cnf = Cnf::new();
+        let mut good: Vec<Option<Lit>> = vec![None; line_count];
+        for input in &self.inputs {
+            for bit in &input.bits {
+                if let Bit::Var(l) = bit {
+                    if in_fanin[l.n] {
+                        good[l.n] = Some(cnf.new_var());
+                    }
+                }
+            }
+        }
+        for wire in self.wires.iter().filter(|wire| in_fanin[wire.out.n]) {
+            good[wire.out.n] = Some(encode_gate(&mut cnf, wire.gate, |l| good[l.n].expect("line outside of the fanin")));
+        }
+        let mut faulty = good.clone();
+        faulty[fault.line.n] = Some(Cnf::constant(fault.stuck_at));
+        for wire in &self.wires[first_wire[fault.line.n]..] {
+            if reached[wire.out.n] && in_fanin[wire.out.n] {
+                faulty[wire.out.n] = Some(encode_gate(&mut cnf, wire.gate, |l| faulty[l.n].expect("line outside of the fanin")));
+            }
+        }
+        // the fault has to be activated and reach an output
+        let activated = good[fault.line.n].expect("faulty line outside of the fanin");
+        cnf.add_clause(&[activated.xor(fault.stuck_at)]);
+        let differences: Vec<Lit> = outputs.iter().map(|l| cnf.xor(good[l.n].unwrap(), faulty[l.n].unwrap())).collect();
+        let differ = cnf.any(&differences);
+        cnf.add_clause(&[differ]);
+
+        let mut solver = Solver::new();
+        solver.reserve(cnf.vars);
+        for clause in &cnf.clauses {
+            solver.add_clause(clause);
+        }
+        let result = solver.solve(&[], Some(conflict_limit));
+        if result != SatResult::Sat {
+            return (result, None);
+        }
+        let vector = self
+            .inputs
+            .iter()
+            .map(|input| {
+                input.bits.iter().enumerate().fold(0, |n, (idx, bit)| {
+                    let value = match bit {
+                        Bit::Var(l) => match good[l.n] {
+                            Some(lit) => solver.model_value(lit.var()) ^ lit.is_negated(),
+                            None => rand::random(),
+                        },
+                        _ => false,
+                    };
+                    n | (value as u128) << idx
+                })
+            })
+            .collect();
+        (result, Some(vector))
+    }
+
+    // test vectors for all detectable stuck-at faults, random vectors detect the easy faults
+    // first, then the sat solver is asked for a vector for every fault still undetected, its
+    // vectors are fault simulated in groups of 64 to drop the faults they detect on the way,
+    // at last the vectors are fault simulated in reverse order and those detecting no fault
+    // the later ones miss are dropped
+    pub fn atpg(&self, random_vectors: usize, conflict_limit: usize) -> Atpg {
+        let faults = self.faults();
+        let first_wire = self.first_reachable_wire();
+        let mut wire_of_line = vec![None; self.stats.line_count];
+        for (idx, wire) in self.wires.iter().enumerate() {
+            wire_of_line[wire.out.n] = Some(idx);
+        }
+        let mut detected = vec![false; faults.len()];
+        let random = self.random_vectors(random_vectors);
+        let useful = self.fault_simulate(&faults, &random, &mut detected);
+        let mut vectors: Vec<Vec<u128>> = random.into_iter().zip(useful).filter(|(_, u)| *u).map(|(v, _)| v).collect();
+
+        let mut redundant = vec![false; faults.len()];
+        let mut aborted = vec![false; faults.len()];
+        let mut pending = Vec::new();
+        let mut sat_calls = 0;
+        for idx in 0..faults.len() {
+            if pending.len() == 64 {
+                self.fault_simulate(&faults, &pending, &mut detected);
+                vectors.append(&mut pending);
+            }
+            if detected[idx] {
+                continue;
+            }
+            sat_calls += 1;
+            match self.test_vector(faults[idx], &first_wire, &wire_of_line, conflict_limit) {
+                (SatResult::Sat, Some(vector)) => {
+                    detected[idx] = true;
+                    pending.push(vector);
+                }
+                (SatResult::Unsat, _) => redundant[idx] = true,
+                _ => aborted[idx] = true,
+            }
+        }
+        self.fault_simulate(&faults, &pending, &mut detected);
+        vectors.append(&mut pending);
+
+        // for up to 16 input bits all vectors are simulated, what they miss is redundant
+        let input_bits: usize = self.inputs.iter().map(|io| io.bits.len()).sum();
+        let exhaustive = input_bits <= EXHAUSTIVE_INPUT_BITS && aborted.contains(&true);
+        if exhaustive {
+            let all: Vec<Vec<u128>> = (0..1_u128 << input_bits)
+                .map(|mut n| {
+                    self.inputs
+                        .iter()
+                        .map(|io| {
+                            let value = n & ((1 << io.bits.len()) - 1);
+                            n >>= io.bits.len();
+                            value
+                        })
+                        .collect()
+                })
+                .collect();
+            let mut simulated: Vec<bool> = aborted.iter().zip(&detected).map(|(&a, &d)| !a || d).collect();
+            let useful = self.fault_simulate(&faults, &all, &mut simulated);
+            vectors.extend(all.into_iter().zip(useful).filter(|(_, u)| *u).map(|(v, _)| v));
+            for idx in 0..faults.len() {
+                if aborted[idx] {
+                    detected[idx] = simulated[idx];
+                    redundant[idx] = !simulated[idx];
+                    aborted[idx] = false;
+                }
+            }
+        }
+
+        vectors.reverse();
+        let mut compacted = vec![false; faults.len()];
+        let useful = self.fault_simulate(&faults, &vectors, &mut compacted);
+        if compacted != detected {
+            panic!("the test vectors do not detect the faults they were generated for");
+        }
+        let mut vectors: Vec<Vec<u128>> = vectors.into_iter().zip(useful).filter(|(_, u)| *u).map(|(v, _)| v).collect();
+        vectors.reverse();
+
+        let select = |marked: &[bool]| faults.iter().zip(marked).filter(|&(_, &m)| m).map(|(&fault, _)| fault).collect::<Vec<Fault>>();
+        let redundant_faults = select(&redundant);
+        Atpg {
+            vectors,
+            faults: faults.len(),
+            detected: detected.iter().filter(|&&d| d).count(),
+            sat_calls,
+            exhaustive,
+            components: self.faults_by_component(&faults, &redundant),
+            redundant_names: redundant_faults.iter().map(|&fault| self.fault_name(fault)).collect(),
+            redundant: redundant_faults,
+            aborted: select(&aborted.iter().zip(&detected).map(|(&a, &d)| a && !d).collect::<Vec<bool>>()),
+        }
+    }
+
+    // the vectors in the format read by parse_vectors and $readmemh
+    pub fn vectors_text(&self, vectors: &[Vec<u128>]) -> String {
+        let names: Vec<&str> = self.inputs.iter().map(|io| io.name.as_str()).collect();
+        let mut s = format!("// {}\n", names.join(" "));
+        for vector in vectors {
+            let fields: Vec<String> = vector
+                .iter()
+                .zip(&self.inputs)
+                .map(|(n, io)| format!("{n:0width$x}", width = io.bits.len().div_ceil(4).max(1)))
+                .collect();
+            s.push_str(&fields.join(" "));
+            s.push('\n');
+        }
+        s
+    }
+
+    // a testbench applying the vectors to the module and comparing every output with the
+    // value the fault free circuit computes, it reports the failing vectors and their count
+    pub fn testbench(&self, module_name: &str, vectors: &[Vec<u128>]) -> String {
+        let mut s = format!("`timescale 1ns / 1ps\nmodule {module_name}_tb;\n");
+        for io in &self.inputs {
+            s.push_str(&format!("reg [{}:0] {};\n", io.bits.len().max(1) - 1, io.name));
+        }
+        for io in &self.outputs {
+            s.push_str(&format!("wire [{}:0] {};\n", io.bits.len().max(1) - 1, io.name));
+        }
+        s.push_str("integer errors;\n");
+        let ports: Vec<String> = self.inputs.iter().chain(&self.outputs).map(|io| format!(".{0}({0})", io.name)).collect();
+        s.push_str(&format!("{module_name} dut({});\n", ports.join(", ")));
+        s.push_str("initial begin\n    errors = 0;\n");
+
+        let constant = |n: u128, bits: usize| format!("{}'h{n:x}", bits.max(1));
+        let mut sim = Simulation::new(self);
+        for (chunk_idx, chunk) in vectors.chunks(64).enumerate() {
+            for (input_idx, io) in self.inputs.iter().enumerate() {
+                let numbers: Vec<u128> = chunk.iter().map(|vector| vector[input_idx]).collect();
+                sim.set_input(&io.name, &pack(&numbers, io.bits.len()));
+            }
+            sim.eval();
+            let expected: Vec<Vec<u128>> = self.outputs.iter().map(|io| unpack(&sim.output(&io.name))).collect();
+            for (idx, vector) in chunk.iter().enumerate() {
+                for (n, io) in vector.iter().zip(&self.inputs) {
+                    s.push_str(&format!("    {} = {};\n", io.name, constant(*n, io.bits.len())));
+                }
+                let checks: Vec<String> = self
+                    .outputs
+                    .iter()
+                    .zip(&expected)
+                    .map(|(io, values)| format!("{} !== {}", io.name, constant(values[idx], io.bits.len())))
+                    .collect();
+                s.push_str(&format!("    #1 if ({}) begin\n", checks.join(" || ")));
+                s.push_str(&format!("        $display(\"vector {} failed\");\n", 64 * chunk_idx + idx));
+                s.push_str("        errors = errors + 1;\n    end\n");
+            }
+        }
+        s.push_str(&format!("    $display(\"%0d of {} vectors failed\", errors);\n", vectors.len()));
+        s.push_str("    $finish;\nend\nendmodule\n");
+        s
+    }
+}
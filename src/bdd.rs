@@ -0,0 +1,432 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::data::{Bit, Circuit, Gate};
+use crate::equivalence::{hex, Counterexample, Equivalence};
+use clap::ValueEnum;
+
+// the nodes of the constant functions
+pub const FALSE: usize = 0;
+pub const TRUE: usize = 1;
+
+// a decision on the variable at level, the constants have the level usize::MAX
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+struct Node {
+    level: usize,
+    low: usize,
+    high: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+enum Op {
+    And,
+    Or,
+    Xor,
+}
+
+// a manager of reduced ordered binary decision diagrams sharing their nodes, a function is
+// the index of its root node, so equal functions have equal indices
+#[derive(Debug, Clone)]
+pub struct Bdd {
+    nodes: Vec<Node>,
+    unique: HashMap<Node, usize>,
+    computed: HashMap<(Op, usize, usize), usize>,
+    // number of variables
+    pub levels: usize,
+}
+
+impl Bdd {
+    pub fn new() -> Self {
+        let constant = Node {
+            level: usize::MAX,
+            low: FALSE,
+            high: FALSE,
+        };
+        Bdd {
+            nodes: vec![constant, constant],
+            unique: HashMap::new(),
+            computed: HashMap::new(),
+            levels: 0,
+        }
+    }
+
+    fn make(&mut self, level: usize, low: usize, high: usize) -> usize {
+        if low == high {
+            return low;
+        }
+        let node = Node { level, low, high };
+        if let Some(&idx) = self.unique.get(&node) {
+            return idx;
+        }
+        self.nodes.push(node);
+        self.unique.insert(node, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    // a new variable below all existing ones
+    pub fn new_var(&mut self) -> usize {
+        self.levels += 1;
+        self.var(self.levels - 1)
+    }
+
+    // the function of the variable at level, which has to exist
+    pub fn var(&mut self, level: usize) -> usize {
+        assert!(level < self.levels, "bdd has no variable at level {level}");
+        self.make(level, FALSE, TRUE)
+    }
+
+    fn apply(&mut self, op: Op, a: usize, b: usize) -> usize {
+        let (a, b) = (a.min(b), a.max(b));
+        match op {
+            Op::And if a == FALSE => return FALSE,
+            Op::And if a == TRUE || a == b => return b,
+            Op::Or if a == TRUE => return TRUE,
+            Op::Or if a == FALSE || a == b => return b,
+            Op::Xor if a == b => return FALSE,
+            Op::Xor if a == FALSE => return b,
+            _ => {}
+        }
+        if let Some(&r) = self.computed.get(&(op, a, b)) {
+            return r;
+        }
+        let (na, nb) = (self.nodes[a], self.nodes[b]);
+        let level = na.level.min(nb.level);
+        let cofactors = |n: Node, idx: usize| if n.level == level { (n.low, n.high) } else { (idx, idx) };
+        let ((a0, a1), (b0, b1)) = (cofactors(na, a), cofactors(nb, b));
+        let low = self.apply(op, a0, b0);
+        let high = self.apply(op, a1, b1);
+        let r = self.make(level, low, high);
+        self.computed.insert((op, a, b), r);
+        r
+    }
+
+    pub fn and(&mut self, a: usize, b: usize) -> usize {
+        self.apply(Op::And, a, b)
+    }
+
+    pub fn or(&mut self, a: usize, b: usize) -> usize {
+        self.apply(Op::Or, a, b)
+    }
+
+    pub fn xor(&mut self, a: usize, b: usize) -> usize {
+        self.apply(Op::Xor, a, b)
+    }
+
+    pub fn not(&mut self, a: usize) -> usize {
+        self.apply(Op::Xor, a, TRUE)
+    }
+
+    // number of assignments of all variables for which f is true
+    pub fn sat_count(&self, f: usize) -> u128 {
+        assert!(self.levels < 128, "bdd has {} variables, too many to count assignments", self.levels);
+        let mut counts = HashMap::new();
+        self.count(f, &mut counts) << self.level(f)
+    }
+
+    // the level of a node, the constants are below all variables
+    fn level(&self, f: usize) -> usize {
+        self.nodes[f].level.min(self.levels)
+    }
+
+    // assignments of the variables from the level of f on
+    fn count(&self, f: usize, counts: &mut HashMap<usize, u128>) -> u128 {
+        if f <= TRUE {
+            return f as u128;
+        }
+        if let Some(&c) = counts.get(&f) {
+            return c;
+        }
+        let node = self.nodes[f];
+        let low = self.count(node.low, counts) << (self.level(node.low) - node.level - 1);
+        let high = self.count(node.high, counts) << (self.level(node.high) - node.level - 1);
+        counts.insert(f, low + high);
+        low + high
+    }
+
+    // an assignment of the variables by level for which f is true, free variables are false
+    pub fn satisfying(&self, f: usize) -> Option<Vec<bool>> {
+        if f == FALSE {
+            return None;
+        }
+        let mut values = vec![false; self.levels];
+        let mut node = f;
+        while node > TRUE {
+            let n = self.nodes[node];
+            values[n.level] = n.low == FALSE;
+            node = if n.low == FALSE { n.high } else { n.low };
+        }
+        Some(values)
+    }
+
+    // number of distinct nodes reachable from the functions, without the constants
+    pub fn size(&self, functions: &[usize]) -> usize {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut stack: Vec<usize> = functions.to_vec();
+        let mut size = 0;
+        while let Some(f) = stack.pop() {
+            if f <= TRUE || seen[f] {
+                continue;
+            }
+            seen[f] = true;
+            size += 1;
+            stack.push(self.nodes[f].low);
+            stack.push(self.nodes[f].high);
+        }
+        size
+    }
+
+    // words are vectors of functions, lowest bit first
+
+    // ripple carry sum of two words of the same width without the carry out
+    pub fn add(&mut self, a: &[usize], b: &[usize]) -> Vec<usize> {
+        let mut carry = FALSE;
+        let mut sum = Vec::with_capacity(a.len());
+        for (&a, &b) in a.iter().zip(b) {
+            let half = self.xor(a, b);
+            sum.push(self.xor(half, carry));
+            let (generate, propagate) = (self.and(a, b), self.and(half, carry));
+            carry = self.or(generate, propagate);
+        }
+        sum
+    }
+
+    // shift and add product of two words of the same width, truncated to that width
+    pub fn mul(&mut self, a: &[usize], b: &[usize]) -> Vec<usize> {
+        let mut product = vec![FALSE; a.len()];
+        for (shift, &b) in b.iter().enumerate() {
+            let mut partial = vec![FALSE; shift];
+            for &a in &a[..a.len() - shift] {
+                partial.push(self.and(a, b));
+            }
+            product = self.add(&product, &partial);
+        }
+        product
+    }
+
+    // true if a is less than b, the borrow out of a - b
+    pub fn less(&mut self, a: &[usize], b: &[usize]) -> usize {
+        let mut borrow = FALSE;
+        for (&a, &b) in a.iter().zip(b) {
+            let differ = self.xor(a, b);
+            let smaller = self.and(differ, b);
+            let same = self.not(differ);
+            let kept = self.and(same, borrow);
+            borrow = self.or(smaller, kept);
+        }
+        borrow
+    }
+}
+
+// order of the bdd variables of the input bits of a circuit
+// Declared: the inputs one after another in the order they were declared, lowest bit first
+// Interleaved: bits of the same significance next to each other, highest bits first, the
+// good order for comparisons and for most arithmetic
+#[derive(Debug, PartialEq, Eq, Clone, Copy, ValueEnum)]
+pub enum VariableOrder {
+    Declared,
+    Interleaved,
+}
+
+// the bdds of the outputs of a combinational circuit with the functions of its input bits
+#[derive(Debug, Clone)]
+pub struct CircuitBdds {
+    pub bdd: Bdd,
+    pub inputs: Vec<(String, Vec<usize>)>,
+    pub outputs: Vec<(String, Vec<usize>)>,
+}
+
+impl CircuitBdds {
+    fn output(&self, name: &str) -> &[usize] {
+        match self.outputs.iter().find(|(n, _)| n == name) {
+            Some((_, bits)) => bits,
+            None => panic!("circuit has no output {name}"),
+        }
+    }
+
+    fn input(&self, name: &str) -> &[usize] {
+        match self.inputs.iter().find(|(n, _)| n == name) {
+            Some((_, bits)) => bits,
+            None => panic!("circuit has no input {name}"),
+        }
+    }
+
+    // the values of the inputs for an assignment of the variables
+    fn input_values(&self, values: &[bool]) -> Vec<(String, Vec<bool>)> {
+        self.inputs
+            .iter()
+            .map(|(name, bits)| (name.clone(), bits.iter().map(|&b| values[self.bdd.nodes[b].level]).collect()))
+            .collect()
+    }
+}
+
+// the inputs of a divider with a valid result but a wrong quotient
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct WrongQuotients {
+    // number of inputs with a valid result
+    pub valid: u128,
+    pub wrong: u128,
+    // one of the inputs with a wrong quotient
+    pub example: Option<Vec<(String, Vec<bool>)>>,
+    // nodes of the bdds of the divider outputs
+    pub nodes: usize,
+}
+
+impl fmt::Display for WrongQuotients {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "bdds with {} nodes, {} of {} inputs with a valid result have a wrong quotient", self.nodes, self.wrong, self.valid)?;
+        if let Some(inputs) = &self.example {
+            let values: Vec<String> = inputs.iter().map(|(name, bits)| format!("{name} = {}", hex(bits))).collect();
+            write!(f, ", e.g. {}", values.join(", "))?;
+        }
+        writeln!(f)
+    }
+}
+
+impl Circuit {
+    // creates the variables of the input bits in the given order
+    fn input_bdds(&self, bdd: &mut Bdd, order: VariableOrder) -> Vec<(String, Vec<usize>)> {
+        let mut levels: Vec<Vec<usize>> = self.inputs.iter().map(|io| vec![0; io.bits.len()]).collect();
+        let mut level = bdd.levels;
+        match order {
+            VariableOrder::Declared => {
+                for bits in levels.iter_mut() {
+                    for l in bits.iter_mut() {
+                        *l = level;
+                        level += 1;
+                    }
+                }
+            }
+            VariableOrder::Interleaved => {
+                let width = levels.iter().map(|bits| bits.len()).max().unwrap_or(0);
+                for i in (0..width).rev() {
+                    for bits in levels.iter_mut().filter(|bits| i < bits.len()) {
+                        bits[i] = level;
+                        level += 1;
+                    }
+                }
+            }
+        }
+        bdd.levels = level;
+        self.inputs
+            .iter()
+            .zip(levels)
+            .map(|(io, bits)| (io.name.clone(), bits.into_iter().map(|l| bdd.var(l)).collect()))
+            .collect()
+    }
+
+    // builds the bdds of the outputs in bdd for the given functions of the inputs, lines that
+    // are neither inputs nor driven by a gate get new variables
+    fn output_bdds(&self, bdd: &mut Bdd, inputs: &[(String, Vec<usize>)]) -> Vec<(String, Vec<usize>)> {
+        if !self.registers.is_empty() {
+            panic!("bdds can only be built for combinational circuits, this one has {} registers", self.registers.len());
+        }
+        let mut lines = HashMap::new();
+        if let Some(l) = self.zero_wire {
+            lines.insert(l.n, FALSE);
+        }
+        if let Some(l) = self.one_wire {
+            lines.insert(l.n, TRUE);
+        }
+        for input in &self.inputs {
+            let Some((_, functions)) = inputs.iter().find(|(name, _)| *name == input.name) else {
+                panic!("no functions given for input {}", input.name);
+            };
+            if functions.len() != input.bits.len() {
+                panic!("input {} has {} bits but {} functions", input.name, input.bits.len(), functions.len());
+            }
+            for (bit, &f) in input.bits.iter().zip(functions) {
+                if let Bit::Var(l) = bit {
+                    lines.insert(l.n, f);
+                }
+            }
+        }
+        for wire in &self.wires {
+            for l in wire.gate.inputs() {
+                lines.entry(l.n).or_insert_with(|| bdd.new_var());
+            }
+            let f = match wire.gate {
+                Gate::Not(l) => bdd.not(lines[&l.n]),
+                Gate::And(l1, l2) => bdd.and(lines[&l1.n], lines[&l2.n]),
+                Gate::Or(l1, l2) => bdd.or(lines[&l1.n], lines[&l2.n]),
+                Gate::Xor(l1, l2) => bdd.xor(lines[&l1.n], lines[&l2.n]),
+            };
+            lines.insert(wire.out.n, f);
+        }
+        let function = |bit: Bit| match bit {
+            Bit::Zero => FALSE,
+            Bit::One => TRUE,
+            Bit::Var(l) => lines[&l.n],
+        };
+        self.outputs
+            .iter()
+            .map(|io| (io.name.clone(), io.bits.iter().map(|&bit| function(bit)).collect()))
+            .collect()
+    }
+
+    // the bdds of all outputs of a combinational circuit
+    pub fn bdds(&self, order: VariableOrder) -> CircuitBdds {
+        let mut bdd = Bdd::new();
+        let inputs = self.input_bdds(&mut bdd, order);
+        let outputs = self.output_bdds(&mut bdd, &inputs);
+        CircuitBdds { bdd, inputs, outputs }
+    }
+
+    // exact equivalence check of two combinational circuits with inputs and outputs of the
+    // same names and sizes, a counterexample shows the first differing output only
+    pub fn check_equivalence_bdd(&self, other: &Circuit, order: VariableOrder) -> Equivalence {
+        let mut bdds = self.bdds(order);
+        let second = other.output_bdds(&mut bdds.bdd, &bdds.inputs);
+        for (name, first) in &bdds.outputs {
+            let Some((_, second)) = second.iter().find(|(n, _)| n == name) else {
+                panic!("second circuit has no output {name}");
+            };
+            if first.len() != second.len() {
+                panic!("output {name} has {} and {} bits", first.len(), second.len());
+            }
+            let Some(i) = (0..first.len()).find(|&i| first[i] != second[i]) else {
+                continue;
+            };
+            let differ = bdds.bdd.xor(first[i], second[i]);
+            let values = bdds.bdd.satisfying(differ).unwrap();
+            let value = |f: &usize| {
+                let mut node = *f;
+                while node > TRUE {
+                    let n = bdds.bdd.nodes[node];
+                    node = if values[n.level] { n.high } else { n.low };
+                }
+                node == TRUE
+            };
+            let outputs = vec![(name.clone(), first.iter().map(value).collect(), second.iter().map(value).collect())];
+            let inputs = bdds.input_values(&values);
+            return Equivalence::Different(Counterexample { inputs, outputs });
+        }
+        Equivalence::Equivalent
+    }
+
+    // all inputs of a divider for which Valid is set but Q is not R_0 / D, so Q * D > R_0 or
+    // Q * D + D <= R_0
+    pub fn wrong_quotients(&self, order: VariableOrder) -> WrongQuotients {
+        let mut bdds = self.bdds(order);
+        let outputs: Vec<usize> = bdds.outputs.iter().flat_map(|(_, bits)| bits.iter().copied()).collect();
+        let nodes = bdds.bdd.size(&outputs);
+        let valid = bdds.output("Valid")[0];
+        let (q, r_0, d) = (bdds.output("Q").to_vec(), bdds.input("R_0").to_vec(), bdds.input("D").to_vec());
+        let bdd = &mut bdds.bdd;
+        let width = (q.len() + d.len()).max(r_0.len()) + 1;
+        let extend = |word: &[usize]| (0..width).map(|i| word.get(i).copied().unwrap_or(FALSE)).collect::<Vec<usize>>();
+        let (q, r_0, d) = (extend(&q), extend(&r_0), extend(&d));
+        let product = bdd.mul(&q, &d);
+        let next = bdd.add(&product, &d);
+        let too_large = bdd.less(&r_0, &product);
+        let too_small = bdd.less(&r_0, &next);
+        let too_small = bdd.not(too_small);
+        let wrong = bdd.or(too_large, too_small);
+        let wrong = bdd.and(valid, wrong);
+        WrongQuotients {
+            valid: bdd.sat_count(valid),
+            wrong: bdd.sat_count(wrong),
+            example: bdd.satisfying(wrong).map(|values| bdds.input_values(&values)),
+            nodes,
+        }
+    }
+}
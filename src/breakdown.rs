@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::cost::GateCosts;
+use crate::data::{Bit, Circuit, Gate};
+
+// gates, area and critical path share of one component including all of its subcomponents,
+// gates counts the not, and, or and xor gates in this order, critical_depth the gates of the
+// critical path inside the component and critical_delay their delay
+#[derive(Debug, PartialEq, Clone)]
+pub struct ComponentStats {
+    pub name: String,
+    pub nesting: usize,
+    pub gates: [usize; 4],
+    pub area: f64,
+    pub critical_depth: usize,
+    pub critical_delay: usize,
+}
+
+impl ComponentStats {
+    pub fn gate_count(&self) -> usize {
+        self.gates.iter().sum()
+    }
+}
+
+// the components of a circuit in the order they got built, every component is followed
+// by its subcomponents, the first entry is the whole circuit
+#[derive(Debug, PartialEq, Clone)]
+pub struct Breakdown {
+    pub components: Vec<ComponentStats>,
+}
+
+impl fmt::Display for Breakdown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self
+            .components
+            .iter()
+            .map(|c| 2 * c.nesting + c.name.len())
+            .max()
+            .unwrap_or(0)
+            .max(9);
+        writeln!(
+            f,
+            "{:width$} {:>8} {:>8} {:>8} {:>8} {:>8} {:>10} {:>8} {:>8}",
+            "component", "gates", "not", "and", "or", "xor", "area", "depth", "delay"
+        )?;
+        for c in &self.components {
+            let name = format!("{}{}", "  ".repeat(c.nesting), c.name);
+            let [not, and, or, xor] = c.gates;
+            writeln!(
+                f,
+                "{name:width$} {:>8} {not:>8} {and:>8} {or:>8} {xor:>8} {:>10.2} {:>8} {:>8}",
+                c.gate_count(),
+                c.area,
+                c.critical_depth,
+                c.critical_delay
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Circuit {
+    // the gates along the slowest path ending in an output or a register in the order
+    // the signal passes them, as indices into wires
+    pub fn critical_path(&self, costs: &GateCosts) -> Vec<usize> {
+        let wire_of_line: HashMap<usize, usize> = self.wires.iter().enumerate().map(|(idx, w)| (w.out.n, idx)).collect();
+        let arrival = self.arrival_times(costs);
+        let delays = self.gate_delays(costs);
+        let ends = self.registers.iter().map(|r| r.d).chain(self.outputs.iter().flat_map(|o| o.bits.iter().copied()));
+        let end = ends
+            .filter_map(|bit| match bit {
+                Bit::Var(l) => wire_of_line.get(&l.n).copied(),
+                _ => None,
+            })
+            .max_by_key(|&idx| arrival[&self.wires[idx].out.n]);
+
+        let mut path = Vec::new();
+        let mut current = end;
+        while let Some(idx) = current {
+            path.push(idx);
+            let start = arrival[&self.wires[idx].out.n] - delays[idx];
+            current = self.wires[idx]
+                .gate
+                .inputs()
+                .iter()
+                .filter_map(|l| wire_of_line.get(&l.n).copied())
+                .find(|&input| arrival[&self.wires[input].out.n] == start);
+        }
+        path.reverse();
+        path
+    }
+
+    // gates and area by component and gate type and the gates and delay every component
+    // contributes to the critical path
+    pub fn breakdown(&self, costs: &GateCosts) -> Breakdown {
+        let scope_count = self.scopes.len();
+        let mut gates = vec![[0; 4]; scope_count];
+        let mut area = vec![0.0; scope_count];
+        for wire in &self.wires {
+            area[wire.scope] += costs.area(&wire.gate);
+            let kind = match wire.gate {
+                Gate::Not(_) => 0,
+                Gate::And(_, _) => 1,
+                Gate::Or(_, _) => 2,
+                Gate::Xor(_, _) => 3,
+            };
+            gates[wire.scope][kind] += 1;
+        }
+        let mut critical_depth = vec![0; scope_count];
+        let mut critical_delay = vec![0; scope_count];
+        let delays = self.gate_delays(costs);
+        for idx in self.critical_path(costs) {
+            critical_depth[self.wires[idx].scope] += 1;
+            critical_delay[self.wires[idx].scope] += delays[idx];
+        }
+        // children have higher indices than their parents
+        for scope in (1..scope_count).rev() {
+            let parent = self.scopes[scope].parent;
+            let scope_gates = gates[scope];
+            for (count, child_count) in gates[parent].iter_mut().zip(scope_gates) {
+                *count += child_count;
+            }
+            area[parent] += area[scope];
+            critical_depth[parent] += critical_depth[scope];
+            critical_delay[parent] += critical_delay[scope];
+        }
+        // registers are not part of any component
+        area[0] += costs.register_area * self.registers.len() as f64;
+
+        let (children, names) = self.component_tree();
+        let mut components = Vec::new();
+        let mut stack = vec![(0, 0)];
+        while let Some((scope, nesting)) = stack.pop() {
+            components.push(ComponentStats {
+                name: names[scope].clone(),
+                nesting,
+                gates: gates[scope],
+                area: area[scope],
+                critical_depth: critical_depth[scope],
+                critical_delay: critical_delay[scope],
+            });
+            for &child in children[scope].iter().rev() {
+                stack.push((child, nesting + 1));
+            }
+        }
+        Breakdown { components }
+    }
+
+    // the full name of every component like newton_iteration[1]/dadda_mul/cra, the gates
+    // outside of all components belong to top
+    pub fn component_paths(&self) -> Vec<String> {
+        let (_, names) = self.component_tree();
+        let mut paths = vec![String::from("top")];
+        for (scope, name) in self.scopes.iter().zip(names).skip(1) {
+            paths.push(match scope.parent {
+                0 => name,
+                parent => format!("{}/{}", paths[parent], name),
+            });
+        }
+        paths
+    }
+
+    // the subcomponents containing gates of every component in the order they got built and
+    // the name of every component, components of the same name created by the same parent
+    // are numbered like the iterations of a newton divider
+    fn component_tree(&self) -> (Vec<Vec<usize>>, Vec<String>) {
+        let scope_count = self.scopes.len();
+        let mut first_wire = vec![usize::MAX; scope_count];
+        for (idx, wire) in self.wires.iter().enumerate() {
+            first_wire[wire.scope] = first_wire[wire.scope].min(idx);
+        }
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); scope_count];
+        for scope in (1..scope_count).rev() {
+            let parent = self.scopes[scope].parent;
+            first_wire[parent] = first_wire[parent].min(first_wire[scope]);
+            if first_wire[scope] != usize::MAX {
+                children[parent].push(scope);
+            }
+        }
+
+        let mut names: Vec<String> = self.scopes.iter().map(|s| s.name.to_string()).collect();
+        names[0] = String::from("total");
+        for scope_children in &mut children {
+            scope_children.sort_by_key(|&scope| first_wire[scope]);
+            let mut count: HashMap<&str, usize> = HashMap::new();
+            for &child in scope_children.iter() {
+                *count.entry(self.scopes[child].name).or_insert(0) += 1;
+            }
+            let mut numbered: HashMap<&str, usize> = HashMap::new();
+            for &child in scope_children.iter() {
+                let name = self.scopes[child].name;
+                if count[name] > 1 {
+                    let number = numbered.entry(name).or_insert(0);
+                    names[child] = format!("{name}[{number}]");
+                    *number += 1;
+                }
+            }
+        }
+        (children, names)
+    }
+}
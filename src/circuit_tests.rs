@@ -3,10 +3,19 @@ mod tests {
     use num::{BigInt, FromPrimitive, Signed, traits::Pow};
 
     use crate::{
-        data::{Bit, Bit::One, Bit::Zero, Circuit, Shift, Mul},
-        dividers::{DivInfo, Estimate, IntDivResult, SubMethod},
+        bdd::{Bdd, VariableOrder},
+        cost::{CostModel, GateCosts},
+        liberty::Library,
+        optimize::Pass,
+        data::{Bit, Bit::One, Bit::Zero, Circuit, Shift, Adder, Mul, Truncation},
+        dividers::{DivInfo, Estimate, IntDivResult, Method, SubMethod},
+        equivalence::Equivalence,
+        sat::{SatResult, Solver},
+        pipeline::Pipelining,
+        simulation::{pack, unpack, Simulation},
     };
     use rand::random;
+    use std::collections::{HashMap, VecDeque};
     
     const TEST_SIZE_SMALL: usize = 1_000;
     const TEST_SIZE_BIG: usize = 10_000;
@@ -352,6 +361,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn dadda_mul_add_carry() {
+        // 15 * 15 + 255 = 480 needs the bit n1 + n2 on top of the product
+        for (m1, m2, s) in [(15u64, 15u64, 255u64), (15, 15, 31), (9, 13, 200), (0, 7, 255), (15, 15, 0)] {
+            let mut circuit = Circuit::new();
+            let v1 = Bit::get_bits_vec_u64(m1)[..4].to_vec();
+            let v2 = Bit::get_bits_vec_u64(m2)[..4].to_vec();
+            let v3 = Bit::get_bits_vec_u64(s)[..8].to_vec();
+            let p_calc = circuit.mul_unsigned_clean(v1, v2, Some(v3), Adder::CRA);
+            assert_eq!(p_calc.len(), 9);
+            assert_eq!(Bit::get_number_u(&p_calc).unwrap(), (m1 * m2 + s) as usize);
+        }
+    }
+
     #[test]
     fn array_mul() {
         let mut circuit = Circuit::new();
@@ -414,6 +437,311 @@ mod tests {
         }
     }
 
+    #[test]
+    fn booth_mul_random_sizes() {
+        let mut circuit = Circuit::new();
+        let mut counter = 0;
+        while counter < TEST_SIZE_BIG {
+            let (s1, v1) = get_random_number_with_random_number_of_digits();
+            let (f1, v2) = get_random_number_with_random_number_of_digits();
+            let (f2, v3) = get_random_number_with_random_number_of_digits();
+
+            let (p, mul_overflow) = f1.overflowing_mul(f2);
+            let (r, skip) = s1.overflowing_add(p);
+            if skip || mul_overflow {
+                continue;
+            }
+
+            let s = if counter % 2 == 0 { Some(v1.clone()) } else { None };
+            let r = if counter % 2 == 0 { r } else { p };
+            let r_calc = circuit.booth_mul(v2.clone(), v3.clone(), s, crate::data::Adder::KSA);
+            let r_calc = match Bit::get_number_u128(&r_calc) {
+                Ok(r) => r,
+                Err(_) => { continue; }
+            };
+
+            assert_eq!(r, r_calc);
+            counter += 1;
+        }
+    }
+
+    #[test]
+    fn test_div_booth_fixed_size() {
+        let mut circuit = Circuit::new();
+        for d_num in 0..=u8::MAX {
+            for z_num in 1..=u8::MAX {
+                let d = Bit::get_bits_vec_u8(d_num);
+                let z = Bit::get_bits_vec_u8(z_num);
+
+                let mut info = DivInfo::default_newton();
+                info.estimator = Estimate::Flip5bit;
+                info.defaultmult = Mul::Booth;
+                let result = circuit.div_newton(d, z, info);
+                let q_calc = Bit::get_number_u(&result.q).unwrap();
+                let r_calc = Bit::get_number_u(&result.r).unwrap();
+
+                assert_eq!(q_calc, (d_num / z_num) as usize);
+                assert_eq!(r_calc, (d_num % z_num) as usize);
+            }
+        }
+    }
+
+    #[test]
+    fn karatsuba_mul_random_sizes() {
+        let mut circuit = Circuit::new();
+        circuit.info.karatsuba_threshold = 8;
+        let mut counter = 0;
+        while counter < TEST_SIZE_SMALL {
+            let (s1, v1) = get_random_number_with_random_number_of_digits();
+            let (f1, v2) = get_random_number_with_random_number_of_digits();
+            let (f2, v3) = get_random_number_with_random_number_of_digits();
+
+            let (p, mul_overflow) = f1.overflowing_mul(f2);
+            let (r, skip) = s1.overflowing_add(p);
+            if skip || mul_overflow {
+                continue;
+            }
+
+            circuit.info.karatsuba_leaf = if counter % 2 == 0 { Mul::DadaTree } else { Mul::Booth };
+            let r_calc = circuit.karatsuba_mul(v2.clone(), v3.clone(), Some(v1.clone()), crate::data::Adder::CRA);
+            let r_calc = match Bit::get_number_u128(&r_calc) {
+                Ok(r) => r,
+                Err(_) => { continue; }
+            };
+            assert_eq!(r, r_calc);
+
+            let (square, square_overflow) = f1.overflowing_mul(f1);
+            let square_calc = Mul::Karatsuba.square_u(&mut circuit, v2.clone(), 0, crate::data::Adder::KSA);
+            if let (false, Ok(square_calc)) = (square_overflow, Bit::get_number_u128(&square_calc)) {
+                assert_eq!(square, square_calc);
+            }
+            counter += 1;
+        }
+    }
+
+    #[test]
+    fn test_div_karatsuba_fixed_size() {
+        let mut circuit = Circuit::new();
+        circuit.info.karatsuba_threshold = 3;
+        for d_num in 0..=u8::MAX {
+            for z_num in 1..=u8::MAX {
+                let d = Bit::get_bits_vec_u8(d_num);
+                let z = Bit::get_bits_vec_u8(z_num);
+
+                let mut info = DivInfo::default_newton();
+                info.estimator = Estimate::Table10bit;
+                info.defaultmult = Mul::Karatsuba;
+                let result = circuit.div_newton(d, z, info);
+                let q_calc = Bit::get_number_u(&result.q).unwrap();
+                let r_calc = Bit::get_number_u(&result.r).unwrap();
+
+                assert_eq!(q_calc, (d_num / z_num) as usize);
+                assert_eq!(r_calc, (d_num % z_num) as usize);
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_mul_error_bound() {
+        let mut circuit = Circuit::new();
+        for i in 0..TEST_SIZE_SMALL {
+            let (f1, v1) = get_random_number_with_fixed_digits(rand::random::<usize>() % 60 + 1);
+            let (f2, v2) = get_random_number_with_fixed_digits(rand::random::<usize>() % 60 + 1);
+            let drop = rand::random::<usize>() % (v1.len() + v2.len());
+            let truncation = if i % 2 == 0 { Truncation::Constant } else { Truncation::Variable };
+
+            let p = f1 * f2;
+            let r_calc = circuit.truncated_mul(v1.clone(), v2.clone(), drop, truncation, crate::data::Adder::CRA);
+            assert_eq!(r_calc.len(), v1.len() + v2.len() - drop);
+            let r_calc = Bit::get_number_u128(&r_calc).unwrap();
+            assert!(r_calc.abs_diff(p >> drop) <= 1, "{f1} * {f2} >> {drop}: {r_calc}");
+
+            let square = f1 * f1;
+            let drop = drop.min(2 * v1.len());
+            let r_calc = circuit.truncated_square(v1.clone(), 0, drop, truncation, crate::data::Adder::CRA);
+            assert_eq!(r_calc.len(), 2 * v1.len() - drop);
+            let r_calc = Bit::get_number_u128(&r_calc).unwrap();
+            assert!(r_calc.abs_diff(square >> drop) <= 1, "{f1}^2 >> {drop}: {r_calc}");
+        }
+    }
+
+    #[test]
+    fn truncated_mul_does_not_wrap() {
+        // operands of all ones give the largest products, the compensation must not carry them
+        // out of the width, and no gate is spent on saturating the result
+        let adder = crate::data::Adder::CRA;
+        for (n1, n2) in [(3, 3), (4, 6), (8, 8), (11, 8)] {
+            let f1 = (1u128 << n1) - 1;
+            let f2 = (1u128 << n2) - 1;
+            for drop in 0..(n1 + n2) {
+                for truncation in [Truncation::Constant, Truncation::Variable] {
+                    let mut circuit = Circuit::new();
+                    let r_calc = circuit.truncated_mul(Bit::get_bits_vec_u128(f1)[..n1].to_vec(), Bit::get_bits_vec_u128(f2)[..n2].to_vec(), drop, truncation, adder);
+                    let r_calc = Bit::get_number_u128(&r_calc).unwrap();
+                    assert!(r_calc.abs_diff((f1 * f2) >> drop) <= 1, "{f1} * {f2} >> {drop}: {r_calc}");
+                    let r_calc = circuit.truncated_square(Bit::get_bits_vec_u128(f1)[..n1].to_vec(), 0, drop.min(2 * n1), truncation, adder);
+                    let r_calc = Bit::get_number_u128(&r_calc).unwrap();
+                    assert!(r_calc.abs_diff((f1 * f1) >> drop.min(2 * n1)) <= 1, "{f1}^2 >> {drop}: {r_calc}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn squarers_random_sizes() {
+        let mut circuit = Circuit::new();
+        circuit.info.karatsuba_threshold = 8;
+        let muls = [Mul::Array, Mul::DadaTree, Mul::Booth, Mul::Karatsuba];
+        for i in 0..TEST_SIZE_SMALL {
+            let mul = muls[i % muls.len()];
+            let (f, v) = get_random_number_with_fixed_digits(random::<usize>() % 64 + 1);
+            let ignore = random::<usize>() % v.len();
+            let width = 2 * v.len() - ignore;
+
+            let square_calc = mul.square_u(&mut circuit, v.clone(), ignore, crate::data::Adder::CRA);
+            assert_eq!(square_calc.len(), width);
+            let square_calc = Bit::get_number_u128(&square_calc).unwrap();
+            let square = f.wrapping_mul(f) & 1_u128.checked_shl(width as u32).unwrap_or(0).wrapping_sub(1);
+            assert_eq!(square, square_calc);
+        }
+    }
+
+    #[test]
+    fn test_div_goldschmidt_squarers_random_size() {
+        let mut circuit = Circuit::new();
+        let muls = [Mul::Array, Mul::Booth, Mul::DadaTree];
+        let truncations = [Truncation::None, Truncation::Constant, Truncation::Variable];
+        for i in 0..TEST_SIZE_SMALL {
+            let (r0, v_r0) = get_random_number_with_random_number_of_digits();
+            let (d, v_d) = get_random_number_with_fixed_digits(v_r0.len());
+            if d == 0 {
+                continue;
+            }
+
+            let mut info = DivInfo::default_goldschmidt();
+            info.defaultmult = muls[i % muls.len()];
+            // only the dadda tree builds truncated squares
+            if info.defaultmult == Mul::DadaTree {
+                info.truncation = truncations[i / muls.len() % truncations.len()];
+            }
+            let result = circuit.goldschmidt_divider(v_r0, v_d, info);
+
+            assert_eq!(r0 / d, Bit::get_number_u128(&result.q).unwrap());
+            assert_eq!(r0 % d, Bit::get_number_u128(&result.r).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_div_truncated_fixed_size() {
+        let mut circuit = Circuit::new();
+        for truncation in [Truncation::Constant, Truncation::Variable] {
+            for d_num in 0..=u8::MAX {
+                for z_num in 1..=u8::MAX {
+                    let d = Bit::get_bits_vec_u8(d_num);
+                    let z = Bit::get_bits_vec_u8(z_num);
+
+                    let mut info = DivInfo::default_newton();
+                    info.estimator = Estimate::Flip5bit;
+                    info.truncation = truncation;
+                    let result = circuit.div_newton(d, z, info);
+                    let q_calc = Bit::get_number_u(&result.q).unwrap();
+                    let r_calc = Bit::get_number_u(&result.r).unwrap();
+
+                    assert_eq!(q_calc, (d_num / z_num) as usize);
+                    assert_eq!(r_calc, (d_num % z_num) as usize);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_div_truncated_random_size() {
+        let mut circuit = Circuit::new();
+        for i in 0..TEST_SIZE_SMALL {
+            let (r0, v_r0) = get_random_number_with_random_number_of_digits();
+            let (d, v_d) = get_random_number_with_fixed_digits(v_r0.len());
+            if d == 0 {
+                continue;
+            }
+
+            let mut info = DivInfo::default_newton();
+            info.estimator = if i % 2 == 0 { Estimate::Table10bit } else { Estimate::None };
+            info.truncation = if i % 3 == 0 { Truncation::Constant } else { Truncation::Variable };
+            let result = circuit.div_newton(v_r0, v_d, info);
+
+            let q_calc = Bit::get_number_u128(&result.q).unwrap();
+            let r_calc = Bit::get_number_u128(&result.r).unwrap();
+            assert_eq!(r0 / d, q_calc, "{r0} / {d}");
+            assert_eq!(r0 % d, r_calc, "{r0} % {d}");
+        }
+    }
+
+    #[test]
+    fn fused_family_random_sizes() {
+        let mut circuit = Circuit::new();
+        circuit.info.karatsuba_threshold = 8;
+        let muls = [Mul::Array, Mul::DadaTree, Mul::Booth, Mul::Karatsuba];
+        for i in 0..TEST_SIZE_SMALL {
+            let mul = muls[i % muls.len()];
+            let adder = if i % 2 == 0 { Adder::KSA } else { Adder::CRA };
+            let digits = || random::<usize>() % 30 + 1;
+            let (a, va) = get_random_number_with_fixed_digits(digits());
+            let (b, vb) = get_random_number_with_fixed_digits(digits());
+            let (c, vc) = get_random_number_with_fixed_digits(digits());
+            let (d, vd) = get_random_number_with_fixed_digits(digits());
+
+            let r_calc = circuit.fused_mul_add(va.clone(), vb.clone(), vc.clone(), mul, adder);
+            assert_eq!(a + b * c, Bit::get_number_u128(&r_calc).unwrap());
+
+            let r_calc = circuit.fused_dot_add(va.clone(), vb.clone(), vc.clone(), vd.clone(), mul, adder);
+            assert_eq!(a * b + c * d, Bit::get_number_u128(&r_calc).unwrap());
+
+            let width = va.len().max(vb.len() + vc.len());
+            let r_calc = circuit.fused_mul_sub(va.clone(), vb.clone(), vc.clone(), mul, adder);
+            assert_eq!(r_calc.len(), width);
+            assert_eq!(a.wrapping_sub(b * c) & ((1 << width) - 1), Bit::get_number_u128(&r_calc).unwrap());
+
+            let width = (va.len() + vb.len()).max(vc.len() + vd.len());
+            let r_calc = circuit.fused_dot_sub(va, vb, vc, vd, mul, adder);
+            assert_eq!(r_calc.len(), width);
+            assert_eq!((a * b).wrapping_sub(c * d) & ((1 << width) - 1), Bit::get_number_u128(&r_calc).unwrap());
+        }
+    }
+
+    #[test]
+    fn fused_family_merges_partial_products() {
+        // dadda and booth multipliers hand their partial products to the fused tree, array and
+        // karatsuba multipliers build the whole product first
+        for (mul, merged) in [(Mul::DadaTree, true), (Mul::Booth, true), (Mul::Array, false), (Mul::Karatsuba, false)] {
+            let mut circuit = Circuit::new();
+            circuit.info.karatsuba_threshold = 4;
+            let operands: Vec<Vec<Bit>> = (0..3).map(|_| (0..8).map(|_| circuit.new_line()).collect()).collect();
+            circuit.fused_mul_add(operands[0].clone(), operands[1].clone(), operands[2].clone(), mul, Adder::CRA);
+            assert_eq!(circuit.scopes.iter().all(|scope| !scope.name.ends_with("_mul")), merged, "{mul:?}");
+        }
+    }
+
+    #[test]
+    fn test_div_goldschmidt_fused_random_size() {
+        let mut circuit = Circuit::new();
+        let muls = [Mul::DadaTree, Mul::Booth];
+        for i in 0..TEST_SIZE_SMALL {
+            let (r0, v_r0) = get_random_number_with_random_number_of_digits();
+            let (d, v_d) = get_random_number_with_fixed_digits(v_r0.len());
+            if d == 0 {
+                continue;
+            }
+
+            let mut info = DivInfo::default_goldschmidt();
+            info.sub_method = SubMethod::Fused;
+            info.defaultmult = muls[i % muls.len()];
+            let result = circuit.goldschmidt_divider(v_r0, v_d, info);
+
+            assert_eq!(r0 / d, Bit::get_number_u128(&result.q).unwrap());
+            assert_eq!(r0 % d, Bit::get_number_u128(&result.r).unwrap());
+        }
+    }
+
     #[test]
     fn test_fused_multiply_subtract_u8() {
         let mut circuit = Circuit::new();
@@ -424,7 +752,7 @@ mod tests {
                     let v2 = Bit::get_bits_vec_u8(f1);
                     let v3 = Bit::get_bits_vec_u8(f2);
             
-                    let r_calc = circuit.fused_mul_subtraction(v1.clone(), v2.clone(), v3.clone(), false);
+                    let r_calc = circuit.fused_mul_subtraction(v1.clone(), v2.clone(), v3.clone(), false, Mul::DadaTree, Adder::KSA);
                     let r_calc = Bit::get_number_u(&r_calc).unwrap();
                     let r = minuend.overflowing_sub(f1 * f2).0;
             
@@ -446,7 +774,7 @@ mod tests {
         let v2 = Bit::get_bits_vec_u16(f1);
         let v3 = Bit::get_bits_vec_u16(f2);
 
-        let r_calc = circuit.fused_mul_subtraction(v1.clone(), v2.clone(), v3.clone(), false);
+        let r_calc = circuit.fused_mul_subtraction(v1.clone(), v2.clone(), v3.clone(), false, Mul::DadaTree, Adder::KSA);
         let r_calc = Bit::get_number_u(&r_calc).unwrap();
         let r = minuend.overflowing_sub(f1 * f2).0;
         println!("{minuend} - {f1} * {f2} = {r}");
@@ -470,7 +798,7 @@ mod tests {
         v2.truncate(63);
         v3.truncate(63);
 
-        let r_calc = circuit.fused_mul_subtraction(v1.clone(), v2.clone(), v3.clone(), false);
+        let r_calc = circuit.fused_mul_subtraction(v1.clone(), v2.clone(), v3.clone(), false, Mul::DadaTree, Adder::KSA);
         let r_calc = Bit::get_number_u128(&r_calc).unwrap();
         let r = minuend.overflowing_sub(f1 * f2).0;
         println!("{minuend} - {f1} * {f2} = {r}");
@@ -495,7 +823,7 @@ mod tests {
             }
            // println!("{minuend} - {f1} * {f2} = {r}");
 
-            let r_calc = circuit.fused_mul_subtraction(v1.clone(), v2.clone(), v3.clone(), false);
+            let r_calc = circuit.fused_mul_subtraction(v1.clone(), v2.clone(), v3.clone(), false, Mul::DadaTree, Adder::KSA);
             // println!("expected_length: {}bits || new_length: {}bits", v1.len().max(v2.len() + v3.len()), r_calc.len());
 
             // error seems to occur in translation! why?
@@ -1001,4 +1329,1294 @@ mod tests {
             println!("{bits} : depth: {}", circuit.stats.level_count);
         }
     }
+
+    fn get_divider_netlist(bits: usize) -> Circuit {
+        let mut info = DivInfo::default_newton();
+        info.estimator = Estimate::Flip5bit;
+        info.number_bits = bits;
+        let mut circuit = Circuit::get_divider_circuit(info);
+        circuit.remove_dead_ends();
+        circuit
+    }
+
+    fn get_random_operands(bits: usize) -> (Vec<u128>, Vec<u128>) {
+        let mask = (1_u128 << bits) - 1;
+        let dividends = (0..64).map(|_| random::<u128>() & mask).collect();
+        let divisors = (0..64).map(|_| (random::<u128>() & mask).max(1)).collect();
+        (dividends, divisors)
+    }
+
+    #[test]
+    fn simulate_divider_netlist() {
+        let bits = 12;
+        let circuit = get_divider_netlist(bits);
+        let mut sim = Simulation::new(&circuit);
+        for _ in 0..TEST_SIZE_SMALL / 64 {
+            let (dividends, divisors) = get_random_operands(bits);
+            sim.set_input("R_0", &pack(&dividends, bits));
+            sim.set_input("D", &pack(&divisors, bits));
+            sim.eval();
+
+            let q = unpack(&sim.output("Q"));
+            let r = unpack(&sim.output("R_n1"));
+            for i in 0..64 {
+                assert_eq!(q[i], dividends[i] / divisors[i]);
+                assert_eq!(r[i], dividends[i] % divisors[i]);
+            }
+            assert_eq!(sim.output("Valid"), vec![u64::MAX]);
+        }
+    }
+
+    // feeds a new batch of operands every cycle and expects every batch latency cycles later
+    fn check_pipelined_divider(circuit: &Circuit, bits: usize, latency: usize) {
+        let mut sim = Simulation::new(circuit);
+        sim.set_input("rst", &[u64::MAX]);
+        sim.eval();
+        sim.clock();
+        sim.set_input("rst", &[0]);
+
+        let mut batches = VecDeque::new();
+        for cycle in 0..(latency + 8) {
+            let (dividends, divisors) = get_random_operands(bits);
+            let in_valid: u64 = random();
+            sim.set_input("R_0", &pack(&dividends, bits));
+            sim.set_input("D", &pack(&divisors, bits));
+            sim.set_input("in_valid", &[in_valid]);
+            sim.eval();
+            batches.push_back((dividends, divisors, in_valid));
+
+            let out_valid = sim.output("out_valid")[0];
+            if cycle < latency {
+                assert_eq!(out_valid, 0);
+            } else {
+                let (dividends, divisors, in_valid) = batches.pop_front().unwrap();
+                assert_eq!(out_valid, in_valid);
+                let q = unpack(&sim.output("Q"));
+                let r = unpack(&sim.output("R_n1"));
+                for i in 0..64 {
+                    assert_eq!(q[i], dividends[i] / divisors[i]);
+                    assert_eq!(r[i], dividends[i] % divisors[i]);
+                }
+                assert_eq!(sim.output("Valid"), vec![u64::MAX]);
+            }
+            sim.clock();
+        }
+    }
+
+    // runs a few divisions through the start/busy/done handshake
+    fn check_iterative_divider(circuit: &Circuit, bits: usize) {
+        let mut sim = Simulation::new(circuit);
+        sim.set_input("rst", &[u64::MAX]);
+        sim.eval();
+        sim.clock();
+        sim.set_input("rst", &[0]);
+        sim.eval();
+        assert_eq!(sim.output("busy"), vec![0]);
+        assert_eq!(sim.output("done"), vec![0]);
+
+        let mut cycles = None;
+        for _ in 0..4 {
+            let (dividends, divisors) = get_random_operands(bits);
+            sim.set_input("R_0", &pack(&dividends, bits));
+            sim.set_input("D", &pack(&divisors, bits));
+            sim.set_input("start", &[u64::MAX]);
+            sim.eval();
+            sim.clock();
+
+            // a start while busy has to be ignored
+            let (other_dividends, other_divisors) = get_random_operands(bits);
+            sim.set_input("R_0", &pack(&other_dividends, bits));
+            sim.set_input("D", &pack(&other_divisors, bits));
+            let mut count = 0;
+            loop {
+                sim.eval();
+                if sim.output("done") == vec![u64::MAX] {
+                    break;
+                }
+                assert_eq!(sim.output("busy"), vec![u64::MAX]);
+                sim.clock();
+                count += 1;
+                assert!(count < 64, "divider never finished");
+            }
+            assert_eq!(*cycles.get_or_insert(count), count);
+            assert_eq!(sim.output("busy"), vec![0]);
+
+            sim.set_input("start", &[0]);
+            sim.eval();
+            sim.clock();
+            sim.eval();
+            assert_eq!(sim.output("done"), vec![u64::MAX]);
+            let q = unpack(&sim.output("Q"));
+            let r = unpack(&sim.output("R_n1"));
+            for i in 0..64 {
+                assert_eq!(q[i], dividends[i] / divisors[i]);
+                assert_eq!(r[i], dividends[i] % divisors[i]);
+            }
+        }
+    }
+
+    fn get_iterative_divider_netlist(bits: usize, estimator: Estimate) -> Circuit {
+        let mut info = DivInfo::default_newton();
+        info.division_method = Method::NewtonIterative;
+        info.estimator = estimator;
+        info.number_bits = bits;
+        let mut circuit = Circuit::get_divider_circuit(info);
+        circuit.remove_dead_ends();
+        circuit
+    }
+
+    #[test]
+    fn pipelined_divider_stream() {
+        let bits = 10;
+        for pipelining in [Pipelining::Stages(1), Pipelining::Stages(5), Pipelining::Levels(7)] {
+            let mut circuit = get_divider_netlist(bits);
+            let depth = circuit.stats.level_count;
+            let latency = circuit.pipeline_by(pipelining);
+            if let Pipelining::Levels(levels) = pipelining {
+                assert!(circuit.stats.level_count <= levels);
+                assert_eq!(latency, (depth - 1) / levels);
+            }
+            // the pipeline must survive a second dead end removal
+            let registers = circuit.stats.register_count;
+            circuit.remove_dead_ends();
+            assert_eq!(registers, circuit.stats.register_count);
+            check_pipelined_divider(&circuit, bits, latency);
+        }
+    }
+
+    #[test]
+    fn iterative_divider_handshake() {
+        for estimator in [Estimate::Flip5bit, Estimate::None] {
+            let circuit = get_iterative_divider_netlist(12, estimator);
+            check_iterative_divider(&circuit, 12);
+        }
+    }
+
+    #[test]
+    fn retimed_dividers() {
+        let bits = 10;
+        // with fanout delays the loop of the iterative divider limits its period already
+        let per_gate = crate::cost::GateCosts { fanout_delay: 0, ..CostModel::Cmos.costs() };
+        for costs in [CostModel::Unit.costs(), per_gate] {
+            let mut circuit = get_divider_netlist(bits);
+            let latency = circuit.pipeline_by(Pipelining::Levels(9));
+            let report = circuit.retime(&costs);
+            assert!(report.period_after <= report.period_before);
+            assert_eq!(report.period_after, circuit.clock_period(&costs));
+            check_pipelined_divider(&circuit, bits, latency);
+
+            let mut circuit = get_iterative_divider_netlist(bits, Estimate::Flip5bit);
+            let report = circuit.retime(&costs);
+            assert!(report.period_after < report.period_before);
+            check_iterative_divider(&circuit, bits);
+        }
+
+        // a combinational circuit has nothing to move
+        let mut circuit = get_divider_netlist(bits);
+        let gates = circuit.stats.gatter_count;
+        let report = circuit.retime(&CostModel::Unit.costs());
+        assert_eq!(report.period_before, report.period_after);
+        assert_eq!((report.registers_after, circuit.stats.gatter_count), (0, gates));
+    }
+
+    // input width, output width, gate count and instantiations with their port widths
+    type ModuleSummary<'a> = (usize, usize, usize, Vec<(&'a str, usize, usize)>);
+
+    // gate count and number of instantiations of the flattened hierarchical netlist and
+    // the number of modules, the port widths of every instantiation are checked against
+    // the module they instantiate
+    fn count_hierarchical_gates(verilog: &str, module: &str) -> (usize, usize, usize) {
+        let mut modules: HashMap<&str, ModuleSummary> = HashMap::new();
+        let mut current = "";
+        for line in verilog.lines() {
+            let width = |line: &str| line.split(['[', ':']).nth(1).unwrap().parse::<usize>().unwrap() + 1;
+            if let Some(header) = line.strip_prefix("module ") {
+                current = header.split('(').next().unwrap();
+                modules.insert(current, (0, 0, 0, Vec::new()));
+            } else if line.starts_with("input [") && line.ends_with(" x;") {
+                modules.get_mut(current).unwrap().0 = width(line);
+            } else if line.starts_with("output [") && line.ends_with(" y;") {
+                modules.get_mut(current).unwrap().1 = width(line);
+            } else if line.starts_with("assign ") && line.contains(['~', '&', '|', '^']) {
+                modules.get_mut(current).unwrap().2 += 1;
+            } else if let Some((name, connections)) = line.split_once(" u") {
+                let bits = |port: &str| {
+                    connections
+                        .split_once(port)
+                        .map_or(0, |(_, bits)| bits.split('}').next().unwrap().split(", ").count())
+                };
+                modules.get_mut(current).unwrap().3.push((name, bits(".x({"), bits(".y({")));
+            }
+        }
+
+        fn expand(modules: &HashMap<&str, ModuleSummary>, module: &str) -> (usize, usize) {
+            let (_, _, gates, instances) = &modules[module];
+            let mut count = (*gates, instances.len());
+            for (name, x, y) in instances {
+                let (inputs, outputs, _, _) = modules.get(name).unwrap_or_else(|| panic!("module {name} is not defined"));
+                assert_eq!((inputs, outputs), (x, y), "ports of {name}");
+                let (gates, instances) = expand(modules, name);
+                count = (count.0 + gates, count.1 + instances);
+            }
+            count
+        }
+        let (gates, instances) = expand(&modules, module);
+        (gates, instances, modules.len())
+    }
+
+    #[test]
+    fn hierarchical_verilog() {
+        let mut info = DivInfo::default_goldschmidt();
+        info.number_bits = 12;
+        info.defaultadder = Adder::KSA;
+        let mut goldschmidt = Circuit::get_divider_circuit(info);
+        goldschmidt.remove_dead_ends();
+        let mut pipelined = get_divider_netlist(12);
+        pipelined.pipeline_by(Pipelining::Stages(4));
+        let mut iterative = get_iterative_divider_netlist(12, Estimate::Table10bit);
+        iterative.retime(&CostModel::Unit.costs());
+
+        for (idx, mut circuit) in [get_divider_netlist(16), goldschmidt, pipelined, iterative].into_iter().enumerate() {
+            let verilog = circuit.hierarchical_verilog("top");
+            let (gates, instances, modules) = count_hierarchical_gates(&verilog, "top");
+            assert_eq!(gates, circuit.stats.gatter_count);
+            assert!(modules > 1);
+            // the later newton iterations share one module
+            if idx == 0 {
+                assert!(modules - 1 < instances, "identical components are not shared");
+            }
+            // the top module keeps the ports and registers of the flat netlist
+            let flat = circuit.to_verilog("top");
+            let declarations = |verilog: &str| -> Vec<String> {
+                let top = verilog.split("endmodule").next().unwrap();
+                top.lines().filter(|l| l.starts_with("input") || l.starts_with("output") || l.starts_with("reg")).map(String::from).collect()
+            };
+            assert_eq!(declarations(&verilog), declarations(&flat));
+        }
+    }
+
+    #[test]
+    fn component_breakdown() {
+        let mut pipelined = get_divider_netlist(12);
+        pipelined.pipeline_by(Pipelining::Levels(20));
+        for circuit in [get_divider_netlist(16), pipelined] {
+            let path = circuit.critical_path(&CostModel::Unit.costs());
+            assert_eq!(path.len(), circuit.stats.level_count);
+            for pair in path.windows(2) {
+                let (from, to) = (circuit.wires[pair[0]].out, circuit.wires[pair[1]].gate);
+                assert!(to.inputs().contains(&from));
+            }
+
+            let breakdown = circuit.breakdown(&CostModel::Unit.costs());
+            let total = &breakdown.components[0];
+            assert_eq!((total.gate_count(), total.critical_depth), (circuit.stats.gatter_count, circuit.stats.level_count));
+            // the direct subcomponents of every component fit into it
+            for (idx, component) in breakdown.components.iter().enumerate() {
+                let children: Vec<_> = breakdown.components[idx + 1..]
+                    .iter()
+                    .take_while(|c| c.nesting > component.nesting)
+                    .filter(|c| c.nesting == component.nesting + 1)
+                    .collect();
+                assert!(children.iter().map(|c| c.gate_count()).sum::<usize>() <= component.gate_count());
+                assert!(children.iter().map(|c| c.critical_depth).sum::<usize>() <= component.critical_depth);
+                for kind in 0..4 {
+                    assert!(children.iter().map(|c| c.gates[kind]).sum::<usize>() <= component.gates[kind]);
+                }
+            }
+            assert!(breakdown.components.iter().any(|c| c.name == "newton_iteration[1]" && c.nesting == 1));
+        }
+    }
+
+    #[test]
+    fn timing_paths() {
+        // every path of a small circuit in order
+        let mut circuit = Circuit::new();
+        let (a, b, c) = (circuit.new_line(), circuit.new_line(), circuit.new_line());
+        let and = circuit.and(a, b);
+        let xor = circuit.xor(and, c);
+        circuit.add_as_io(&vec![a, b, c], "x", false);
+        circuit.add_as_io(&vec![xor, One], "y", true);
+        circuit.update_stats();
+        let report = circuit.timing_report(10, &CostModel::Unit.costs());
+        let paths: Vec<(&str, &str, usize)> = report.paths.iter().map(|p| (p.start.as_str(), p.end.as_str(), p.depth())).collect();
+        assert_eq!(paths.len(), 3);
+        assert!(paths.contains(&("x[0]", "y[0]", 2)) && paths.contains(&("x[1]", "y[0]", 2)));
+        assert_eq!(paths[2], ("x[2]", "y[0]", 1));
+        assert_eq!(report.arrivals, vec![(String::from("y"), vec![Some(2), None])]);
+        let json = report.to_json();
+        assert!(json.contains("\"y\": [2, null]") && json.contains("\"gate\": \"xor\""));
+
+        let mut pipelined = get_divider_netlist(12);
+        pipelined.pipeline_by(Pipelining::Stages(3));
+        for circuit in [get_divider_netlist(16), pipelined] {
+            let report = circuit.timing_report(20, &CostModel::Unit.costs());
+            assert_eq!(report.paths.len(), 20);
+            assert_eq!(report.paths[0].depth(), circuit.stats.level_count);
+            for (idx, path) in report.paths.iter().enumerate() {
+                assert!(idx == 0 || report.paths[idx - 1].depth() >= path.depth());
+                assert!(path.gates.iter().enumerate().all(|(depth, gate)| gate.depth == depth + 1));
+                // no path is found twice
+                let lines: Vec<&String> = path.gates.iter().map(|g| &g.line).collect();
+                assert!(report.paths[..idx].iter().all(|p| p.start != path.start || p.gates.iter().map(|g| &g.line).ne(lines.iter().copied())));
+            }
+            assert!(report.paths[0].gates.iter().any(|g| g.component.starts_with("newton_iteration")));
+            for ((name, arrivals), output) in report.arrivals.iter().zip(&circuit.outputs) {
+                assert_eq!(name, &output.name);
+                assert!(arrivals.iter().flatten().all(|&a| a <= circuit.stats.level_count));
+            }
+        }
+    }
+
+    #[test]
+    fn cost_models() {
+        // a not driving three gates pays for two additional loads
+        let mut circuit = Circuit::new();
+        let (a, b) = (circuit.new_line(), circuit.new_line());
+        let not = circuit.not(a);
+        let and = circuit.and(not, b);
+        let or = circuit.or(not, b);
+        let xor = circuit.xor(not, and);
+        circuit.add_as_io(&vec![a, b], "x", false);
+        circuit.add_as_io(&vec![and, or, xor], "y", true);
+        let cmos = CostModel::Cmos.costs();
+        let cost = circuit.cost(&cmos);
+        assert_eq!(cost.delay, cmos.not_delay + 2 * cmos.fanout_delay + cmos.and_delay + cmos.fanout_delay + cmos.xor_delay);
+        assert!((cost.area - (cmos.not_area + cmos.and_area + cmos.or_area + cmos.xor_area)).abs() < 1e-9);
+
+        let mut pipelined = get_divider_netlist(12);
+        pipelined.pipeline_by(Pipelining::Stages(3));
+        for circuit in [get_divider_netlist(12), pipelined] {
+            let unit = circuit.cost(&CostModel::Unit.costs());
+            assert_eq!(unit.delay, circuit.stats.level_count);
+            assert_eq!(unit.area as usize, circuit.stats.gatter_count + circuit.stats.register_count);
+            for model in [CostModel::Cmos, CostModel::Fpga] {
+                let costs = model.costs();
+                let cost = circuit.cost(&costs);
+                let delays = circuit.gate_delays(&costs);
+                let path = circuit.critical_path(&costs);
+                assert_eq!(path.iter().map(|&idx| delays[idx]).sum::<usize>(), cost.delay);
+                assert_eq!(circuit.timing_report(1, &costs).paths[0].delay(), cost.delay);
+                let breakdown = circuit.breakdown(&costs);
+                assert_eq!(breakdown.components[0].critical_delay, cost.delay);
+                assert!((breakdown.components[0].area - cost.area).abs() < 1e-6);
+            }
+        }
+
+        // a cost file sets the values it names and keeps the others of the preset
+        let costs = CostModel::Unit.costs().parse("# slow xors\nxor_area = 2.5\n  xor_delay=3 # ps\n\nfanout_delay = 1\n").unwrap();
+        assert_eq!(costs, GateCosts { xor_area: 2.5, xor_delay: 3, fanout_delay: 1, ..CostModel::Unit.costs() });
+        let cost = circuit.cost(&costs);
+        assert_eq!(cost.delay, 1 + 2 + 1 + 1 + 3);
+        assert!((cost.area - 5.5).abs() < 1e-9);
+        for malformed in ["xor_area 2", "nand_area = 1", "and_delay = 1.5", "or_area = -1", "not_area = inf"] {
+            assert_eq!(CostModel::Cmos.costs().parse(malformed).unwrap_err().kind(), std::io::ErrorKind::InvalidData, "{malformed}");
+        }
+
+        let mut info = DivInfo::default_newton();
+        info.number_bits = 8;
+        let space = Circuit::design_space(info, &CostModel::Unit.costs());
+        assert_eq!(space.points.len(), 12);
+        info.defaultadder = Adder::KSA;
+        info.defaultmult = Mul::Booth;
+        let mut circuit = Circuit::get_divider_circuit(info);
+        circuit.remove_dead_ends();
+        let point = space.points.iter().find(|p| p.adder == Adder::KSA && p.multiplier == Mul::Booth).unwrap();
+        assert_eq!((point.gates, point.levels), (circuit.stats.gatter_count, circuit.stats.level_count));
+
+        // a truncating divider is only built with the dadda tree
+        info.truncation = Truncation::Constant;
+        let space = Circuit::design_space(info, &CostModel::Unit.costs());
+        assert_eq!(space.points.len(), 3);
+        assert!(space.points.iter().all(|p| p.multiplier == Mul::DadaTree));
+    }
+
+    // bit parallel evaluation of a truth table, inputs[i] holds 64 patterns of leaf i
+    fn eval_truth_table(truth: u64, inputs: &[u64]) -> u64 {
+        let mut r = 0;
+        for m in 0..(1_usize << inputs.len()) {
+            if (truth >> m) & 0x1 == 1 {
+                r |= inputs
+                    .iter()
+                    .enumerate()
+                    .fold(u64::MAX, |term, (i, &v)| if (m >> i) & 0x1 == 1 { term & v } else { term & !v });
+            }
+        }
+        r
+    }
+
+    // simulates the nodes of a mapped netlist given as (inputs, output, truth table) in
+    // topological order on random operands and compares them with every output and register input
+    fn assert_mapping_equivalent(circuit: &Circuit, nodes: &[(Vec<usize>, usize, u64)]) {
+        let bits = circuit.inputs.iter().find(|io| io.name == "D").unwrap().bits.len();
+        let (dividends, divisors) = get_random_operands(bits);
+        let mut simulation = Simulation::new(circuit);
+        simulation.set_input("R_0", &pack(&dividends, bits));
+        simulation.set_input("D", &pack(&divisors, bits));
+        simulation.eval();
+        let mut values: HashMap<usize, u64> = HashMap::new();
+        for input in circuit.inputs.iter().flat_map(|io| io.bits.iter()) {
+            if let Bit::Var(l) = *input {
+                values.insert(l.n, simulation.value(Bit::Var(l)));
+            }
+        }
+        for register in &circuit.registers {
+            values.insert(register.q.n, simulation.value(Bit::Var(register.q)));
+        }
+        for (inputs, output, truth) in nodes {
+            let inputs: Vec<u64> = inputs.iter().map(|n| values[n]).collect();
+            values.insert(*output, eval_truth_table(*truth, &inputs));
+        }
+        for bit in circuit.outputs.iter().flat_map(|io| io.bits.iter()).chain(circuit.registers.iter().map(|r| &r.d)) {
+            if let Bit::Var(l) = *bit {
+                assert_eq!(values[&l.n], simulation.value(*bit));
+            }
+        }
+    }
+
+    // a small library in both the linear and the table delay style
+    const TEST_LIBERTY: &str = r#"
+        /* test library */
+        library(test_cells) {
+            time_unit : "1ns";
+            cell(INV) {
+                area : 0.67;
+                pin(A) { direction : input; }
+                pin(Y) { direction : output; function : "!A";
+                    timing() { related_pin : "A"; intrinsic_rise : 0.02; intrinsic_fall : 0.015; } }
+            }
+            cell(BUF) { area : 1.0; pin(A) { direction : input; } pin(Y) { direction : output; function : "A"; } }
+            cell(NAND2) {
+                area : 1.0;
+                pin(A, B) { direction : input; }
+                pin(Y) { direction : output; function : "(A B)'";
+                    timing() { related_pin : "A";
+                        cell_rise(delay_2x2) { index_1("0.1, 0.5"); values("0.02, 0.04", \
+                                                                           "0.03, 0.05"); }
+                        cell_fall(delay_2x2) { values("0.02, 0.03", "0.03, 0.04"); } } }
+            }
+            cell(NOR2) { area : 1.0; pin(A) { direction : input; } pin(B) { direction : input; }
+                pin(Y) { direction : output; function : "!(A | B)"; } }
+            cell(AND2) { area : 1.33; pin(A) { direction : input; } pin(B) { direction : input; }
+                pin(Y) { direction : output; function : "A * B"; } }
+            cell(OR2) { area : 1.33; pin(A) { direction : input; } pin(B) { direction : input; }
+                pin(Y) { direction : output; function : "A + B"; } }
+            cell(XOR2) { area : 2.33; pin(A) { direction : input; } pin(B) { direction : input; }
+                pin(Y) { direction : output; function : "A ^ B"; } }
+            cell(XNOR2) { area : 2.33; pin(A) { direction : input; } pin(B) { direction : input; }
+                pin(Y) { direction : output; function : "!(A ^ B)"; } }
+            cell(AOI21) { area : 1.33; pin(A1) { direction : input; } pin(A2) { direction : input; }
+                pin(B) { direction : input; } pin(Y) { direction : output; function : "!((A1 & A2) | B)"; } }
+            cell(OAI21) { area : 1.33; pin(A1) { direction : input; } pin(A2) { direction : input; }
+                pin(B) { direction : input; } pin(Y) { direction : output; function : "!((A1 | A2) & B)"; } }
+            cell(MUX2) { area : 2.33; pin(A) { direction : input; } pin(B) { direction : input; }
+                pin(S) { direction : input; } pin(Y) { direction : output; function : "(A & !S) | (B & S)"; } }
+            cell(FA_CARRY) { area : 3.0; dont_use : true; pin(A) { direction : input; } pin(B) { direction : input; }
+                pin(C) { direction : input; } pin(Y) { direction : output; function : "A B + A C + B C"; } }
+            cell(DFF) { area : 4.67; ff(IQ, IQN) { next_state : "D"; clocked_on : "CK"; }
+                pin(D) { direction : input; } pin(CK) { direction : input; } pin(Q) { direction : output; function : "IQ"; } }
+        }
+    "#;
+
+    #[test]
+    fn liberty_mapping() {
+        let library = Library::parse(TEST_LIBERTY).unwrap();
+        assert_eq!(library.name, "test_cells");
+        let names: Vec<&str> = library.cells.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["INV", "BUF", "NAND2", "NOR2", "AND2", "OR2", "XOR2", "XNOR2", "AOI21", "OAI21", "MUX2"]);
+        let cell = |name: &str| library.cells.iter().find(|c| c.name == name).unwrap();
+        assert_eq!((cell("NAND2").truth, cell("NAND2").delay), (0b0111, 0.03));
+        assert_eq!((cell("INV").truth, cell("INV").delay), (0b01, 0.02));
+        assert_eq!(cell("AOI21").truth, 0b0000_0111);
+        assert_eq!(cell("MUX2").truth, 0b1100_1010);
+
+        let mut pipelined = get_divider_netlist(10);
+        pipelined.pipeline_by(Pipelining::Stages(2));
+        for mut circuit in [get_divider_netlist(10), pipelined] {
+            let netlist = circuit.map_to_library(&library).unwrap();
+            // a cell for every gate would be needed without covering several gates at once
+            let gate_area = circuit.area(&CostModel::Cmos.costs());
+            assert!(netlist.area < gate_area);
+            assert!(netlist.cells.iter().any(|c| library.cells[c.cell].inputs.len() == 3));
+
+            let nodes: Vec<(Vec<usize>, usize, u64)> = netlist
+                .cells
+                .iter()
+                .map(|c| (c.inputs.clone(), c.output, library.cells[c.cell].truth))
+                .collect();
+            assert_mapping_equivalent(&circuit, &nodes);
+
+            let verilog = circuit.mapped_verilog(&netlist, &library, "top");
+            assert_eq!(verilog.lines().filter(|l| l.contains(" u") && l.contains(".Y(")).count(), netlist.cells.len());
+            assert!(!verilog.contains("assign _"));
+        }
+    }
+
+    #[test]
+    fn liberty_mapping_without_matching_cells() {
+        // without XOR2, XNOR2, BUF and larger cells the unmatched gates fall back to NAND2 and
+        // INV cells, without INV to NAND2 cells alone
+        let nand = r#"cell(NAND2) { area : 1.0; pin(A) { direction : input; } pin(B) { direction : input; }
+            pin(Y) { direction : output; function : "!(A & B)"; } }"#;
+        let inv = r#"cell(INV) { area : 0.67; pin(A) { direction : input; } pin(Y) { direction : output; function : "!A"; } }"#;
+        let and = r#"cell(AND2) { area : 1.33; pin(A) { direction : input; } pin(B) { direction : input; }
+            pin(Y) { direction : output; function : "A & B"; } }"#;
+        for cells in [format!("{nand} {inv} {and}"), nand.to_string()] {
+            let library = Library::parse(&format!("library(small) {{ {cells} }}")).unwrap();
+            let mut circuit = get_divider_netlist(8);
+            let netlist = circuit.map_to_library(&library).unwrap();
+            let nodes: Vec<(Vec<usize>, usize, u64)> = netlist
+                .cells
+                .iter()
+                .map(|c| (c.inputs.clone(), c.output, library.cells[c.cell].truth))
+                .collect();
+            assert_mapping_equivalent(&circuit, &nodes);
+            let verilog = circuit.mapped_verilog(&netlist, &library, "top");
+            assert_eq!(verilog.lines().filter(|l| l.contains(" u") && l.contains(".Y(")).count(), netlist.cells.len());
+        }
+
+        let library = Library::parse(&format!("library(small) {{ {and} }}")).unwrap();
+        let error = get_divider_netlist(8).map_to_library(&library).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+        for malformed in [
+            "cell(INV) { }",
+            "library(small) { cell(INV) { area : 1.0; ",
+            "library(small) { cell(INV) { area 1.0; } }",
+            r#"library(small) { cell(INV) { pin(A) { direction : input; } pin(Y) { direction : output; function : "!(A"; } } }"#,
+            r#"library(small) { cell(INV) { pin(A) { direction : input; } pin(Y) { direction : output; function : "!B"; } } }"#,
+            r#"library(small) { cell(INV) { pin(A) { direction : input; } pin(Y) { direction : output; function : "A)"; } } }"#,
+        ] {
+            assert_eq!(Library::parse(malformed).unwrap_err().kind(), std::io::ErrorKind::InvalidData, "{malformed}");
+        }
+    }
+
+    #[test]
+    fn lut_mapping() {
+        let mut pipelined = get_divider_netlist(10);
+        pipelined.pipeline_by(Pipelining::Stages(3));
+        for circuit in [get_divider_netlist(10), pipelined] {
+            let mut counts = Vec::new();
+            for k in 2..=6 {
+                let netlist = circuit.map_to_luts(k);
+                assert!(netlist.luts.iter().all(|lut| lut.inputs.len() <= k));
+                // the area recovery keeps the depth of the depth optimal cover
+                let mut levels: HashMap<usize, usize> = HashMap::new();
+                for lut in &netlist.luts {
+                    let level = 1 + lut.inputs.iter().map(|n| levels.get(n).copied().unwrap_or(0)).max().unwrap_or(0);
+                    levels.insert(lut.output, level);
+                }
+                assert_eq!(levels.values().max().copied().unwrap_or(0), netlist.depth);
+                assert!(netlist.depth <= circuit.stats.level_count);
+                let nodes: Vec<(Vec<usize>, usize, u64)> = netlist.luts.iter().map(|l| (l.inputs.clone(), l.output, l.truth)).collect();
+                assert_mapping_equivalent(&circuit, &nodes);
+                counts.push((netlist.luts.len(), netlist.depth));
+
+                let blif = circuit.blif(&netlist, "top");
+                assert!(blif.starts_with(".model top\n.inputs R_0[0]"));
+                assert!(blif.ends_with(".end\n"));
+                assert_eq!(blif.matches(".latch ").count(), circuit.registers.len());
+                assert!(blif.matches(".names ").count() >= netlist.luts.len());
+            }
+            assert!(counts[4].0 < counts[0].0 && counts[4].1 < counts[0].1);
+            assert!(counts[4].0 < circuit.wires.len());
+        }
+    }
+
+    #[test]
+    fn optimization_passes() {
+        let mut circuit = Circuit::new();
+        let x: Vec<Bit> = (0..4).map(|_| circuit.new_line()).collect();
+        circuit.add_as_io(&x, "X", false);
+        let not_x0 = circuit.not(x[0]);
+        let zero = circuit.and(x[0], not_x0);
+        let not_x1 = circuit.not(x[1]);
+        let x1 = circuit.not(not_x1);
+        let x01 = circuit.and(x[0], x[1]);
+        let x10 = circuit.and(x[1], x[0]);
+        let mut chain = x[0];
+        for &bit in &x[1..] {
+            chain = circuit.xor(chain, bit);
+        }
+        circuit.add_as_io(&vec![zero, x1, x01, x10, chain], "Y", true);
+        circuit.update_stats();
+
+        let passes = [Pass::ConstProp, Pass::DoubleNot, Pass::Strash, Pass::Balance];
+        let report = circuit.optimize(&passes);
+        let stats: Vec<(usize, usize, usize, usize)> = report
+            .passes
+            .iter()
+            .map(|p| (p.gates_before, p.gates_after, p.depth_before, p.depth_after))
+            .collect();
+        assert_eq!(stats, [(9, 7, 3, 3), (7, 5, 3, 3), (5, 4, 3, 3), (4, 4, 3, 2)]);
+        assert_eq!(circuit.outputs[0].bits[0], Zero);
+        assert_eq!(circuit.outputs[0].bits[1], x[1]);
+        assert_eq!(circuit.outputs[0].bits[2], circuit.outputs[0].bits[3]);
+        assert!(report.to_string().contains("double-not"));
+
+        let all = [Pass::ConstProp, Pass::DoubleNot, Pass::Strash, Pass::Rewrite, Pass::Balance, Pass::Strash, Pass::Rewrite];
+        let bits = 10;
+        let mut circuit = get_divider_netlist(bits);
+        let (gates, depth) = (circuit.stats.gatter_count, circuit.stats.level_count);
+        circuit.optimize(&all);
+        assert!(circuit.stats.gatter_count < gates);
+        assert!(circuit.stats.level_count <= depth);
+        let mut sim = Simulation::new(&circuit);
+        for _ in 0..4 {
+            let (dividends, divisors) = get_random_operands(bits);
+            sim.set_input("R_0", &pack(&dividends, bits));
+            sim.set_input("D", &pack(&divisors, bits));
+            sim.eval();
+            let q = unpack(&sim.output("Q"));
+            let r = unpack(&sim.output("R_n1"));
+            for i in 0..64 {
+                assert_eq!(q[i], dividends[i] / divisors[i]);
+                assert_eq!(r[i], dividends[i] % divisors[i]);
+            }
+        }
+
+        let mut circuit = get_iterative_divider_netlist(bits, Estimate::Flip5bit);
+        let gates = circuit.stats.gatter_count;
+        circuit.optimize(&all);
+        assert!(circuit.stats.gatter_count < gates);
+        check_iterative_divider(&circuit, bits);
+    }
+
+    #[test]
+    fn tree_balancing() {
+        // a chain of and gates whose first input arrives late, and an xor tree with a leaf
+        // shared with another output that is only worth duplicating on the deepest path
+        let mut circuit = Circuit::new();
+        let x: Vec<Bit> = (0..8).map(|_| circuit.new_line()).collect();
+        circuit.add_as_io(&x, "X", false);
+        let mut late = circuit.xor(x[0], x[1]);
+        for &bit in &x[2..4] {
+            late = circuit.or(late, bit);
+        }
+        let mut chain = late;
+        for &bit in &x[4..] {
+            chain = circuit.and(chain, bit);
+        }
+        let shared = circuit.xor(x[4], x[5]);
+        let parity = circuit.xor(late, shared);
+        circuit.add_as_io(&vec![chain, parity, shared], "Y", true);
+        circuit.update_stats();
+        let (gates, depth) = (circuit.stats.gatter_count, circuit.stats.level_count);
+        assert_eq!(depth, 7);
+
+        let report = circuit.optimize(&[Pass::Balance]);
+        assert_eq!(report.passes[0].depth_after, circuit.stats.level_count);
+        assert_eq!(circuit.stats.level_count, 3);
+        assert_eq!(circuit.stats.gatter_count, gates);
+        let mut sim = Simulation::new(&circuit);
+        for half in 0..4_u128 {
+            let patterns: Vec<u128> = (0..64).map(|i| half * 64 + i).collect();
+            sim.set_input("X", &pack(&patterns, 8));
+            sim.eval();
+            let y = unpack(&sim.output("Y"));
+            for (&m, &y) in patterns.iter().zip(&y) {
+                let x = |i: usize| (m >> i) & 0x1;
+                let late = (x(0) ^ x(1)) | x(2) | x(3);
+                let shared = x(4) ^ x(5);
+                let chain = late & x(4) & x(5) & x(6) & x(7);
+                assert_eq!(y, chain | ((late ^ shared) << 1) | (shared << 2));
+            }
+        }
+
+        let bits = 10;
+        let mut circuit = get_divider_netlist(bits);
+        let (gates, depth) = (circuit.stats.gatter_count, circuit.stats.level_count);
+        circuit.optimize(&[Pass::Balance]);
+        assert!(circuit.stats.level_count < depth);
+        assert!(circuit.stats.gatter_count <= gates + gates / 20);
+        let mut sim = Simulation::new(&circuit);
+        for _ in 0..4 {
+            let (dividends, divisors) = get_random_operands(bits);
+            sim.set_input("R_0", &pack(&dividends, bits));
+            sim.set_input("D", &pack(&divisors, bits));
+            sim.eval();
+            let q = unpack(&sim.output("Q"));
+            let r = unpack(&sim.output("R_n1"));
+            for i in 0..64 {
+                assert_eq!(q[i], dividends[i] / divisors[i]);
+                assert_eq!(r[i], dividends[i] % divisors[i]);
+            }
+        }
+
+        let mut circuit = get_iterative_divider_netlist(bits, Estimate::Flip5bit);
+        let depth = circuit.stats.level_count;
+        circuit.optimize(&[Pass::Balance]);
+        assert!(circuit.stats.level_count <= depth);
+        check_iterative_divider(&circuit, bits);
+    }
+
+    #[test]
+    fn equivalence_checking() {
+        // y0 = x0 & x1 in both, y1 of the second circuit is wrong if x1 and x2 are set
+        let build = |wrong: bool| {
+            let mut circuit = Circuit::new();
+            let x: Vec<Bit> = (0..3).map(|_| circuit.new_line()).collect();
+            circuit.add_as_io(&x, "X", false);
+            let y0 = if wrong {
+                let (n0, n1) = (circuit.not(x[0]), circuit.not(x[1]));
+                let n = circuit.or(n0, n1);
+                circuit.not(n)
+            } else {
+                circuit.and(x[0], x[1])
+            };
+            let mut y1 = circuit.xor(x[0], x[2]);
+            if wrong {
+                let x12 = circuit.and(x[1], x[2]);
+                y1 = circuit.xor(y1, x12);
+            }
+            circuit.add_as_io(&vec![y0, y1], "Y", true);
+            circuit.update_stats();
+            circuit
+        };
+        let (first, second) = (build(false), build(true));
+        assert_eq!(first.check_equivalence(&build(false)), Equivalence::Equivalent);
+        let Equivalence::Different(cex) = first.check_equivalence(&second) else {
+            panic!("circuits differ");
+        };
+        let x = &cex.inputs[0].1;
+        assert!(x[1] && x[2]);
+        let (y0, y1) = (x[0] && x[1], x[0] ^ x[2]);
+        assert_eq!(cex.outputs, [("Y".to_string(), vec![y0, y1], vec![y0, !y1])]);
+        let miter = first.miter(&second);
+        let dimacs = miter.dimacs();
+        assert!(dimacs.starts_with("c input X "));
+        let header = format!("p cnf {} {}", miter.cnf.vars, miter.cnf.clauses.len() + 1);
+        assert!(dimacs.lines().any(|line| line == header));
+
+        let bits = 8;
+        let mut circuit = get_divider_netlist(bits);
+        circuit.optimize(&[Pass::ConstProp, Pass::DoubleNot, Pass::Strash, Pass::Rewrite, Pass::Balance]);
+        assert_eq!(circuit.check_equivalence(&get_divider_netlist(bits)), Equivalence::Equivalent);
+        let mut info = DivInfo::default_newton();
+        info.estimator = Estimate::Flip5bit;
+        info.number_bits = bits;
+        info.defaultadder = Adder::KSA;
+        let mut reference = Circuit::get_divider_circuit(info);
+        reference.remove_dead_ends();
+        assert_eq!(circuit.check_equivalence(&reference), Equivalence::Equivalent);
+    }
+
+    #[test]
+    fn divider_property_export() {
+        let bits = 8;
+        let mut circuit = get_divider_netlist(bits);
+        let solve = |circuit: &Circuit| {
+            let property = circuit.divider_property();
+            let mut solver = Solver::new();
+            solver.reserve(property.cnf.vars);
+            for clause in &property.cnf.clauses {
+                solver.add_clause(clause);
+            }
+            let result = solver.solve(&[], None);
+            let values: Vec<u128> = property
+                .inputs
+                .iter()
+                .filter(|_| result == SatResult::Sat)
+                .map(|(_, lits)| lits.iter().rev().fold(0, |v, l| v << 1 | (solver.model_value(l.var()) ^ l.is_negated()) as u128))
+                .collect();
+            (result, values, property.dimacs())
+        };
+        let (result, _, dimacs) = solve(&circuit);
+        assert_eq!(result, SatResult::Unsat);
+        assert!(dimacs.starts_with("c unsatisfiable if and only if Valid implies"));
+        assert!(dimacs.contains("\nc input D "));
+
+        // without the lowest quotient bit the property fails for every odd quotient
+        circuit.outputs.iter_mut().find(|io| io.name == "Q").unwrap().bits[0] = Zero;
+        let (result, values, _) = solve(&circuit);
+        assert_eq!(result, SatResult::Sat);
+        let (dividend, divisor) = (values[0], values[1]);
+        assert!(divisor != 0 && (dividend / divisor) % 2 == 1);
+
+        let smt2 = get_divider_netlist(bits).divider_property_smt2();
+        assert!(smt2.contains("(set-logic QF_BV)"));
+        assert!(smt2.contains("(declare-const R_0 (_ BitVec 8))"));
+        assert!(smt2.contains("(define-fun Valid () (_ BitVec 1) "));
+        assert!(smt2.ends_with("(check-sat)\n"));
+        assert_eq!(smt2.matches('(').count(), smt2.matches(')').count());
+    }
+
+    #[test]
+    fn bdd_analysis() {
+        let mut bdd = Bdd::new();
+        let x: Vec<usize> = (0..3).map(|_| bdd.new_var()).collect();
+        let x01 = bdd.and(x[0], x[1]);
+        let majority = bdd.or(x01, x[2]);
+        let parity = bdd.xor(x[0], x[2]);
+        assert_eq!(bdd.sat_count(majority), 5);
+        assert_eq!(bdd.sat_count(parity), 4);
+        let not_parity = bdd.not(parity);
+        let none = bdd.and(parity, not_parity);
+        assert_eq!(bdd.satisfying(none), None);
+        assert_eq!(bdd.size(&[majority, parity]), 5);
+
+        // the adders are equal, a carry in of one instead of zero is found
+        let build = |adder: Adder, c_in: Bit| {
+            let mut circuit = Circuit::new();
+            let a: Vec<Bit> = (0..8).map(|_| circuit.new_line()).collect();
+            let b: Vec<Bit> = (0..8).map(|_| circuit.new_line()).collect();
+            circuit.add_as_io(&a, "A", false);
+            circuit.add_as_io(&b, "B", false);
+            let sum = adder.add(&mut circuit, a, b, c_in);
+            circuit.add_as_io(&sum, "S", true);
+            circuit
+        };
+        let cra = build(Adder::CRA, Zero);
+        for adder in [Adder::CSA, Adder::KSA] {
+            assert_eq!(cra.check_equivalence_bdd(&build(adder, Zero), VariableOrder::Interleaved), Equivalence::Equivalent);
+        }
+        let Equivalence::Different(cex) = cra.check_equivalence_bdd(&build(Adder::KSA, One), VariableOrder::Declared) else {
+            panic!("carry in is ignored");
+        };
+        let value = |bits: &Vec<bool>| bits.iter().rev().fold(0_u128, |v, &b| v << 1 | b as u128);
+        let (a, b) = (value(&cex.inputs[0].1), value(&cex.inputs[1].1));
+        let (name, first, second) = &cex.outputs[0];
+        assert_eq!(name, "S");
+        let mask = (1 << first.len()) - 1;
+        assert_eq!((value(first), value(second)), ((a + b) & mask, (a + b + 1) & mask));
+
+        // exact count of the inputs with a wrong quotient against all inputs simulated
+        for bits in [6, 8] {
+            let circuit = get_divider_netlist(bits);
+            let mut sim = Simulation::new(&circuit);
+            let mut wrong = 0;
+            for dividend in 0..1_u128 << bits {
+                let divisors: Vec<u128> = (1..1_u128 << bits).collect();
+                for chunk in divisors.chunks(64) {
+                    let mut divisors = chunk.to_vec();
+                    divisors.resize(64, 1);
+                    sim.set_input("R_0", &pack(&vec![dividend; 64], bits));
+                    sim.set_input("D", &pack(&divisors, bits));
+                    sim.eval();
+                    let q = unpack(&sim.output("Q"));
+                    wrong += (0..chunk.len()).filter(|&i| q[i] != dividend / divisors[i]).count() as u128;
+                }
+            }
+            let interleaved = circuit.wrong_quotients(VariableOrder::Interleaved);
+            let declared = circuit.wrong_quotients(VariableOrder::Declared);
+            assert_eq!(interleaved.valid, (1 << bits) * ((1 << bits) - 1));
+            assert_eq!((interleaved.wrong, declared.wrong), (wrong, wrong));
+            assert!(interleaved.nodes < declared.nodes);
+            match &interleaved.example {
+                Some(inputs) => {
+                    let (dividend, divisor) = (value(&inputs[0].1), value(&inputs[1].1));
+                    sim.set_input("R_0", &pack(&vec![dividend; 64], bits));
+                    sim.set_input("D", &pack(&vec![divisor; 64], bits));
+                    sim.eval();
+                    assert_ne!(unpack(&sim.output("Q"))[0], dividend / divisor);
+                }
+                None => assert_eq!(wrong, 0),
+            }
+        }
+    }
+
+    #[test]
+    fn estimator_error_bounds() {
+        // the dividers iterate as often as the error bounds ask for, with the truncated
+        // products one more time at most
+        for estimator in [Estimate::Flip5bit, Estimate::Table10bit, Estimate::Linear, Estimate::None] {
+            for n in 1..=64 {
+                let bound = Circuit::estimator_error_bound(estimator, n);
+                assert!(bound.max_error < 1.0);
+                assert_eq!(bound.exact, n <= 16);
+                let mut info = DivInfo::default_newton();
+                info.estimator = estimator;
+                assert_eq!(Circuit::exact_iterations(&info, n), bound.iterations(0));
+                info.truncation = Truncation::Constant;
+                assert_eq!(Circuit::exact_iterations(&info, n), bound.iterations(1));
+                assert!(bound.iterations(1) <= bound.iterations(0) + 1, "{}", bound);
+            }
+        }
+
+        // without an estimate x = 1 is off by one half for d = 0.5, the error of the largest
+        // quotient 255 / 1 has to fall below 2^-8
+        let bound = Circuit::estimator_error_bound(Estimate::None, 8);
+        assert_eq!((bound.max_error, bound.worst_divisor), (0.5, 0x80));
+        assert_eq!(bound.iterations(0), 3);
+        assert_eq!(bound.iterations(1), 4);
+        let bound = Circuit::estimator_error_bound(Estimate::Flip5bit, 64);
+        assert!(bound.precise_bits() > 4.0);
+        assert_eq!(bound.iterations(0), 4);
+
+        // 4 iterations give x = 1 for d = 0.5 16 precise bits, 17 bits need the fifth, the
+        // closed form with n - 2 used before stopped at 4 and divided 131071 by 1 wrong
+        let bound = Circuit::estimator_error_bound(Estimate::None, 17);
+        assert_eq!(bound.iterations(0), 5);
+        let mut info = DivInfo::default_newton();
+        info.number_bits = 17;
+        info.dropped_iterations = 1;
+        let circuit = Circuit::get_divider_circuit(info);
+        let mut sim = Simulation::new(&circuit);
+        sim.set_input("R_0", &pack(&[131071, 131071], 17));
+        sim.set_input("D", &pack(&[1, 2], 17));
+        sim.eval();
+        assert_eq!(unpack(&sim.output("Q"))[..2], [131070, 65535]);
+
+        // the quotient of the 6 bit flip estimate of 9 is 5 for 63 / 9, two off, although the
+        // relative error is small enough, the correction step only fixes one
+        let bound = Circuit::estimator_error_bound(Estimate::Flip5bit, 6);
+        assert_eq!(bound.iterations(0), 1);
+        let mut info = DivInfo::default_newton();
+        info.estimator = Estimate::Flip5bit;
+        info.number_bits = 6;
+        info.dropped_iterations = 1;
+        let circuit = Circuit::get_divider_circuit(info);
+        let mut sim = Simulation::new(&circuit);
+        sim.set_input("R_0", &pack(&[63, 63], 6));
+        sim.set_input("D", &pack(&[9, 7], 6));
+        sim.eval();
+        assert_eq!(unpack(&sim.output("Q"))[..2], [6, 8]);
+
+        // with the iterations of the closed forms every estimate divides exactly, like the 5 bit
+        // divider without estimate with the third iteration the error bound asks for
+        for estimator in [Estimate::Flip5bit, Estimate::Table10bit, Estimate::Linear, Estimate::None] {
+            for truncation in [Truncation::None, Truncation::Constant, Truncation::Variable] {
+                for n in 2..=8 {
+                    let mut info = DivInfo::default_newton();
+                    info.estimator = estimator;
+                    info.truncation = truncation;
+                    info.number_bits = n;
+                    let circuit = Circuit::get_divider_circuit(info);
+                    let mut sim = Simulation::new(&circuit);
+                    let pairs: Vec<(u128, u128)> = (1..1 << n).flat_map(|divisor| (0..1 << n).map(move |dividend| (dividend, divisor))).collect();
+                    for chunk in pairs.chunks(64) {
+                        let (dividends, divisors): (Vec<u128>, Vec<u128>) = chunk.iter().copied().unzip();
+                        sim.set_input("R_0", &pack(&dividends, n));
+                        sim.set_input("D", &pack(&divisors, n));
+                        sim.eval();
+                        let (q, r) = (unpack(&sim.output("Q")), unpack(&sim.output("R_n1")));
+                        for (i, &(dividend, divisor)) in chunk.iter().enumerate() {
+                            assert_eq!((q[i], r[i]), (dividend / divisor, dividend % divisor), "{estimator:?} {truncation:?} {n} bits");
+                        }
+                    }
+                }
+            }
+        }
+
+        // dividers wider than 128 bits are bounded with the analyzed leading bits alone
+        for estimator in [Estimate::Flip5bit, Estimate::None] {
+            let bound = Circuit::estimator_error_bound(estimator, 200);
+            assert!(!bound.exact);
+            let mut info = DivInfo::default_newton();
+            info.estimator = estimator;
+            info.number_bits = 200;
+            assert_eq!(Circuit::exact_iterations(&info, 200), bound.iterations(0));
+            let circuit = Circuit::get_divider_circuit(info);
+            assert_eq!(circuit.outputs[0].bits.len(), 200);
+        }
+    }
+
+    #[test]
+    fn stuck_at_fault_simulation() {
+        // y = x0 | (x0 & x1) does not depend on x1, so neither x1 nor the and stuck at 0 can
+        // be detected
+        let mut circuit = Circuit::new();
+        let x: Vec<Bit> = (0..2).map(|_| circuit.new_line()).collect();
+        circuit.add_as_io(&x, "X", false);
+        let a = circuit.and(x[0], x[1]);
+        let y = circuit.or(x[0], a);
+        circuit.add_as_io(&vec![y], "Y", true);
+        circuit.update_stats();
+        assert_eq!(circuit.faults().len(), 8);
+        let coverage = circuit.fault_coverage(&circuit.parse_vectors("// X\n1\n\n2 // x0 = 0\n"));
+        assert_eq!((coverage.vectors, coverage.faults, coverage.detected, coverage.useful_vectors), (2, 8, 5, 2));
+        let exhaustive: Vec<Vec<u128>> = (0..4).map(|x| vec![x]).collect();
+        let coverage = circuit.fault_coverage(&exhaustive);
+        assert_eq!(coverage.detected, 5);
+        assert_eq!(coverage.undetected, ["X[1] stuck-at-0", "X[1] stuck-at-1", &format!("_{}_ stuck-at-0", circuit.wires[0].out.n)]);
+        assert_eq!(coverage.components, [("top".to_string(), 8, 3)]);
+
+        // the fault simulation of a divider agrees with simulating every faulty circuit in full
+        let mut info = DivInfo::default_newton();
+        info.number_bits = 8;
+        let mut circuit = Circuit::get_divider_circuit(info);
+        circuit.remove_dead_ends();
+        let vectors = circuit.random_vectors(100);
+        let coverage = circuit.fault_coverage(&vectors);
+        let mut sim = Simulation::new(&circuit);
+        let outputs: Vec<Bit> = circuit.outputs.iter().flat_map(|io| io.bits.iter().copied()).collect();
+        let mut detected = 0;
+        for fault in circuit.faults() {
+            let mut found = false;
+            for chunk in vectors.chunks(64) {
+                for (idx, io) in circuit.inputs.iter().enumerate() {
+                    let numbers: Vec<u128> = chunk.iter().map(|vector| vector[idx]).collect();
+                    sim.set_input(&io.name, &pack(&numbers, io.bits.len()));
+                }
+                sim.eval();
+                let mut values = sim.values().to_vec();
+                values[fault.line.n] = if fault.stuck_at { u64::MAX } else { 0 };
+                for wire in circuit.wires.iter().filter(|wire| wire.out != fault.line) {
+                    values[wire.out.n] = crate::simulation::eval_gate(wire.gate, &values);
+                }
+                let mask = u64::MAX >> (64 - chunk.len());
+                found |= outputs.iter().any(|&bit| match bit {
+                    Bit::Var(l) => (values[l.n] ^ sim.value(bit)) & mask != 0,
+                    _ => false,
+                });
+            }
+            detected += found as usize;
+        }
+        assert_eq!(coverage.detected, detected);
+        assert!(coverage.useful_vectors <= 100);
+        // the self correcting newton iterations hide many faults even from more vectors
+        let more: Vec<Vec<u128>> = vectors.iter().cloned().chain(circuit.random_vectors(1000)).collect();
+        let more = circuit.fault_coverage(&more);
+        assert!(more.detected >= coverage.detected && more.coverage() < 0.9);
+        let parsed = circuit.parse_vectors(&vectors.iter().map(|v| format!("{:x} {:02x}\n", v[0], v[1])).collect::<String>());
+        assert_eq!(parsed, vectors);
+    }
+
+    #[test]
+    fn test_pattern_generation() {
+        // the sat solver alone proves the faults of y = x0 | (x0 & x1) on x1 and the and
+        // stuck at 0 redundant
+        let mut circuit = Circuit::new();
+        let x: Vec<Bit> = (0..2).map(|_| circuit.new_line()).collect();
+        circuit.add_as_io(&x, "X", false);
+        let a = circuit.and(x[0], x[1]);
+        let y = circuit.or(x[0], a);
+        circuit.add_as_io(&vec![y], "Y", true);
+        circuit.update_stats();
+        let result = circuit.atpg(0, 1000);
+        assert_eq!((result.faults, result.detected, result.redundant.len(), result.aborted.len()), (8, 5, 3, 0));
+        assert!(!result.exhaustive && result.vectors.len() <= 2);
+        assert_eq!(result.redundant_names, ["X[1] stuck-at-0", "X[1] stuck-at-1", &format!("_{}_ stuck-at-0", circuit.wires[0].out.n)]);
+        assert_eq!(circuit.fault_coverage(&result.vectors).detected, 5);
+
+        // the vectors of a divider detect every fault the simulation of all inputs detects
+        let mut info = DivInfo::default_newton();
+        info.number_bits = 4;
+        let mut circuit = Circuit::get_divider_circuit(info);
+        circuit.remove_dead_ends();
+        let result = circuit.atpg(16, 200);
+        let all: Vec<Vec<u128>> = (0..256).map(|v| vec![v >> 4, v & 0xf]).collect();
+        let coverage = circuit.fault_coverage(&all);
+        assert!(result.aborted.is_empty());
+        assert_eq!(result.detected + result.redundant.len(), result.faults);
+        assert_eq!(result.detected, coverage.detected);
+        assert_eq!(circuit.fault_coverage(&result.vectors).detected, result.detected);
+        assert!(result.vectors.len() < coverage.useful_vectors + 16);
+        assert_eq!(circuit.parse_vectors(&circuit.vectors_text(&result.vectors)), result.vectors);
+
+        // the testbench expects the quotient and remainder of the first vector
+        let testbench = circuit.testbench("NewtDiv", &result.vectors);
+        let (dividend, divisor) = (result.vectors[0][0], result.vectors[0][1]);
+        assert!(testbench.starts_with("`timescale 1ns / 1ps\nmodule NewtDiv_tb;\n"));
+        assert!(testbench.contains("NewtDiv dut(.R_0(R_0), .D(D), .Q(Q), .R_n1(R_n1), .Valid(Valid));"));
+        assert!(testbench.contains(&format!("    R_0 = 4'h{dividend:x};\n    D = 4'h{divisor:x};\n")));
+        if let Some(quotient) = dividend.checked_div(divisor) {
+            let check = format!("#1 if (Q !== 4'h{quotient:x} || R_n1 !== 4'h{:x} || Valid !== 1'h1)", dividend % divisor);
+            assert!(testbench.contains(&check));
+        }
+        assert_eq!(testbench.matches("errors = errors + 1;").count(), result.vectors.len());
+    }
+
+    #[test]
+    fn residue_check() {
+        let bits = 6;
+        let divisors: Vec<u128> = (1..64).chain([1]).collect();
+        for (estimator, k) in [(Estimate::None, 2), (Estimate::None, 3), (Estimate::Flip5bit, 2)] {
+            let mut info = DivInfo::default_newton();
+            info.number_bits = bits;
+            info.estimator = estimator;
+            info.residue_check = Some(k);
+            let circuit = Circuit::get_divider_circuit(info);
+            let mut sim = Simulation::new(&circuit);
+            let q_line = match circuit.outputs[0].bits[0] {
+                Bit::Var(l) => l,
+                bit => panic!("quotient bit 0 is the constant {bit:?}"),
+            };
+            for dividend in 0..1_u128 << bits {
+                sim.set_input("R_0", &pack(&vec![dividend; 64], bits));
+                sim.set_input("D", &pack(&divisors, bits));
+                sim.eval();
+                let (q, r, error) = (unpack(&sim.output("Q")), unpack(&sim.output("R_n1")), unpack(&sim.output("Error")));
+                for (i, &divisor) in divisors.iter().enumerate() {
+                    assert_eq!((q[i], r[i]), (dividend / divisor, dividend % divisor), "{estimator:?} {dividend} / {divisor}");
+                    assert_eq!(error[i], 0, "{estimator:?} {dividend} / {divisor}");
+                }
+
+                // quotient bit 0 stuck changes q * d + r by d, which is missed if 2^k - 1 divides d
+                if estimator == Estimate::None {
+                    let mut values = sim.values().to_vec();
+                    let good_q = values[q_line.n];
+                    for stuck in [0, u64::MAX] {
+                        values[q_line.n] = stuck;
+                        for wire in circuit.wires.iter().filter(|wire| wire.out != q_line) {
+                            values[wire.out.n] = crate::simulation::eval_gate(wire.gate, &values);
+                        }
+                        let error = match circuit.outputs.iter().find(|io| io.name == "Error").unwrap().bits[0] {
+                            Bit::Var(l) => values[l.n],
+                            _ => 0,
+                        };
+                        for (i, &divisor) in divisors.iter().enumerate() {
+                            if (stuck ^ good_q) >> i & 1 == 1 {
+                                let detected = divisor % ((1 << k) - 1) != 0;
+                                assert_eq!(error >> i & 1 == 1, detected, "{dividend} / {divisor} with q0 stuck");
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // an iterative divider is only checked while done is high
+        let mut info = DivInfo::default_newton();
+        info.division_method = Method::NewtonIterative;
+        info.number_bits = 8;
+        info.residue_check = Some(2);
+        let circuit = Circuit::get_divider_circuit(info);
+        let names: Vec<&str> = circuit.outputs.iter().map(|io| io.name.as_str()).collect();
+        assert_eq!(names, ["Q", "R_n1", "Valid", "Error", "busy", "done"]);
+        let mut sim = Simulation::new(&circuit);
+        sim.set_input("rst", &[u64::MAX]);
+        sim.eval();
+        sim.clock();
+        sim.set_input("rst", &[0]);
+        sim.set_input("R_0", &pack(&vec![200; 64], 8));
+        sim.set_input("D", &pack(&vec![7; 64], 8));
+        sim.set_input("start", &[u64::MAX]);
+        for _ in 0..10 {
+            sim.eval();
+            assert_eq!(sim.output("Error"), [0]);
+            sim.clock();
+            sim.set_input("start", &[0]);
+        }
+        assert_eq!((unpack(&sim.output("Q"))[0], unpack(&sim.output("done"))[0]), (28, 1));
+    }
+
+    #[test]
+    fn triple_modular_redundancy() {
+        let bits = 6;
+        let netlist = || {
+            let mut info = DivInfo::default_newton();
+            info.number_bits = bits;
+            let mut circuit = Circuit::get_divider_circuit(info);
+            circuit.remove_dead_ends();
+            circuit
+        };
+        let original = netlist();
+        let output_bits: usize = original.outputs.iter().map(|io| io.bits.len()).sum();
+        for vote_after in [vec![], vec!["newton_iteration".to_string()], vec!["newton_iteration[1]".to_string(), "correction".to_string()]] {
+            let mut circuit = netlist();
+            let report = circuit.triplicate(&vote_after);
+            assert_eq!(report.gates_after, circuit.wires.len());
+            assert_eq!(report.voters, 3 * report.voted_lines + output_bits);
+            assert!(report.gates_after >= 3 * report.gates_before && report.depth_after > report.depth_before);
+            assert_eq!(vote_after.is_empty(), report.voted_lines == 0);
+            let mut sims = [Simulation::new(&original), Simulation::new(&circuit)];
+            for dividend in 0..1_u128 << bits {
+                let divisors: Vec<u128> = (0..1 << bits).collect();
+                let outputs: Vec<Vec<Vec<u64>>> = sims
+                    .iter_mut()
+                    .map(|sim| {
+                        sim.set_input("R_0", &pack(&vec![dividend; 64], bits));
+                        sim.set_input("D", &pack(&divisors, bits));
+                        sim.eval();
+                        original.outputs.iter().map(|io| sim.output(&io.name)).collect()
+                    })
+                    .collect();
+                assert_eq!(outputs[0], outputs[1]);
+            }
+
+            // single stuck-at faults can only show at the outputs if they hit a voter or an input
+            let faults = circuit.faults();
+            let mut detected = vec![false; faults.len()];
+            circuit.fault_simulate(&faults, &circuit.random_vectors(256), &mut detected);
+            let scope_of_line: HashMap<usize, usize> = circuit.wires.iter().map(|wire| (wire.out.n, wire.scope)).collect();
+            for (fault, _) in faults.iter().zip(&detected).filter(|&(_, &d)| d) {
+                if let Some(&scope) = scope_of_line.get(&fault.line.n) {
+                    assert_eq!(circuit.scopes[scope].name, "tmr_voter", "{} is not masked", circuit.fault_name(*fault));
+                }
+            }
+        }
+
+        // the registers of a sequential divider are triplicated and reload the vote of the copies
+        let original = get_iterative_divider_netlist(8, Estimate::Flip5bit);
+        let mut circuit = get_iterative_divider_netlist(8, Estimate::Flip5bit);
+        let report = circuit.triplicate(&[]);
+        let output_bits: usize = original.outputs.iter().map(|io| io.bits.len()).sum();
+        assert_eq!(report.registers_after, 3 * report.registers_before);
+        assert_eq!((report.voted_lines, report.voters), (report.registers_before, 3 * report.registers_before + output_bits));
+        let (dividends, divisors) = get_random_operands(8);
+        let mut sims = [Simulation::new(&original), Simulation::new(&circuit)];
+        for cycle in 0..12 {
+            let mut outputs = Vec::new();
+            for sim in &mut sims {
+                sim.set_input("rst", &[(cycle == 0) as u64 * u64::MAX]);
+                sim.set_input("start", &[(cycle == 1) as u64 * u64::MAX]);
+                sim.set_input("R_0", &pack(&dividends, 8));
+                sim.set_input("D", &pack(&divisors, 8));
+                sim.eval();
+                outputs.push(original.outputs.iter().map(|io| sim.output(&io.name)).collect::<Vec<Vec<u64>>>());
+                sim.clock();
+            }
+            assert_eq!(outputs[0], outputs[1], "cycle {cycle}");
+        }
+    }
+
+    #[test]
+    fn approximate_dividers() {
+        let bits = 6;
+        let divider = |info: DivInfo| {
+            let mut circuit = Circuit::get_divider_circuit(DivInfo { number_bits: bits, ..info });
+            circuit.remove_dead_ends();
+            circuit
+        };
+        let exact = divider(DivInfo::default_newton());
+        let stats = exact.error_stats(0);
+        assert!(stats.exhaustive);
+        assert_eq!((stats.samples, stats.erroneous, stats.max_error), (64 * 63, 0, 0));
+
+        let approximations = [
+            DivInfo { dropped_iterations: 1, ..DivInfo::default_newton() },
+            DivInfo { truncated_columns: bits, ..DivInfo::default_newton() },
+            DivInfo { skip_correction: true, ..DivInfo::default_newton() },
+        ];
+        for info in approximations {
+            let circuit = divider(info);
+            assert!(circuit.stats.gatter_count < exact.stats.gatter_count, "{info:?}");
+            let stats = circuit.error_stats(0);
+            // the statistics recomputed from a plain simulation of every input
+            let (mut erroneous, mut max_error, mut distances, mut relative) = (0, 0, 0.0, 0.0);
+            for divisor in 1..64u128 {
+                let mut sim = Simulation::new(&circuit);
+                sim.set_input("R_0", &pack(&(0..64).collect::<Vec<u128>>(), bits));
+                sim.set_input("D", &pack(&vec![divisor; 64], bits));
+                sim.eval();
+                for (dividend, q) in unpack(&sim.output("Q")).into_iter().enumerate() {
+                    let exact = dividend as u128 / divisor;
+                    let distance = q.abs_diff(exact);
+                    erroneous += (distance > 0) as usize;
+                    max_error = max_error.max(distance);
+                    distances += distance as f64;
+                    relative += distance as f64 / exact.max(1) as f64;
+                }
+            }
+            assert!(erroneous > 0, "{info:?}");
+            assert_eq!((stats.erroneous, stats.max_error), (erroneous, max_error));
+            assert!((stats.mean_error_distance - distances / 4032.0).abs() < 1e-9);
+            assert!((stats.mred - relative / 4032.0).abs() < 1e-9);
+            assert!((stats.error_rate() - erroneous as f64 / 4032.0).abs() < 1e-9);
+            if info.skip_correction {
+                assert_eq!(stats.max_error, 1);
+            }
+        }
+
+        // the iterative divider computes the same quotients with the same iterations dropped
+        let combinational = divider(approximations[0]).error_stats(0);
+        let iterative = divider(DivInfo { division_method: Method::NewtonIterative, ..approximations[0] }).error_stats(0);
+        assert_eq!(iterative, combinational);
+
+        let space = Circuit::approximation_space(DivInfo { number_bits: bits, ..DivInfo::default_newton() }, 0);
+        let names: Vec<&str> = space.points.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names[0], "exact");
+        assert_eq!(names.last(), Some(&"skip correction"));
+        assert_eq!(space.points[0].gates, exact.stats.gatter_count);
+    }
 }
@@ -1,6 +1,10 @@
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser};
 use crate::dividers::{Method, DividendSize, Precision, Estimate, SubMethod, DivInfo};
-use crate::data::{Adder, Mul};
+use crate::cost::CostModel;
+use crate::optimize::Pass;
+use crate::data::{Adder, Mul, Truncation};
+use crate::pipeline::Pipelining;
+use crate::bdd::VariableOrder;
 
 #[derive(Parser, Debug)]
 #[command(name = "NewtonDivisionCircuitGenerator")]
@@ -22,14 +26,194 @@ pub struct Args {
     pub dividend_size: DividendSize,
     #[arg(value_enum, short, long, default_value_t = Precision::Fixed)]
     pub precision: Precision,
+    // fused merges the subtraction into the partial products of dadda and booth multipliers,
+    // array and karatsuba multipliers finish the product first and subtract it afterwards
     #[arg(value_enum, short, long, default_value_t = SubMethod::Seperate)]
     pub sub_method: SubMethod,
+    #[arg(long, default_value_t = 64)]
+    pub karatsuba_threshold: usize,
+    #[arg(value_enum, long, default_value_t = Mul::DadaTree)]
+    pub karatsuba_leaf: Mul,
+    #[arg(value_enum, short, long, default_value_t = Truncation::None)]
+    pub truncation: Truncation,
+    // adds an Error output checking q * d + r_n1 == r_0 modulo 2^k - 1 and r_n1 < d, 2 checks modulo 3
+    #[arg(long, value_parser = clap::value_parser!(u16).range(2..=64))]
+    pub residue_check: Option<u16>,
+    // approximate computing, leaves out that many of the newton iterations the precision needs
+    #[arg(long, default_value_t = 0)]
+    pub drop_iterations: usize,
+    // drops that many more low columns of the squares and products of every newton iteration
+    #[arg(long, default_value_t = 0)]
+    pub truncate_columns: usize,
+    // outputs the quotient of the reciprocal without selecting q, q + 1 or q - 1 by the remainder
+    #[arg(long)]
+    pub skip_correction: bool,
+    // prints error rate, mean error distance, max error and mred of the quotient against exact
+    // division, approximate dividers always print them
+    #[arg(long)]
+    pub error_stats: bool,
+    // random inputs of error statistics for dividers with more than 20 input bits
+    #[arg(long, default_value_t = 100000)]
+    pub error_samples: usize,
+    // prints gates, depth and error statistics of the exact divider, every number of dropped
+    // iterations, 1, 2, 4, ... truncated columns and the divider without the correction step
+    #[arg(long)]
+    pub approximation_space: bool,
+    // optimization passes run in the given order after removing dead ends, like const-prop,strash,rewrite
+    #[arg(value_enum, long, value_delimiter = ',')]
+    pub optimize: Vec<Pass>,
+    // number of pipeline stages, the circuit stays combinational if neither this nor stage_levels is given
+    #[arg(long, conflicts_with = "stage_levels", value_parser = clap::value_parser!(u16).range(1..))]
+    pub stages: Option<u16>,
+    // maximum number of gate levels per pipeline stage
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    pub stage_levels: Option<u16>,
+    // moves the registers of a pipelined or iterative divider to minimize its clock period
+    // with the gate delays of the cost model
+    #[arg(long)]
+    pub retime: bool,
+    // triple modular redundancy as the last transformation, voters at the outputs, behind the
+    // registers and at the lines leaving the given components like newton_iteration or
+    // newton_iteration[1]
+    #[arg(long, num_args = 0.., value_delimiter = ',')]
+    pub tmr: Option<Vec<String>>,
+    // area and delay of the gates used by the reports
+    #[arg(value_enum, long, default_value_t = CostModel::Unit)]
+    pub cost_model: CostModel,
+    // overrides gate areas and delays of the cost model by the `name = value` lines of the given
+    // file, named like not_area, xor_delay, register_area or fanout_delay
+    #[arg(long)]
+    pub cost_file: Option<String>,
+    // prints area and delay of the divider for every adder and multiplier
+    #[arg(long)]
+    pub design_space: bool,
+    // prints the gates and critical path depth of every component of the circuit
+    #[arg(long)]
+    pub breakdown: bool,
+    // prints the given number of deepest paths and the arrival time of every output bit
+    #[arg(long)]
+    pub timing: Option<usize>,
+    // writes the timing report as json to the given file
+    #[arg(long)]
+    pub timing_json: Option<String>,
+    // prints the number and depth of the luts needed with 4, 5 and 6 inputs per lut
+    #[arg(long)]
+    pub luts: bool,
+    // prints lut count and depth of the divider for every adder, multiplier and estimator
+    #[arg(long)]
+    pub lut_space: bool,
+    // writes the circuit mapped to luts with lut_size inputs as blif to the given file
+    #[arg(long)]
+    pub blif: Option<String>,
+    #[arg(long, default_value_t = 6, value_parser = clap::value_parser!(u8).range(2..=6))]
+    pub lut_size: u8,
+    // proves the circuit after the optimization passes equivalent to the generated one, or to
+    // the one built with the reference adder and multiplier, and prints a counterexample if not
+    #[arg(long)]
+    pub check_equivalence: bool,
+    #[arg(value_enum, long)]
+    pub reference_adder: Option<Adder>,
+    #[arg(value_enum, long)]
+    pub reference_multiplier: Option<Mul>,
+    // writes the miter of the circuit and its reference as dimacs cnf for external sat solvers,
+    // it is satisfiable if and only if they differ
+    #[arg(long)]
+    pub miter_dimacs: Option<String>,
+    // writes the divider and the negation of its property, valid implies q * d + r_n1 == r_0 and
+    // r_n1 < d, as dimacs cnf, it is unsatisfiable if and only if the divider is correct
+    #[arg(long)]
+    pub property_dimacs: Option<String>,
+    // writes the same formula as smt-lib2 over bit vectors for solvers like bitwuzla or z3
+    #[arg(long)]
+    pub property_smt2: Option<String>,
+    // builds bdds of the outputs with the given variable order and prints their size and all
+    // inputs with a wrong quotient, check_equivalence uses them too, feasible up to about 12 bits
+    #[arg(value_enum, long)]
+    pub bdd: Option<VariableOrder>,
+    // prints the exhaustively evaluated relative error of every initial estimate for the width
+    // and the newton iterations it needs
+    #[arg(long)]
+    pub error_bounds: bool,
+    // fault simulates every stuck-at-0 and stuck-at-1 fault for the vectors of a $readmemh
+    // style file with one hexadecimal number per input and line and prints the coverage
+    #[arg(long)]
+    pub fault_vectors: Option<String>,
+    // adds that many random vectors to the fault simulation, atpg starts with them, 256 by default
+    #[arg(long)]
+    pub random_vectors: Option<usize>,
+    // generates test vectors for every detectable stuck-at fault with a sat solver and prints
+    // the redundant faults, whose lines could be replaced by constants
+    #[arg(long)]
+    pub atpg: bool,
+    // writes the atpg vectors in the format read by fault_vectors
+    #[arg(long)]
+    pub atpg_vectors: Option<String>,
+    // writes a verilog testbench checking the outputs for the atpg vectors, the circuit
+    // has to stay combinational
+    #[arg(long, conflicts_with_all = ["stages", "stage_levels"])]
+    pub testbench: Option<String>,
+    // conflicts after which the sat solver gives up on a fault, which counts as aborted
+    #[arg(long, default_value_t = 1000)]
+    pub atpg_conflicts: usize,
+    // maps the circuit onto the cells of the given liberty file and writes the mapped netlist
+    #[arg(long, conflicts_with = "hierarchical")]
+    pub liberty: Option<String>,
+    // writes every arithmetic block as its own verilog module instead of one flat module
+    #[arg(long)]
+    pub hierarchical: bool,
     #[arg(short, long)]
     pub outputfile: Option<String>,
 }
 
 pub fn parse() -> (DivInfo, bool, Args) {
     let args = Args::parse();
+    if args.division_method == Method::NewtonIterative && (args.stages.is_some() || args.stage_levels.is_some()) {
+        Args::command()
+            .error(ErrorKind::ArgumentConflict, "the iterative divider already has registers, only combinational dividers can be pipelined with --stages or --stage-levels")
+            .exit();
+    }
+    let fault_simulation = args.atpg || args.atpg_vectors.is_some() || args.testbench.is_some() || args.fault_vectors.is_some() || args.random_vectors.is_some();
+    if args.division_method == Method::NewtonIterative && fault_simulation {
+        Args::command()
+            .error(ErrorKind::ArgumentConflict, "only combinational dividers can be fault simulated, the iterative divider has registers and does not work with --atpg, --atpg-vectors, --testbench, --fault-vectors or --random-vectors")
+            .exit();
+    }
+    if args.division_method == Method::NewtonIterative && (args.property_dimacs.is_some() || args.property_smt2.is_some()) {
+        Args::command()
+            .error(ErrorKind::ArgumentConflict, "only combinational dividers can be encoded, the iterative divider has registers and does not work with --property-dimacs or --property-smt2")
+            .exit();
+    }
+    if args.division_method == Method::NewtonIterative && args.bdd.is_some() {
+        Args::command()
+            .error(ErrorKind::ArgumentConflict, "only combinational dividers have bdds, the iterative divider has registers and does not work with --bdd")
+            .exit();
+    }
+    let approximate = args.drop_iterations > 0 || args.truncate_columns > 0 || args.skip_correction;
+    if args.division_method == Method::Goldschmidt && (approximate || args.approximation_space) {
+        Args::command()
+            .error(ErrorKind::ArgumentConflict, "approximations are only implemented for newton dividers, goldschmidt does not work with --drop-iterations, --truncate-columns, --skip-correction or --approximation-space")
+            .exit();
+    }
+    let truncated = args.truncation != Truncation::None || args.truncate_columns > 0;
+    if truncated && (args.preferred_multiplier != Mul::DadaTree || args.reference_multiplier.is_some_and(|m| m != Mul::DadaTree)) {
+        Args::command()
+            .error(ErrorKind::ArgumentConflict, "truncated products are only built by the dadda tree multiplier, --truncation and --truncate-columns do not work with other --preferred-multiplier or --reference-multiplier values")
+            .exit();
+    }
+    let dividend_bits = match args.dividend_size {
+        DividendSize::DividendDouble => 2 * args.bits as usize - 2,
+        DividendSize::Equal => args.bits as usize,
+    };
+    if dividend_bits > 127 && (approximate || args.error_stats || args.approximation_space) {
+        Args::command()
+            .error(ErrorKind::ArgumentConflict, "error statistics support operands of at most 127 bits, which approximate dividers, --error-stats and --approximation-space need")
+            .exit();
+    }
+    if dividend_bits > 128 && fault_simulation {
+        Args::command()
+            .error(ErrorKind::ArgumentConflict, "test vectors support inputs of at most 128 bits, which --atpg, --atpg-vectors, --testbench, --fault-vectors and --random-vectors need")
+            .exit();
+    }
 
     let info = DivInfo {
         division_method: args.division_method,
@@ -39,11 +223,26 @@ pub fn parse() -> (DivInfo, bool, Args) {
         estimator: args.estimator,
         sub_method: args.sub_method,
         dividend_size: args.dividend_size,
+        karatsuba_threshold: args.karatsuba_threshold,
+        karatsuba_leaf: args.karatsuba_leaf,
+        truncation: args.truncation,
+        residue_check: args.residue_check.map(|k| k as usize),
+        dropped_iterations: args.drop_iterations,
+        truncated_columns: args.truncate_columns,
+        skip_correction: args.skip_correction,
     };
 
     (info, args.precision == Precision::Fixed, args)
 }
 
+pub fn get_pipelining(args: &Args) -> Option<Pipelining> {
+    match (args.stages, args.stage_levels) {
+        (Some(stages), _) => Some(Pipelining::Stages(stages as usize)),
+        (None, Some(levels)) => Some(Pipelining::Levels(levels as usize)),
+        (None, None) => None,
+    }
+}
+
 use std::path::Path;
 pub fn get_file_and_module_name(args: Args) -> (String, String) {
     let mut file_name = String::from("");
@@ -51,6 +250,7 @@ pub fn get_file_and_module_name(args: Args) -> (String, String) {
     match args.division_method {
         Method::Newton => { file_name.push_str("NewtDiv"); }
         Method::Goldschmidt => { file_name.push_str("GoldDiv"); }
+        Method::NewtonIterative => { file_name.push_str("NewtDivIter"); }
     }
 
     match args.dividend_size {
@@ -58,7 +258,7 @@ pub fn get_file_and_module_name(args: Args) -> (String, String) {
         DividendSize::Equal => { file_name.push_str("");}
     }
 
-    if args.division_method == Method::Newton {
+    if args.division_method != Method::Goldschmidt {
         match args.estimator {
             Estimate::Flip5bit => { file_name.push_str("FlipEst");},
             Estimate::Table10bit => { file_name.push_str("TableEst");},
@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+
+use crate::data::{Adder, Bit, Circuit, Gate, Mul};
+use crate::dividers::DivInfo;
+use clap::ValueEnum;
+
+// area in nand2 equivalents and delay of every gate type and of a register, the delay of a
+// gate grows by fanout_delay for every gate, register or output it drives beyond the first
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct GateCosts {
+    pub not_area: f64,
+    pub and_area: f64,
+    pub or_area: f64,
+    pub xor_area: f64,
+    pub register_area: f64,
+    pub not_delay: usize,
+    pub and_delay: usize,
+    pub or_delay: usize,
+    pub xor_delay: usize,
+    pub fanout_delay: usize,
+}
+
+// the error of a malformed cost file
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("cost file: {message}"))
+}
+
+impl GateCosts {
+    pub fn read(&self, file_name: &str) -> Result<GateCosts> {
+        self.parse(&std::fs::read_to_string(file_name)?)
+    }
+
+    // the costs with the values of the `name = value` lines of text, which are named like the
+    // fields, e.g. xor_area = 2.33 or fanout_delay = 5, # starts a comment and the fields
+    // without a line keep their values
+    pub fn parse(&self, text: &str) -> Result<GateCosts> {
+        let mut costs = *self;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, value) = line.split_once('=').ok_or_else(|| invalid(format!("line {} is not name = value", number + 1)))?;
+            let (name, value) = (name.trim(), value.trim());
+            let area = || match value.parse::<f64>() {
+                Ok(area) if area.is_finite() && area >= 0.0 => Ok(area),
+                _ => Err(invalid(format!("{name} in line {} is no area: {value}", number + 1))),
+            };
+            let delay = || value.parse::<usize>().map_err(|_| invalid(format!("{name} in line {} is no delay: {value}", number + 1)));
+            match name {
+                "not_area" => costs.not_area = area()?,
+                "and_area" => costs.and_area = area()?,
+                "or_area" => costs.or_area = area()?,
+                "xor_area" => costs.xor_area = area()?,
+                "register_area" => costs.register_area = area()?,
+                "not_delay" => costs.not_delay = delay()?,
+                "and_delay" => costs.and_delay = delay()?,
+                "or_delay" => costs.or_delay = delay()?,
+                "xor_delay" => costs.xor_delay = delay()?,
+                "fanout_delay" => costs.fanout_delay = delay()?,
+                _ => return Err(invalid(format!("unknown cost {name} in line {}", number + 1))),
+            }
+        }
+        Ok(costs)
+    }
+
+    pub fn area(&self, gate: &Gate) -> f64 {
+        match gate {
+            Gate::Not(_) => self.not_area,
+            Gate::And(..) => self.and_area,
+            Gate::Or(..) => self.or_area,
+            Gate::Xor(..) => self.xor_area,
+        }
+    }
+
+    pub fn delay(&self, gate: &Gate, fanout: usize) -> usize {
+        let delay = match gate {
+            Gate::Not(_) => self.not_delay,
+            Gate::And(..) => self.and_delay,
+            Gate::Or(..) => self.or_delay,
+            Gate::Xor(..) => self.xor_delay,
+        };
+        delay + self.fanout_delay * fanout.saturating_sub(1)
+    }
+}
+
+// built-in cost models
+// Unit: every gate and register has area 1 and every gate delay 1, so the area is the number
+// of gates and registers and the delay the number of levels
+// Cmos: nand2 equivalents of a typical static cmos library, delays in ps
+// Fpga: a 6-input lut absorbs about three 2-input gates and all inverters, a lut level with
+// routing takes about 450 ps, the area is given in luts
+#[derive(Debug, PartialEq, Eq, Clone, Copy, ValueEnum)]
+pub enum CostModel {
+    Unit,
+    Cmos,
+    Fpga,
+}
+
+impl CostModel {
+    pub fn costs(&self) -> GateCosts {
+        match self {
+            Self::Unit => GateCosts {
+                not_area: 1.0,
+                and_area: 1.0,
+                or_area: 1.0,
+                xor_area: 1.0,
+                register_area: 1.0,
+                not_delay: 1,
+                and_delay: 1,
+                or_delay: 1,
+                xor_delay: 1,
+                fanout_delay: 0,
+            },
+            Self::Cmos => GateCosts {
+                not_area: 0.67,
+                and_area: 1.33,
+                or_area: 1.33,
+                xor_area: 2.33,
+                register_area: 4.67,
+                not_delay: 12,
+                and_delay: 28,
+                or_delay: 32,
+                xor_delay: 45,
+                fanout_delay: 5,
+            },
+            Self::Fpga => GateCosts {
+                not_area: 0.0,
+                and_area: 0.33,
+                or_area: 0.33,
+                xor_area: 0.33,
+                register_area: 0.5,
+                not_delay: 0,
+                and_delay: 150,
+                or_delay: 150,
+                xor_delay: 150,
+                fanout_delay: 10,
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct CostReport {
+    pub area: f64,
+    pub delay: usize,
+}
+
+impl fmt::Display for CostReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Area: {:.2}, Delay: {}", self.area, self.delay)
+    }
+}
+
+// one configuration of a design space sweep
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct DesignPoint {
+    pub adder: Adder,
+    pub multiplier: Mul,
+    pub gates: usize,
+    pub levels: usize,
+    pub cost: CostReport,
+}
+
+// all configurations of a design space sweep
+#[derive(Debug, PartialEq, Clone)]
+pub struct DesignSpace {
+    pub points: Vec<DesignPoint>,
+}
+
+impl fmt::Display for DesignSpace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<6} {:<10} {:>8} {:>8} {:>12} {:>8}", "adder", "multiplier", "gates", "levels", "area", "delay")?;
+        for p in &self.points {
+            writeln!(
+                f,
+                "{:<6} {:<10} {:>8} {:>8} {:>12.2} {:>8}",
+                format!("{:?}", p.adder),
+                format!("{:?}", p.multiplier),
+                p.gates,
+                p.levels,
+                p.cost.area,
+                p.cost.delay
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Circuit {
+    // number of gates, registers and outputs every line drives
+    pub fn fanouts(&self) -> HashMap<usize, usize> {
+        let mut fanouts = HashMap::new();
+        let register_inputs = self.registers.iter().map(|r| r.d);
+        let outputs = self.outputs.iter().flat_map(|o| o.bits.iter().copied());
+        for bit in register_inputs.chain(outputs) {
+            if let Bit::Var(l) = bit {
+                *fanouts.entry(l.n).or_insert(0) += 1;
+            }
+        }
+        for wire in &self.wires {
+            for l in wire.gate.inputs() {
+                *fanouts.entry(l.n).or_insert(0) += 1;
+            }
+        }
+        fanouts
+    }
+
+    // delay of every gate in the order of wires
+    pub fn gate_delays(&self, costs: &GateCosts) -> Vec<usize> {
+        let fanouts = self.fanouts();
+        self.wires
+            .iter()
+            .map(|w| costs.delay(&w.gate, fanouts.get(&w.out.n).copied().unwrap_or(0)))
+            .collect()
+    }
+
+    // the time the value of every line driven by a gate settles, inputs and registers
+    // change at time 0
+    pub fn arrival_times(&self, costs: &GateCosts) -> HashMap<usize, usize> {
+        let mut arrival: HashMap<usize, usize> = HashMap::with_capacity(self.wires.len());
+        for (wire, delay) in self.wires.iter().zip(self.gate_delays(costs)) {
+            let inputs = wire.gate.inputs().iter().map(|l| arrival.get(&l.n).copied().unwrap_or(0)).max().unwrap_or(0);
+            arrival.insert(wire.out.n, inputs + delay);
+        }
+        arrival
+    }
+
+    pub fn area(&self, costs: &GateCosts) -> f64 {
+        let gates: f64 = self.wires.iter().map(|w| costs.area(&w.gate)).sum();
+        gates + costs.register_area * self.registers.len() as f64
+    }
+
+    // area and the delay of the slowest path ending in an output or a register
+    pub fn cost(&self, costs: &GateCosts) -> CostReport {
+        let arrival = self.arrival_times(costs);
+        let register_inputs = self.registers.iter().map(|r| r.d);
+        let outputs = self.outputs.iter().flat_map(|o| o.bits.iter().copied());
+        let delay = register_inputs
+            .chain(outputs)
+            .filter_map(|bit| match bit {
+                Bit::Var(l) => arrival.get(&l.n).copied(),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+        CostReport {
+            area: self.area(costs),
+            delay,
+        }
+    }
+
+    // builds the divider described by info with every adder and multiplier and evaluates it,
+    // a truncating divider only with the dadda tree
+    pub fn design_space(info: DivInfo, costs: &GateCosts) -> DesignSpace {
+        let mut points = Vec::new();
+        for &adder in Adder::value_variants() {
+            for &multiplier in Mul::value_variants().iter().filter(|&&m| m == Mul::DadaTree || !info.truncates()) {
+                let mut info = info;
+                info.defaultadder = adder;
+                info.defaultmult = multiplier;
+                let mut circuit = Circuit::get_divider_circuit(info);
+                circuit.remove_dead_ends();
+                points.push(DesignPoint {
+                    adder,
+                    multiplier,
+                    gates: circuit.stats.gatter_count,
+                    levels: circuit.stats.level_count,
+                    cost: circuit.cost(costs),
+                });
+            }
+        }
+        DesignSpace { points }
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use crate::data::{Circuit, Gate};
+
+// a cut of a line is a set of lines, the leaves, every path from an input or register to
+// the line passes through, truth is the function of the line in terms of the leaves:
+// bit m of truth is the value of the line if leaf i has the value of bit i of m,
+// leaves are sorted by line number and the function depends on every one of them
+#[derive(Debug, PartialEq, Eq, Clone, Default, Hash)]
+pub struct Cut {
+    pub leaves: Vec<usize>,
+    pub truth: u64,
+}
+
+// truth table of leaf i in a cut of `leaves` leaves
+pub fn var_truth(i: usize, leaves: usize) -> u64 {
+    const VARS: [u64; 6] = [
+        0xaaaa_aaaa_aaaa_aaaa,
+        0xcccc_cccc_cccc_cccc,
+        0xf0f0_f0f0_f0f0_f0f0,
+        0xff00_ff00_ff00_ff00,
+        0xffff_0000_ffff_0000,
+        0xffff_ffff_0000_0000,
+    ];
+    VARS[i] & truth_mask(leaves)
+}
+
+pub fn truth_mask(leaves: usize) -> u64 {
+    if leaves >= 6 {
+        u64::MAX
+    } else {
+        (1 << (1 << leaves)) - 1
+    }
+}
+
+// the truth table of a function of the leaves `from` as a function of the leaves `to`,
+// which have to contain all of `from`
+fn expand_truth(truth: u64, from: &[usize], to: &[usize]) -> u64 {
+    let positions: Vec<usize> = from.iter().map(|l| to.binary_search(l).unwrap()).collect();
+    let mut r = 0;
+    for m in 0..(1_usize << to.len()) {
+        let index = positions.iter().enumerate().fold(0, |index, (i, &p)| index | (((m >> p) & 0x1) << i));
+        r |= ((truth >> index) & 0x1) << m;
+    }
+    r
+}
+
+// removes the leaves the function does not depend on
+fn minimize_support(cut: Cut) -> Cut {
+    let n = cut.leaves.len();
+    let mask = truth_mask(n);
+    let mut leaves = Vec::with_capacity(n);
+    let mut truth = 0;
+    let mut kept = Vec::with_capacity(n);
+    for i in 0..n {
+        let var = var_truth(i, n);
+        let shift = 1 << i;
+        // the cofactors of leaf i are equal if the function does not depend on it
+        if ((cut.truth & var) >> shift) != (cut.truth & !var & mask) {
+            kept.push(i);
+            leaves.push(cut.leaves[i]);
+        }
+    }
+    if kept.len() == n {
+        return cut;
+    }
+    for m in 0..(1_usize << kept.len()) {
+        let index = kept.iter().enumerate().fold(0, |index, (j, &i)| index | (((m >> j) & 0x1) << i));
+        truth |= ((cut.truth >> index) & 0x1) << m;
+    }
+    Cut { leaves, truth }
+}
+
+impl Circuit {
+    // the cuts with at most k leaves of every line driven by a gate indexed like wires,
+    // every line keeps at most `limit` cuts with the fewest leaves besides the cut formed by
+    // the inputs of its gate, which always comes first, the line itself is a cut of every
+    // line and not listed, inputs and registers only have that cut
+    pub fn enumerate_cuts(&self, k: usize, limit: usize) -> Vec<Vec<Cut>> {
+        self.enumerate_cuts_with(k, limit, |_, cuts| cuts)
+    }
+
+    // like enumerate_cuts, but the cuts a line keeps are chosen by select, which gets the
+    // index of the wire and its cuts in the order of enumerate_cuts
+    pub fn enumerate_cuts_with(&self, k: usize, limit: usize, mut select: impl FnMut(usize, Vec<Cut>) -> Vec<Cut>) -> Vec<Vec<Cut>> {
+        let k = k.clamp(2, 6);
+        let wire_of_line: HashMap<usize, usize> = self.wires.iter().enumerate().map(|(idx, w)| (w.out.n, idx)).collect();
+        let mut cuts: Vec<Vec<Cut>> = Vec::with_capacity(self.wires.len());
+        for (idx, wire) in self.wires.iter().enumerate() {
+            // the cuts of a gate input including the input itself
+            let input_cuts = |n: usize| -> Vec<Cut> {
+                let mut r = vec![Cut {
+                    leaves: vec![n],
+                    truth: 0b10,
+                }];
+                if let Some(&input) = wire_of_line.get(&n) {
+                    r.extend(cuts[input].iter().cloned());
+                }
+                r
+            };
+            let mut node_cuts: Vec<Cut> = Vec::new();
+            match wire.gate {
+                Gate::Not(l) => {
+                    for cut in input_cuts(l.n) {
+                        let truth = !cut.truth & truth_mask(cut.leaves.len());
+                        node_cuts.push(Cut { leaves: cut.leaves, truth });
+                    }
+                }
+                Gate::And(l1, l2) | Gate::Or(l1, l2) | Gate::Xor(l1, l2) => {
+                    let (cuts1, cuts2) = (input_cuts(l1.n), input_cuts(l2.n));
+                    for c1 in &cuts1 {
+                        for c2 in &cuts2 {
+                            let mut leaves = c1.leaves.clone();
+                            leaves.extend(c2.leaves.iter().copied());
+                            leaves.sort_unstable();
+                            leaves.dedup();
+                            if leaves.len() > k {
+                                continue;
+                            }
+                            let t1 = expand_truth(c1.truth, &c1.leaves, &leaves);
+                            let t2 = expand_truth(c2.truth, &c2.leaves, &leaves);
+                            let truth = match wire.gate {
+                                Gate::And(..) => t1 & t2,
+                                Gate::Or(..) => t1 | t2,
+                                _ => t1 ^ t2,
+                            };
+                            node_cuts.push(minimize_support(Cut { leaves, truth }));
+                        }
+                    }
+                }
+            }
+
+            // the first cut built from the inputs is the gate itself, the others are sorted
+            // by size and cuts containing the leaves of another one are dropped
+            let mut kept = vec![node_cuts.remove(0)];
+            node_cuts.sort_by_key(|c| c.leaves.len());
+            for cut in node_cuts {
+                if kept.len() > limit {
+                    break;
+                }
+                if !kept.iter().any(|c| c.leaves.iter().all(|l| cut.leaves.binary_search(l).is_ok())) {
+                    kept.push(cut);
+                }
+            }
+            cuts.push(select(idx, kept));
+        }
+        cuts
+    }
+}
@@ -51,17 +51,17 @@ pub enum Adder {
 impl Adder {
     pub fn add(&self, circuit: &mut Circuit, s1: Vec<Bit>, s2: Vec<Bit>, c_in: Bit) -> Vec<Bit> {
         match *self {
-            Self::CRA => Circuit::cra(circuit, s1, s2, c_in),
-            Self::CSA => Circuit::csa(circuit, s1, s2, c_in),
-            Self::KSA => Circuit::ksa(circuit, s1, s2, c_in),
+            Self::CRA => circuit.scoped("cra", |c| c.cra(s1, s2, c_in)),
+            Self::CSA => circuit.scoped("csa", |c| c.csa(s1, s2, c_in)),
+            Self::KSA => circuit.scoped("ksa", |c| c.ksa(s1, s2, c_in)),
         }
     }
 
     pub fn sub(&self, circuit: &mut Circuit, minuend: Vec<Bit>, subtrahend: Vec<Bit>, c_in: Bit) -> Vec<Bit> {
         match *self {
-            Self::CRA => Circuit::crs(circuit, minuend, subtrahend, c_in),
-            Self::CSA => Circuit::css(circuit, minuend, subtrahend, c_in),
-            Self::KSA => Circuit::kss(circuit, minuend, subtrahend, c_in),
+            Self::CRA => circuit.scoped("crs", |c| c.crs(minuend, subtrahend, c_in)),
+            Self::CSA => circuit.scoped("css", |c| c.css(minuend, subtrahend, c_in)),
+            Self::KSA => circuit.scoped("kss", |c| c.kss(minuend, subtrahend, c_in)),
         }
     }
 }
@@ -70,24 +70,90 @@ impl Adder {
 pub enum Mul {
     Array,
     DadaTree,
+    Booth,
+    Karatsuba,
 }
 
 impl Mul {
     pub fn mul_u(&self, circuit: &mut Circuit, f1: Vec<Bit>, f2: Vec<Bit>, s: Option<Vec<Bit>>, adder: Adder) -> Vec<Bit> {
         match *self {
-            Self::DadaTree => Circuit::mul_unsigned_clean(circuit, f1, f2, s, adder),
-            Self::Array => Circuit::array_mul(circuit, f1, f2, s, adder),
+            Self::DadaTree => circuit.scoped("dadda_mul", |c| c.mul_unsigned_clean(f1, f2, s, adder)),
+            Self::Array => circuit.scoped("array_mul", |c| c.array_mul(f1, f2, s, adder)),
+            Self::Booth => circuit.scoped("booth_mul", |c| c.booth_mul(f1, f2, s, adder)),
+            Self::Karatsuba => circuit.scoped("karatsuba_mul", |c| c.karatsuba_mul(f1, f2, s, adder)),
         }
     }
 
     pub fn square_u(&self, circuit: &mut Circuit, f1: Vec<Bit>, ignore: usize, adder: Adder) -> Vec<Bit> {
         match *self {
-            Self::DadaTree => Circuit::square_u(circuit, f1, ignore, adder),
-            Self::Array => Circuit::array_mul(circuit, f1.clone(), f1, None, adder),
+            Self::DadaTree => circuit.scoped("dadda_square", |c| c.square_u(f1, ignore, adder)),
+            Self::Array => circuit.scoped("array_square", |c| c.array_square(f1, ignore, adder)),
+            Self::Booth => circuit.scoped("booth_square", |c| c.booth_square(f1, ignore, adder)),
+            Self::Karatsuba => {
+                let new_len = 2 * f1.len();
+                let mut r = circuit.scoped("karatsuba_square", |c| c.karatsuba_square(f1, adder));
+                r.truncate(new_len - ignore);
+                r
+            }
+        }
+    }
+
+    // the partial products of f1 * f2 modulo 2^columns sorted by columns, multipliers without
+    // an own partial product array contribute their finished product as a single row
+    pub fn partial_products(&self, circuit: &mut Circuit, f1: &[Bit], f2: &[Bit], columns: usize, adder: Adder) -> Vec<VecDeque<Bit>> {
+        match *self {
+            Self::DadaTree => Circuit::and_partial_products(circuit, f1, f2, columns),
+            Self::Booth => Circuit::booth_partial_products(circuit, f1, f2, columns),
+            Self::Array | Self::Karatsuba => {
+                let p = if f1.is_empty() || f2.is_empty() {
+                    Vec::new()
+                } else {
+                    self.mul_u(circuit, f1.to_vec(), f2.to_vec(), None, adder)
+                };
+                (0..columns).map(|column| p.get(column).copied().into_iter().collect()).collect()
+            }
+        }
+    }
+
+    // the product f1 * f2 without its lowest `drop` bits, only the dadda tree builds truncated products
+    pub fn mul_u_truncated(&self, circuit: &mut Circuit, f1: Vec<Bit>, f2: Vec<Bit>, drop: usize, truncation: Truncation, adder: Adder) -> Vec<Bit> {
+        match (*self, truncation) {
+            (_, Truncation::None) => {
+                let mut p = self.mul_u(circuit, f1, f2, None, adder);
+                p.drain(0..drop.min(p.len()));
+                p
+            }
+            (Self::DadaTree, _) => circuit.scoped("truncated_mul", |c| c.truncated_mul(f1, f2, drop, truncation, adder)),
+            _ => panic!("truncated products are only built by the dadda tree multiplier, not by the {self:?} one"),
+        }
+    }
+
+    // the square of f1 without its highest `ignore` and its lowest `drop` bits, only the dadda
+    // tree builds truncated squares
+    pub fn square_u_truncated(&self, circuit: &mut Circuit, f1: Vec<Bit>, ignore: usize, drop: usize, truncation: Truncation, adder: Adder) -> Vec<Bit> {
+        match (*self, truncation) {
+            (_, Truncation::None) => {
+                let mut p = self.square_u(circuit, f1, ignore, adder);
+                p.drain(0..drop.min(p.len()));
+                p
+            }
+            (Self::DadaTree, _) => circuit.scoped("truncated_square", |c| c.truncated_square(f1, ignore, drop, truncation, adder)),
+            _ => panic!("truncated squares are only built by the dadda tree multiplier, not by the {self:?} one"),
         }
     }
 }
 
+// how a product is built when its lowest bits get discarded anyway
+// None: the full product is computed and the low bits are dropped afterwards
+// Constant: the low partial product columns are omitted and a constant compensates their mean value
+// Variable: like Constant but the highest omitted column is added one column higher
+#[derive(Debug, PartialEq, Eq, Clone, Copy, ValueEnum)]
+pub enum Truncation {
+    None,
+    Constant,
+    Variable,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum Shift {
     Left,
@@ -316,6 +382,13 @@ impl Gate {
         }
     }
 
+    pub fn inputs(&self) -> Vec<Line> {
+        match *self {
+            Not(l) => vec![l],
+            And(l1, l2) | Or(l1, l2) | Xor(l1, l2) => vec![l1, l2],
+        }
+    }
+
     pub fn to_verilog(&self, io_lines: &HashMap<usize, NamedLine>) -> String {
         let op;
         match self {
@@ -340,11 +413,13 @@ impl Gate {
 pub struct Wire {
     pub gate: Gate,
     pub out: Line,
+    // index of the component in circuit.scopes the gate was created in
+    pub scope: usize,
 }
 
 impl Wire {
-    pub fn new(out: Line, gate: Gate) -> Self {
-        Wire { gate, out }
+    pub fn new(out: Line, gate: Gate, scope: usize) -> Self {
+        Wire { gate, out, scope }
     }
 
     pub fn to_verilog(&self, io_lines: &HashMap<usize, NamedLine>) -> String {
@@ -356,10 +431,50 @@ impl Wire {
     }
 }
 
+// a component like an adder or a newton iteration, scopes form a tree with the whole
+// circuit at index 0 as root, which is its own parent
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Scope {
+    pub name: &'static str,
+    pub parent: usize,
+}
+
+// a d flip-flop clocked by the circuit's clock line, q is the registered copy of d,
+// registers with reset are cleared to zero while the circuit's reset line is high
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Register {
+    pub q: Line,
+    pub d: Bit,
+    pub reset: bool,
+}
+
+impl Register {
+    pub fn to_verilog(&self, io_lines: &HashMap<usize, NamedLine>, reset: Option<Line>) -> String {
+        let d = match self.d {
+            Bit::Zero => String::from("1'b0"),
+            Bit::One => String::from("1'b1"),
+            Bit::Var(l) => l.to_verilog(io_lines),
+        };
+        match reset {
+            Some(rst) if self.reset => format!(
+                "    {} <= {} ? 1'b0 : {};\n",
+                self.q.to_verilog(io_lines),
+                rst.to_verilog(io_lines),
+                d
+            ),
+            _ => format!("    {} <= {};\n", self.q.to_verilog(io_lines), d),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Info {
     pub list_mul_numbers: Vec<usize>,
     pub mul_numbers_map: HashMap<usize, usize>,
+    // operands with at most this many bits are not split any further by the karatsuba multiplier
+    pub karatsuba_threshold: usize,
+    // multiplier used for the operands at the leaves of the karatsuba recursion
+    pub karatsuba_leaf: Mul,
 }
 
 impl Info {
@@ -381,6 +496,8 @@ impl Info {
         Info {
             list_mul_numbers: l,
             mul_numbers_map: m,
+            karatsuba_threshold: 64,
+            karatsuba_leaf: Mul::DadaTree,
         }
     }
 
@@ -416,6 +533,12 @@ pub struct Circuit {
     pub inputs: Vec<IO>,
     pub outputs: Vec<IO>,
     pub wires: Vec<Wire>,
+    pub registers: Vec<Register>,
+    pub scopes: Vec<Scope>,
+    // scope new gates are created in
+    pub scope: usize,
+    pub clock: Option<Line>,
+    pub reset: Option<Line>,
     pub stats: Stats,
     pub info: Info,
     pub zero_wire: Option<Line>,
@@ -429,6 +552,11 @@ impl Circuit {
             inputs: { Vec::new() },
             outputs: { Vec::new() },
             wires: { Vec::new() },
+            registers: { Vec::new() },
+            scopes: vec![Scope { name: "top", parent: 0 }],
+            scope: 0,
+            clock: None,
+            reset: None,
             stats: { Stats::new() },
             info: { Info::new() },
             zero_wire: None,
@@ -459,9 +587,65 @@ impl Circuit {
         line
     }
 
+    // lines for the outputs of registers which get connected to their inputs later by add_registers
+    pub fn new_register_lines(&mut self, count: usize) -> Vec<Bit> {
+        (0..count).map(|_| self.new_line()).collect()
+    }
+
+    pub fn add_registers(&mut self, q: &[Bit], d: &[Bit], reset: bool) {
+        for (&q, &d) in q.iter().zip(d) {
+            match q {
+                Bit::Var(q) => self.registers.push(Register { q, d, reset }),
+                _ => panic!("register output has to be a line!"),
+            }
+        }
+    }
+
+    // after registers have been moved in front of outputs only the output bits still driven
+    // by a gate keep their names, a register gets an assign statement to its output bit instead
+    pub fn rename_outputs(&mut self) {
+        let gates: HashSet<usize> = self.wires.iter().map(|w| w.out.n).collect();
+        let names: HashMap<String, &'static str> = self
+            .io_lines
+            .values()
+            .filter(|named| named.is_output)
+            .map(|named| (named.name.to_string(), named.name))
+            .collect();
+        self.io_lines.retain(|_, named| !named.is_output);
+        for output in &self.outputs {
+            let Some(&name) = names.get(&output.name) else {
+                continue;
+            };
+            for (idx, bit) in output.bits.iter().enumerate() {
+                if let Bit::Var(l) = bit {
+                    if gates.contains(&l.n) && !self.io_lines.contains_key(&l.n) {
+                        self.io_lines.insert(l.n, NamedLine { idx, name, is_output: true });
+                    }
+                }
+            }
+        }
+    }
+
+    // creates the clk and rst inputs all registers of the circuit are driven by
+    pub fn add_clock_and_reset(&mut self) {
+        let clk = self.new_line_constant_value();
+        let rst = self.new_line_constant_value();
+        self.clock = Some(clk);
+        self.reset = Some(rst);
+        self.add_as_io(&vec![Bit::Var(clk)], "clk", false);
+        self.add_as_io(&vec![Bit::Var(rst)], "rst", false);
+    }
+
     pub fn update_stats(&mut self) {
         self.stats.gatter_count = self.wires.len();
+        self.stats.register_count = self.registers.len();
         let mut max_depth = 0;
+        // in a pipelined circuit the deepest path ends in a register
+        for register in &self.registers {
+            if let Bit::Var(l) = register.d {
+                max_depth = max_depth.max(l.level);
+            }
+        }
         for out in &self.outputs {
             for bit in &out.bits {
                 match bit {
@@ -476,9 +660,11 @@ impl Circuit {
     }
 
     pub fn add_as_io(&mut self, bits: &Vec<Bit>, name: &'static str, is_output: bool) {
+        // register outputs are declared as reg, so an output driven by one gets an assign statement
+        let registered: HashSet<usize> = self.registers.iter().map(|r| r.q.n).collect();
         for (idx, bit) in bits.iter().enumerate() {
             if let Bit::Var(l) = bit {
-                if !self.io_lines.contains_key(&l.n) {
+                if !self.io_lines.contains_key(&l.n) && !registered.contains(&l.n) {
                     self.io_lines.insert(
                         l.n,
                         NamedLine {
@@ -540,6 +726,20 @@ impl Circuit {
     }
 
     pub fn all_labels(&mut self) -> String {
+        let mut s = self.io_labels();
+
+        for wire in &self.wires {
+            if !self.io_lines.contains_key(&wire.out.n) {
+                s.push_str(&format!("wire _{}_;\n", wire.out.n));
+            }
+        }
+
+        s.push_str(&self.register_labels());
+        s
+    }
+
+    // declarations of the ports and of the constant wires the outputs need
+    pub fn io_labels(&mut self) -> String {
         let mut s = String::new();
         let mut zero = false;
         let mut one = false;
@@ -591,16 +791,30 @@ impl Circuit {
             s.push_str(&format!{"wire oneWire;\n"});
         }
 
+        s
+    }
+
+    pub fn register_labels(&self) -> String {
+        let mut s = String::new();
+        for register in &self.registers {
+            s.push_str(&format!("reg _{}_;\n", register.q.n));
+        }
+        s
+    }
+
+    pub fn all_assigns_statements(&mut self) -> String {
+        let mut s = self.constant_assigns();
+
         for wire in &self.wires {
-            if !self.io_lines.contains_key(&wire.out.n) {
-                s.push_str(&format!("wire _{}_;\n", wire.out.n));
-            }
+            s.push_str(&wire.to_verilog(&self.io_lines));
         }
 
+        s.push_str(&self.register_block());
+        s.push_str(&self.output_assigns());
         s
     }
 
-    pub fn all_assigns_statements(&mut self) -> String {
+    pub fn constant_assigns(&self) -> String {
         let mut s = String::new();
         if let Some(_) = self.zero_wire {
             s.push_str(&format!("assign zeroWire = 1'b0 /*0*/;\n"))
@@ -608,12 +822,25 @@ impl Circuit {
         if let Some(_) = self.one_wire {
             s.push_str(&format!("assign oneWire = 1'b1 /*0*/;\n"))
         }
+        s
+    }
 
-        for wire in &self.wires {
-            s.push_str(&wire.to_verilog(&self.io_lines));
+    pub fn register_block(&self) -> String {
+        let mut s = String::new();
+        if let (Some(clk), false) = (self.clock, self.registers.is_empty()) {
+            s.push_str(&format!("always @(posedge {}) begin\n", clk.to_verilog(&self.io_lines)));
+            for register in &self.registers {
+                s.push_str(&register.to_verilog(&self.io_lines, self.reset));
+            }
+            s.push_str("end\n");
         }
+        s
+    }
 
-        for out in &mut self.outputs.clone() {
+    // assigns for the output bits which are constant, inputs or registers
+    pub fn output_assigns(&self) -> String {
+        let mut s = String::new();
+        for out in &self.outputs {
             for (idx, bit) in out.bits.iter().enumerate() {
                 match bit {
                     &Bit::Zero => {
@@ -667,7 +894,19 @@ impl Circuit {
     }
 
     pub fn remove_dead_ends(&mut self) {
-        let input_lines_count = self.stats.line_count - self.stats.gatter_count;
+        // lines driven by gates or registers, everything else is an input line
+        let wire_of_line: HashMap<usize, usize> = self
+            .wires
+            .iter()
+            .enumerate()
+            .map(|(idx, w)| (w.out.n, idx))
+            .collect();
+        let register_of_line: HashMap<usize, usize> = self
+            .registers
+            .iter()
+            .enumerate()
+            .map(|(idx, r)| (r.q.n, idx))
+            .collect();
 
         let mut wire_indexes = HashSet::new();
         let mut lines_to_traverse = VecDeque::new();
@@ -686,26 +925,27 @@ impl Circuit {
         while !lines_to_traverse.is_empty() {
             let w_idx = lines_to_traverse.pop_front().unwrap();
             if !wire_indexes.contains(&w_idx) {
-                if w_idx < input_lines_count {
-                    continue;
-                }
-                let w = self.wires[w_idx - input_lines_count];
-
-                match w.gate {
-                    Not(l) => {
+                if let Some(&r_idx) = register_of_line.get(&w_idx) {
+                    if let Bit::Var(l) = self.registers[r_idx].d {
                         lines_to_traverse.push_front(l.n);
                     }
-                    And(l1, l2) => {
-                        lines_to_traverse.push_front(l1.n);
-                        lines_to_traverse.push_front(l2.n);
-                    }
-                    Or(l1, l2) => {
-                        lines_to_traverse.push_front(l1.n);
-                        lines_to_traverse.push_front(l2.n);
-                    }
-                    Xor(l1, l2) => {
-                        lines_to_traverse.push_front(l1.n);
-                        lines_to_traverse.push_front(l2.n);
+                } else if let Some(&idx) = wire_of_line.get(&w_idx) {
+                    match self.wires[idx].gate {
+                        Not(l) => {
+                            lines_to_traverse.push_front(l.n);
+                        }
+                        And(l1, l2) => {
+                            lines_to_traverse.push_front(l1.n);
+                            lines_to_traverse.push_front(l2.n);
+                        }
+                        Or(l1, l2) => {
+                            lines_to_traverse.push_front(l1.n);
+                            lines_to_traverse.push_front(l2.n);
+                        }
+                        Xor(l1, l2) => {
+                            lines_to_traverse.push_front(l1.n);
+                            lines_to_traverse.push_front(l2.n);
+                        }
                     }
                 }
             }
@@ -713,13 +953,8 @@ impl Circuit {
             wire_indexes.insert(w_idx);
         }
 
-        let mut reduced_wires = vec![];
-        for w in &self.wires {
-            if wire_indexes.contains(&w.out.n) {
-                reduced_wires.push(*w);
-            }
-        }
-        self.wires = reduced_wires;
+        self.wires.retain(|w| wire_indexes.contains(&w.out.n));
+        self.registers.retain(|r| wire_indexes.contains(&r.q.n));
         self.update_stats();
     }
 }
@@ -729,6 +964,7 @@ pub struct Stats {
     pub line_count: usize,
     pub gatter_count: usize,
     pub level_count: usize,
+    pub register_count: usize,
 }
 
 impl Stats {
@@ -737,6 +973,7 @@ impl Stats {
             line_count: 0,
             gatter_count: 0,
             level_count: 0,
+            register_count: 0,
         }
     }
 
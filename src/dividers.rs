@@ -1,4 +1,4 @@
-use crate::data::{Adder, Mul};
+use crate::data::{Adder, Mul, Truncation};
 use crate::data::{Bit, Bit::One, Bit::Zero, Circuit, Shift};
 use crate::primitives::*;
 use clap::ValueEnum;
@@ -9,10 +9,18 @@ pub struct IntDivResult {
     pub ok: Bit,
 }
 
+pub struct IterativeDivResult {
+    pub result: IntDivResult,
+    pub busy: Bit,
+    pub done: Bit,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, ValueEnum)]
 pub enum Method {
     Newton,
     Goldschmidt,
+    // sequential newton divider which computes one iteration per clock cycle
+    NewtonIterative,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, ValueEnum)]
@@ -50,6 +58,18 @@ pub struct DivInfo {
     pub dividend_size: DividendSize,
     pub sub_method: SubMethod,
     pub number_bits: usize,
+    pub karatsuba_threshold: usize,
+    pub karatsuba_leaf: Mul,
+    pub truncation: Truncation,
+    // k of the modulus 2^k - 1 of the residue check driving the Error output, none without
+    pub residue_check: Option<usize>,
+    // approximate computing, newton iterations left out of those the precision needs
+    pub dropped_iterations: usize,
+    // low columns of the squares and products of every iteration dropped on top of the exact
+    // ones, their partial products are omitted as by truncation, Constant if that is None
+    pub truncated_columns: usize,
+    // the quotient of the reciprocal is output without the correction step selecting q, q + 1 or q - 1
+    pub skip_correction: bool,
 }
 
 impl DivInfo {
@@ -62,7 +82,14 @@ impl DivInfo {
             estimator: Estimate::None,
             dividend_size: DividendSize::Equal,
             sub_method: SubMethod::Seperate,
-            number_bits: 0
+            number_bits: 0,
+            karatsuba_threshold: 64,
+            karatsuba_leaf: Mul::DadaTree,
+            truncation: Truncation::None,
+            residue_check: None,
+            dropped_iterations: 0,
+            truncated_columns: 0,
+            skip_correction: false,
         }
     }
 
@@ -75,15 +102,35 @@ impl DivInfo {
             estimator: Estimate::None,
             dividend_size: DividendSize::Equal,
             sub_method: SubMethod::Seperate,
-            number_bits: 0
+            number_bits: 0,
+            karatsuba_threshold: 64,
+            karatsuba_leaf: Mul::DadaTree,
+            truncation: Truncation::None,
+            residue_check: None,
+            dropped_iterations: 0,
+            truncated_columns: 0,
+            skip_correction: false,
         }
     }
+
+    // true if the divider trades exactness for area
+    pub fn approximate(&self) -> bool {
+        self.dropped_iterations > 0 || self.truncated_columns > 0 || self.skip_correction
+    }
+
+    // true if low partial products of the iterations are omitted, which only the dadda tree
+    // multiplier can do
+    pub fn truncates(&self) -> bool {
+        self.truncation != Truncation::None || self.truncated_columns > 0
+    }
 }
 
 impl Circuit {
     pub fn get_divider_circuit(info: DivInfo) -> Circuit {
         // create a new circuit object to store the circuit and its stats
         let mut circuit = Circuit::new();
+        circuit.info.karatsuba_threshold = info.karatsuba_threshold;
+        circuit.info.karatsuba_leaf = info.karatsuba_leaf;
 
         let bits = info.number_bits;
 
@@ -109,9 +156,19 @@ impl Circuit {
             }
         }
 
+        if info.division_method == Method::Goldschmidt && info.approximate() {
+            panic!("approximations are only implemented for newton dividers");
+        }
+        let mut handshake = None;
         let IntDivResult { mut q, mut r, ok } = match info.division_method {
             Method::Newton => { circuit.div_newton(dividend.clone(), divisor.clone(), info) }
             Method::Goldschmidt => { circuit.goldschmidt_divider(dividend.clone(), divisor.clone(), info) }
+            Method::NewtonIterative => {
+                let start = circuit.new_line();
+                let IterativeDivResult { result, busy, done } = circuit.div_newton_iterative(dividend.clone(), divisor.clone(), start, info);
+                handshake = Some((start, busy, done));
+                result
+            }
         };
 
         if info.dividend_size == DividendSize::DividendDouble {
@@ -125,6 +182,22 @@ impl Circuit {
         circuit.add_as_io(&q, "Q", true);
         circuit.add_as_io(&r, "R_n1", true);
         circuit.add_as_io(&vec![ok], "Valid", true);
+        if let Some(k) = info.residue_check {
+            // an iterative divider is checked while it presents its result
+            let mut checked = ok;
+            if let Some((_, _, done)) = handshake {
+                checked = circuit.and(ok, done);
+            }
+            let error = circuit.scoped("residue_check", |c| c.residue_check(&dividend, &divisor, &q, &r, k, &info));
+            let error = circuit.and(error, checked);
+            circuit.add_as_io(&vec![error], "Error", true);
+        }
+        if let Some((start, busy, done)) = handshake {
+            circuit.add_clock_and_reset();
+            circuit.add_as_io(&vec![start], "start", false);
+            circuit.add_as_io(&vec![busy], "busy", true);
+            circuit.add_as_io(&vec![done], "done", true);
+        }
         circuit
     }
 
@@ -147,44 +220,71 @@ impl Circuit {
         let d_plus = info.defaultadder.add(self, dividend.clone(), divisor.clone(), Zero);
         let d_minus = info.defaultadder.sub(self, dividend.clone(), divisor.clone(), Zero);
         // println!("dividend_alt_1 {:?}, dividend_alt2 {:?}", d_plus, d_minus);
-        let mut shift_left_by = self.lzc(divisor.clone());
+        let mut shift_left_by = self.scoped("lzc", |c| c.lzc(divisor.clone()));
         // we dont need the all or bit
         shift_left_by.pop();
         // println!{"shifting left by {:?}", shift_left_by};
-        let shifted_divisor = self.shift(divisor.clone(), shift_left_by.clone(), Shift::Left, Zero);
+        let shifted_divisor = self.scoped("shift", |c| c.shift(divisor.clone(), shift_left_by.clone(), Shift::Left, Zero));
         let _shifted_dividend =
-            self.shift(dividend.clone(), shift_left_by.clone(), Shift::Left, Zero);
+            self.scoped("shift", |c| c.shift(dividend.clone(), shift_left_by.clone(), Shift::Left, Zero));
         // println!{"after shifting {:?}", shifted_divisor};
 
-        let estimate = match info.estimator {
-            Estimate::Flip5bit => self.flip_estimate(&shifted_divisor),
-            Estimate::Table10bit => self.table_estimate(&shifted_divisor),
-            _ => {
-                let mut estimate = Bit::zeroes(n);
-                estimate.append(&mut vec![One, Zero, Zero]);
-                estimate
-            }
-        };
+        let estimate = self.initial_estimate(&shifted_divisor, info.estimator);
 
         // println!{"after appending {:?}", shifted_divisor};
         // println!{"estimate {:?}", estimate};
         // let mut negative_estimate = self.get_negative(estimate.clone());
         // // println!{"negative_estimate {:?}", negative_estimate};
-        let necessary_its = match info.estimator {
-            Estimate::Flip5bit => (0.max(n as i32 - 6) as f64 / 5_f64 + 1_f64).ceil().log2().ceil() as usize,
-            Estimate::Linear => /* ((n + 1) as f64 / (27f64).log2()).log2().ceil() as usize */ (0.max(n as i32 - 2) as f64 + 1_f64).ceil().log2().ceil() as usize,
-            Estimate::Table10bit => (0.max(n as i32 - 10) as f64 / 5_f64 + 1_f64).ceil().log2().ceil() as usize,
-            Estimate::None => (0.max(n as i32 - 2) as f64 + 1_f64).ceil().log2().ceil() as usize,
-        };
+        let necessary_its = Self::exact_iterations(info, n).saturating_sub(info.dropped_iterations);
         // println!("{necessary_its} :: {n}");
 
         (shift_left_by, estimate, d_plus, d_minus, shifted_divisor, ok, necessary_its, n)
     }
 
+    // the estimate of the reciprocal of the normalized divisor the newton iterations start with,
+    // n + 3 bits with n fractional bits, so 1 is the bit n
+    pub fn initial_estimate(&mut self, shifted_divisor: &Vec<Bit>, estimator: Estimate) -> Vec<Bit> {
+        match estimator {
+            Estimate::Flip5bit => self.scoped("flip_estimate", |c| c.flip_estimate(shifted_divisor)),
+            Estimate::Table10bit => self.scoped("table_estimate", |c| c.table_estimate(shifted_divisor)),
+            _ => {
+                let mut estimate = Bit::zeroes(shifted_divisor.len());
+                estimate.append(&mut vec![One, Zero, Zero]);
+                estimate
+            }
+        }
+    }
+
+    // bits of precision the truncated square and product of one iteration may cost at most,
+    // truncation_guard_columns keeps each of their errors within half a unit on top of the two
+    // units plain truncation costs, (2 + 0.5 + 0.5) / 2 < 2, so one bit more makes up for them
+    fn truncation_precision_loss(truncation: Truncation) -> usize {
+        match truncation {
+            Truncation::None => 0,
+            _ => 1,
+        }
+    }
+
+    // the iterations an exact newton divider of n bits needs, those the exhaustive error bound
+    // of the estimate asks for, every truncated product of an iteration may add its error bound
+    // to the two units the plain truncation of the square and the product may cost, the
+    // precision lost by that is made up by asking for correspondingly more bits
+    pub fn exact_iterations(info: &DivInfo, n: usize) -> usize {
+        let lost_bits = Self::truncation_precision_loss(info.truncation);
+        Circuit::estimator_error_bound(info.estimator, n).iterations(lost_bits)
+    }
+
     #[inline(always)]
     fn div_newton_iterations(&mut self, mut estimate: Vec<Bit>, shifted_divisor: Vec<Bit>, n: usize, necessary_its: usize, info: &DivInfo) -> Vec<Bit> {
+        // the truncated columns leave their bits of the square and the product zero
+        let truncated = info.truncated_columns.min(n);
+        let truncation = match info.truncation {
+            Truncation::None if truncated > 0 => Truncation::Constant,
+            truncation => truncation,
+        };
         for _i in 0..necessary_its {
-            let mut squared = info.defaultmult.square_u(self,estimate.clone(), 3, info.defaultadder);
+            self.enter_scope("newton_iteration");
+            let mut squared = info.defaultmult.square_u_truncated(self, estimate.clone(), 3, n + truncated, truncation, info.defaultadder);
             let mut shifted_estimate = vec![Zero];
             for i in 0..(n + 2) {
                 shifted_estimate.push(estimate.get_or(i, Zero));
@@ -202,13 +302,12 @@ impl Circuit {
             squared.drain(0..(neg_digits - n));
             */
 
-            squared.drain(0..n);
             // println!("squared after discard: {:?}", squared);
 
             match info.sub_method {
                 SubMethod::Seperate => {
-                    let mut p = info.defaultmult.mul_u(self, squared, shifted_divisor.clone(), None, info.defaultadder);
-                    p.drain(0..n);
+                    let mut p = Bit::zeroes(truncated);
+                    p.append(&mut info.defaultmult.mul_u_truncated(self, squared, shifted_divisor.clone(), n, truncation, info.defaultadder));
                     // println!("mult: {:?}", p);
         
                     estimate = info.defaultadder.sub(self, shifted_estimate, p, Zero);
@@ -218,7 +317,10 @@ impl Circuit {
                     }
                 },
                 SubMethod::Fused => {
-                    estimate = self.fused_mul_subtraction(shifted_estimate, squared, shifted_divisor.clone(), true);
+                    let mut padded = Bit::zeroes(truncated);
+                    padded.append(&mut squared);
+                    let squared = padded;
+                    estimate = self.scoped("fused_mul_sub", |c| c.fused_mul_subtraction(shifted_estimate, squared, shifted_divisor.clone(), true, info.defaultmult, info.defaultadder));
                     estimate.drain(0..n);
                 }
                 
@@ -228,6 +330,7 @@ impl Circuit {
             // println!("new estimate: {:?}", estimate);
 
             // negative_estimate = self.get_negative(estimate.clone());
+            self.leave_scope();
         }
         estimate
     }
@@ -243,12 +346,12 @@ impl Circuit {
         let mut shift_right_by = info.defaultadder.sub(self, v_n, shift_left_by, Zero);
         shift_right_by.truncate(num_bits);
         // println!("right_shift_by {:?}", shift_right_by);
-        let mut restored_estimate = self.shift(estimate.clone(), shift_right_by, Shift::Right, One);
+        let mut restored_estimate = self.scoped("shift", |c| c.shift(estimate.clone(), shift_right_by, Shift::Right, One));
         // restored_estimate can be 1 caution!
         restored_estimate.truncate(n + 1);
         // println!("restored_estimate {:?}", restored_estimate);
 
-        let mut q0 = self.mul_unsigned_clean(restored_estimate.clone(), dividend.clone(), None, info.defaultadder);
+        let mut q0 = info.defaultmult.mul_u(self, restored_estimate.clone(), dividend.clone(), None, info.defaultadder);
         // let mut q0 = self.mul_unsigned_clean(estimate.clone(), shifted_dividend.clone(), None);
 
         // println!("q {:?}", q0);
@@ -258,6 +361,15 @@ impl Circuit {
 
         // println!("q {:?}", q0);
 
+        if info.skip_correction {
+            // the quotient can be off by one, the remainder then wraps around
+            let mut qz = info.defaultmult.mul_u(self, q0.clone(), divisor.clone(), None, info.defaultadder);
+            qz.truncate(n);
+            let mut r0 = info.defaultadder.sub(self, dividend.clone(), qz, Zero);
+            r0.truncate(n);
+            return IntDivResult { q: q0, r: r0, ok };
+        }
+
         let mut q_plus = info.defaultadder.add(self,q0.clone(), vec![], One);
         let mut q_minus = info.defaultadder.sub(self,q0.clone(), vec![One], Zero);
 
@@ -266,7 +378,7 @@ impl Circuit {
 
         // println!("q_plus {:?}, q_minus {:?}", q_plus, q_minus);
 
-        let mut qz = self.mul_unsigned_clean(q0.clone(), divisor.clone(), None, info.defaultadder);
+        let mut qz = info.defaultmult.mul_u(self, q0.clone(), divisor.clone(), None, info.defaultadder);
 
         // println!("qz {:?}", qz);
 
@@ -291,8 +403,9 @@ impl Circuit {
 
         // println!("r {:?}, r_plus {:?}, r_minus {:?}", r0, r_plus, r_minus);
 
-        let q_final = self.mux_n_4(&q_plus, &q0, &vec![Zero], &q_minus, (s0, s1));
-        let r_final = self.mux_n_4(&r_plus, &r0, &vec![Zero], &r_minus, (s0, s1));
+        let (q_final, r_final) = self.scoped("correction_mux", |c| {
+            (c.mux_n_4(&q_plus, &q0, &vec![Zero], &q_minus, (s0, s1)), c.mux_n_4(&r_plus, &r0, &vec![Zero], &r_minus, (s0, s1)))
+        });
         // println!("q_final {:?}, r_final {:?}, result is valid: {:?}", q_final, r_final, ok);
 
         IntDivResult {
@@ -310,7 +423,71 @@ impl Circuit {
     ) -> IntDivResult {
         let (shift_left_by, estimate, d_plus, d_minus, shifted_divisor, result_valid, necessary_its, n) = self.div_newton_precalculations(&dividend, &divisor, &info);
         let estimate = self.div_newton_iterations(estimate, shifted_divisor.clone(), n, necessary_its, &info);
-        self.div_newton_correction_step(divisor, dividend, estimate, d_plus, d_minus, shift_left_by, result_valid, n, &info)
+        self.scoped("correction", |c| c.div_newton_correction_step(divisor, dividend, estimate, d_plus, d_minus, shift_left_by, result_valid, n, &info))
+    }
+
+    // multi-cycle newton divider which reuses the circuit of a single iteration:
+    // start loads the operands if the divider is not busy, afterwards one iteration is computed
+    // per cycle, after necessary_its iterations busy falls and done rises, the results stay
+    // valid and done stays high until the next start, rst clears busy and done
+    pub fn div_newton_iterative(
+        &mut self,
+        dividend: Vec<Bit>,
+        divisor: Vec<Bit>,
+        start: Bit,
+        info: DivInfo
+    ) -> IterativeDivResult {
+        let dividend_reg = self.new_register_lines(dividend.len());
+        let divisor_reg = self.new_register_lines(divisor.len());
+        let busy = self.new_register_lines(1)[0];
+        let done = self.new_register_lines(1)[0];
+        // the initial estimate is used until the first iteration has been stored
+        let first = self.new_register_lines(1)[0];
+
+        let (shift_left_by, initial_estimate, d_plus, d_minus, shifted_divisor, result_valid, necessary_its, n) = self.div_newton_precalculations(&dividend_reg, &divisor_reg, &info);
+        let counter_bits = ((usize::BITS - necessary_its.leading_zeros()) as usize).max(1);
+        let counter = self.new_register_lines(counter_bits);
+        let estimate_reg = self.new_register_lines(initial_estimate.len());
+
+        let not_busy = self.not(busy);
+        let accept = self.and(start, not_busy);
+        let counter_running = self.or_of_all(counter.clone());
+        let counter_zero = self.not(counter_running);
+        let iterate = self.and(busy, counter_running);
+        let finish = self.and(busy, counter_zero);
+
+        let estimate = self.mux_n_1(&initial_estimate, &estimate_reg, first);
+        let mut next_estimate = self.div_newton_iterations(estimate.clone(), shifted_divisor.clone(), n, 1, &info);
+        next_estimate.resize(estimate.len(), Zero);
+        let estimate_d = self.mux_n_1(&next_estimate, &estimate_reg, iterate);
+
+        let mut its = Bit::get_bits_vec_usize(necessary_its);
+        its.truncate(counter_bits);
+        let mut decremented = info.defaultadder.sub(self, counter.clone(), vec![One], Zero);
+        decremented.truncate(counter_bits);
+        let counter_d = self.mux_n_1(&decremented, &counter, iterate);
+        let counter_d = self.mux_n_1(&its, &counter_d, accept);
+
+        let dividend_d = self.mux_n_1(&dividend, &dividend_reg, accept);
+        let divisor_d = self.mux_n_1(&divisor, &divisor_reg, accept);
+        let busy_d = self.or(accept, iterate);
+        let not_accept = self.not(accept);
+        let done_held = self.and(done, not_accept);
+        let done_d = self.or(finish, done_held);
+        let not_iterate = self.not(iterate);
+        let first_held = self.and(first, not_iterate);
+        let first_d = self.or(accept, first_held);
+
+        let result = self.scoped("correction", |c| c.div_newton_correction_step(divisor_reg.clone(), dividend_reg.clone(), estimate, d_plus, d_minus, shift_left_by, result_valid, n, &info));
+
+        self.add_registers(&dividend_reg, &dividend_d, false);
+        self.add_registers(&divisor_reg, &divisor_d, false);
+        self.add_registers(&estimate_reg, &estimate_d, false);
+        self.add_registers(&counter, &counter_d, false);
+        self.add_registers(&[first], &[first_d], false);
+        self.add_registers(&[busy, done], &[busy_d, done_d], true);
+
+        IterativeDivResult { result, busy, done }
     }
 
     #[allow(dead_code)]
@@ -336,13 +513,13 @@ impl Circuit {
 
         let d_plus = info.defaultadder.add(self, dividend.clone(), divisor.clone(), Zero);
         let d_minus = info.defaultadder.sub(self, dividend.clone(), divisor.clone(), Zero);
-        let mut shift_left_by = self.lzc(divisor.clone());
-        let mut dividend_shift_left_by = self.lzc(dividend.clone());
+        let mut shift_left_by = self.scoped("lzc", |c| c.lzc(divisor.clone()));
+        let mut dividend_shift_left_by = self.scoped("lzc", |c| c.lzc(dividend.clone()));
         // we dont need the <all bits are zero> bit
         shift_left_by.pop();
         dividend_shift_left_by.pop();
-        let shifted_divisor = self.shift(divisor.clone(), shift_left_by.clone(), Shift::Left, Zero);
-        let shifted_dividend = self.shift(dividend.clone(), dividend_shift_left_by.clone(), Shift::Left, Zero);
+        let shifted_divisor = self.scoped("shift", |c| c.shift(divisor.clone(), shift_left_by.clone(), Shift::Left, Zero));
+        let shifted_dividend = self.scoped("shift", |c| c.shift(dividend.clone(), dividend_shift_left_by.clone(), Shift::Left, Zero));
 
         let mut constant_one = Bit::zeroes(n);
         constant_one.push(One);
@@ -354,22 +531,18 @@ impl Circuit {
         factors.push(x);
         for i in 0..necessary_iters {
             let current_factor = factors[i].clone();
-            let mut new_factor = self.square_u(current_factor, 0, info.defaultadder);
-            new_factor.drain(0..n);
+            let new_factor = info.defaultmult.square_u_truncated(self, current_factor, 0, n, info.truncation, info.defaultadder);
             factors.push(new_factor);
         }
 
-        let mut first_factor = factors.get(0).unwrap_or(&vec![Zero]).clone();
-        first_factor.push(One);
-        let mut p = self.mul_unsigned_clean(first_factor, shifted_dividend, None, info.defaultadder);
-        p.drain(0..n);
+        let first_factor = factors.get(0).unwrap_or(&vec![Zero]).clone();
+        let mut p = self.scoped("goldschmidt_step", |c| c.goldschmidt_step(shifted_dividend, first_factor, n, &info));
+        p.truncate(n+1);
 
         if factors.len() > 1 {
             for i in 1..factors.len() {
-                let mut factor = factors[i].clone();
-                factor.push(One);
-                p = self.mul_unsigned_clean(p, factor, None, info.defaultadder);
-                p.drain(0..(n));
+                let factor = factors[i].clone();
+                p = self.scoped("goldschmidt_step", |c| c.goldschmidt_step(p, factor, n, &info));
                 p.truncate(n+1);
             }
         }
@@ -387,7 +560,7 @@ impl Circuit {
         self.not_all(&mut shift_right_by);
         let mut shift_right_by = info.defaultadder.add(self, shift_right_by.clone(), shift_additional, Zero);
         self.not_all(&mut shift_right_by);
-        let mut q0 = self.shift(p.clone(), shift_right_by, Shift::Right, Zero);
+        let mut q0 = self.scoped("shift", |c| c.shift(p.clone(), shift_right_by, Shift::Right, Zero));
         q0.truncate(n);
 
         let mut q_plus = info.defaultadder.add(self, q0.clone(), vec![], One);
@@ -395,7 +568,7 @@ impl Circuit {
         q_plus.truncate(n);
         q_minus.truncate(n);
 
-        let mut qz = self.mul_unsigned_clean(q0.clone(), divisor.clone(), None, info.defaultadder);
+        let mut qz = info.defaultmult.mul_u(self, q0.clone(), divisor.clone(), None, info.defaultadder);
         qz.truncate(n);
 
         let mut r0 = info.defaultadder.sub(self, dividend.clone(), qz.clone(), Zero);
@@ -412,8 +585,9 @@ impl Circuit {
         r_plus.truncate(n);
         r_minus.truncate(n);
 
-        let q_final = self.mux_n_4(&q_plus, &q0, &dividend, &q_minus, (s0, s1));
-        let r_final = self.mux_n_4(&r_plus, &r0, &constant_zero,&r_minus, (s0, s1));
+        let (q_final, r_final) = self.scoped("correction_mux", |c| {
+            (c.mux_n_4(&q_plus, &q0, &dividend, &q_minus, (s0, s1)), c.mux_n_4(&r_plus, &r0, &constant_zero, &r_minus, (s0, s1)))
+        });
 
         IntDivResult {
             q: q_final,
@@ -421,4 +595,22 @@ impl Circuit {
             ok,
         }
     }
+
+    // computes p * (1 + x) where x is a fraction of n bits and drops the n fractional bits,
+    // the fused variant adds the shifted p directly into the partial products of p * x
+    fn goldschmidt_step(&mut self, p: Vec<Bit>, mut x: Vec<Bit>, n: usize, info: &DivInfo) -> Vec<Bit> {
+        let mut r = match info.sub_method {
+            SubMethod::Seperate => {
+                x.push(One);
+                info.defaultmult.mul_u(self, p, x, None, info.defaultadder)
+            }
+            SubMethod::Fused => {
+                let mut shifted_p = Bit::zeroes(n);
+                shifted_p.extend_from_slice(&p);
+                self.scoped("fused_mul_add", |c| c.fused_mul_add(shifted_p, p, x, info.defaultmult, info.defaultadder))
+            }
+        };
+        r.drain(0..n.min(r.len()));
+        r
+    }
 }
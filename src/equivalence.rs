@@ -0,0 +1,528 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+use rand::random;
+
+use crate::data::{Bit, Circuit, Gate};
+use crate::sat::{Cnf, Lit, SatResult, Solver};
+
+// the literals of named inputs or outputs, lowest bit first
+pub type Ports = Vec<(String, Vec<Lit>)>;
+
+// a gate of an encoded circuit, var is true if a and b are, or if they differ for xor
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Definition {
+    var: usize,
+    xor: bool,
+    a: Lit,
+    b: Lit,
+}
+
+// the conjunctive normal form of two circuits sharing their inputs and register outputs,
+// differ is true if any output or register input of the circuits differs
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Miter {
+    pub cnf: Cnf,
+    pub inputs: Ports,
+    pub outputs: Vec<(String, Vec<Lit>, Vec<Lit>)>,
+    pub differ: Lit,
+    definitions: Vec<Definition>,
+    // number of definitions of the first circuit
+    first: usize,
+    // index of the first clause comparing the outputs
+    compared: usize,
+}
+
+impl Miter {
+    // the miter in the dimacs format, satisfiable if and only if the circuits differ,
+    // the comments name the variables of the inputs so a model can be read back
+    pub fn dimacs(&self) -> String {
+        let mut cnf = self.cnf.clone();
+        cnf.add_clause(&[self.differ]);
+        cnf.dimacs(&input_comments(&self.inputs))
+    }
+}
+
+// dimacs comments naming the variables of every input, lowest bit first
+pub fn input_comments(inputs: &[(String, Vec<Lit>)]) -> Vec<String> {
+    inputs
+        .iter()
+        .map(|(name, lits)| {
+            let vars: Vec<String> = lits.iter().map(|l| l.to_dimacs().to_string()).collect();
+            format!("input {name} {}", vars.join(" "))
+        })
+        .collect()
+}
+
+// input values for which two circuits differ, with the values of every differing output
+// of the first and the second circuit, bit i of a value is entry i
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Counterexample {
+    pub inputs: Vec<(String, Vec<bool>)>,
+    pub outputs: Vec<(String, Vec<bool>, Vec<bool>)>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Equivalence {
+    Equivalent,
+    Different(Counterexample),
+}
+
+// the value of the bits as hexadecimal number, bit i is entry i
+pub fn hex(bits: &[bool]) -> String {
+    let digits: String = bits
+        .chunks(4)
+        .rev()
+        .map(|nibble| {
+            let v = nibble.iter().enumerate().fold(0, |v, (i, &b)| v | (b as u32) << i);
+            std::char::from_digit(v, 16).unwrap()
+        })
+        .collect();
+    format!("0x{digits}")
+}
+
+impl fmt::Display for Equivalence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Equivalence::Equivalent => writeln!(f, "circuits are equivalent"),
+            Equivalence::Different(cex) => {
+                writeln!(f, "circuits differ for")?;
+                for (name, bits) in &cex.inputs {
+                    writeln!(f, "  {name} = {}", hex(bits))?;
+                }
+                for (name, first, second) in &cex.outputs {
+                    writeln!(f, "  {name}: {} != {}", hex(first), hex(second))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// the literal of a bit of an encoded circuit
+fn literal(bit: Bit, lines: &HashMap<usize, Lit>) -> Lit {
+    match bit {
+        Bit::Zero => Cnf::constant(false),
+        Bit::One => Cnf::constant(true),
+        Bit::Var(l) => lines[&l.n],
+    }
+}
+
+impl Circuit {
+    // tseitin encoding of the gates, lines maps the lines of inputs and register outputs to
+    // literals and gets the literal of every line, lines without one get new variables,
+    // inverters need no variable of their own and or gates are and gates of the inverted inputs
+    fn encode_definitions(&self, cnf: &mut Cnf, lines: &mut HashMap<usize, Lit>) -> Vec<Definition> {
+        if let Some(l) = self.zero_wire {
+            lines.insert(l.n, Cnf::constant(false));
+        }
+        if let Some(l) = self.one_wire {
+            lines.insert(l.n, Cnf::constant(true));
+        }
+        let mut definitions = Vec::with_capacity(self.wires.len());
+        for wire in &self.wires {
+            for l in wire.gate.inputs() {
+                lines.entry(l.n).or_insert_with(|| cnf.new_var());
+            }
+            let lit = match wire.gate {
+                Gate::Not(l) => !lines[&l.n],
+                Gate::And(l1, l2) | Gate::Or(l1, l2) | Gate::Xor(l1, l2) => {
+                    let or = matches!(wire.gate, Gate::Or(..));
+                    let (a, b) = (lines[&l1.n].xor(or), lines[&l2.n].xor(or));
+                    let xor = matches!(wire.gate, Gate::Xor(..));
+                    let r = if xor { cnf.xor(a, b) } else { cnf.and(a, b) };
+                    definitions.push(Definition { var: r.var(), xor, a, b });
+                    r.xor(or)
+                }
+            };
+            lines.insert(wire.out.n, lit);
+        }
+        definitions
+    }
+
+    // tseitin encoding of a combinational circuit, returns the literals of its inputs and outputs
+    pub fn encode(&self, cnf: &mut Cnf) -> (Ports, Ports) {
+        if !self.registers.is_empty() {
+            panic!("only combinational circuits can be encoded, this one has {} registers", self.registers.len());
+        }
+        let mut lines = HashMap::new();
+        let mut inputs = Vec::new();
+        for input in &self.inputs {
+            let lits: Vec<Lit> = input.bits.iter().map(|_| cnf.new_var()).collect();
+            for (bit, &lit) in input.bits.iter().zip(&lits) {
+                if let Bit::Var(l) = bit {
+                    lines.insert(l.n, lit);
+                }
+            }
+            inputs.push((input.name.clone(), lits));
+        }
+        self.encode_definitions(cnf, &mut lines);
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|output| (output.name.clone(), output.bits.iter().map(|&bit| literal(bit, &lines)).collect()))
+            .collect();
+        (inputs, outputs)
+    }
+
+    // the miter of two circuits with inputs and outputs of the same names and sizes, the
+    // registers are matched by their position, so both circuits need the same number of them
+    pub fn miter(&self, other: &Circuit) -> Miter {
+        if self.registers.len() != other.registers.len() {
+            panic!("circuits have {} and {} registers", self.registers.len(), other.registers.len());
+        }
+        let mut cnf = Cnf::new();
+        let mut lines = (HashMap::new(), HashMap::new());
+        let mut inputs = Vec::new();
+        for input in &self.inputs {
+            let Some(io) = other.inputs.iter().find(|io| io.name == input.name) else {
+                panic!("second circuit has no input {}", input.name);
+            };
+            if io.bits.len() != input.bits.len() {
+                panic!("input {} has {} and {} bits", input.name, input.bits.len(), io.bits.len());
+            }
+            let lits: Vec<Lit> = input.bits.iter().map(|_| cnf.new_var()).collect();
+            for ((bit, other_bit), &lit) in input.bits.iter().zip(&io.bits).zip(&lits) {
+                if let (Bit::Var(l1), Bit::Var(l2)) = (bit, other_bit) {
+                    lines.0.insert(l1.n, lit);
+                    lines.1.insert(l2.n, lit);
+                }
+            }
+            inputs.push((input.name.clone(), lits));
+        }
+        if !self.registers.is_empty() {
+            let lits: Vec<Lit> = self.registers.iter().map(|_| cnf.new_var()).collect();
+            for ((r1, r2), &lit) in self.registers.iter().zip(&other.registers).zip(&lits) {
+                if r1.reset != r2.reset {
+                    panic!("registers {} and {} differ in their reset", r1.q.n, r2.q.n);
+                }
+                lines.0.insert(r1.q.n, lit);
+                lines.1.insert(r2.q.n, lit);
+            }
+            inputs.push(("registers".to_string(), lits));
+        }
+
+        let mut definitions = self.encode_definitions(&mut cnf, &mut lines.0);
+        let first = definitions.len();
+        definitions.extend(other.encode_definitions(&mut cnf, &mut lines.1));
+
+        let mut outputs = Vec::new();
+        for output in &self.outputs {
+            let Some(io) = other.outputs.iter().find(|io| io.name == output.name) else {
+                panic!("second circuit has no output {}", output.name);
+            };
+            if io.bits.len() != output.bits.len() {
+                panic!("output {} has {} and {} bits", output.name, output.bits.len(), io.bits.len());
+            }
+            let first: Vec<Lit> = output.bits.iter().map(|&bit| literal(bit, &lines.0)).collect();
+            let second = io.bits.iter().map(|&bit| literal(bit, &lines.1)).collect();
+            outputs.push((output.name.clone(), first, second));
+        }
+        if !self.registers.is_empty() {
+            let first: Vec<Lit> = self.registers.iter().map(|r| literal(r.d, &lines.0)).collect();
+            let second = other.registers.iter().map(|r| literal(r.d, &lines.1)).collect();
+            outputs.push(("registers".to_string(), first, second));
+        }
+
+        let compared = cnf.clauses.len();
+        let mut differences = Vec::new();
+        for (_, first, second) in &outputs {
+            for (&a, &b) in first.iter().zip(second) {
+                if a != b {
+                    differences.push(cnf.xor(a, b));
+                }
+            }
+        }
+        let differ = cnf.any(&differences);
+        Miter {
+            cnf,
+            inputs,
+            outputs,
+            differ,
+            definitions,
+            first,
+            compared,
+        }
+    }
+
+    // proves that two circuits compute the same outputs and register inputs for all inputs
+    // and register states or finds inputs for which they differ, see miter
+    pub fn check_equivalence(&self, other: &Circuit) -> Equivalence {
+        let miter = self.miter(other);
+        let mut cones = Cones::new(&miter);
+        sweep(&miter, &mut cones);
+        for (_, first, second) in &miter.outputs {
+            for l in first.iter().chain(second) {
+                cones.load(l.var());
+            }
+        }
+        for clause in &miter.cnf.clauses[miter.compared..] {
+            cones.solver.add_clause(clause);
+        }
+        cones.solver.unrestrict_decisions();
+        let solver = &mut cones.solver;
+        match solver.solve(&[miter.differ], None) {
+            SatResult::Unsat => Equivalence::Equivalent,
+            _ => {
+                let value = |l: &Lit| solver.model_value(l.var()) ^ l.is_negated();
+                let inputs = miter
+                    .inputs
+                    .iter()
+                    .map(|(name, lits)| (name.clone(), lits.iter().map(value).collect()))
+                    .collect();
+                let outputs = miter
+                    .outputs
+                    .iter()
+                    .map(|(name, first, second)| {
+                        let first: Vec<bool> = first.iter().map(value).collect();
+                        let second: Vec<bool> = second.iter().map(value).collect();
+                        (name.clone(), first, second)
+                    })
+                    .filter(|(_, first, second)| first != second)
+                    .collect();
+                Equivalence::Different(Counterexample { inputs, outputs })
+            }
+        }
+    }
+}
+
+// a solver holding the clauses of the gates in the fanin cones of the variables loaded so
+// far, so decisions and conflicts stay in the logic a query is about
+struct Cones<'a> {
+    solver: Solver,
+    definitions: &'a [Definition],
+    // index of the definition of every variable defined by a gate
+    definition_of: HashMap<usize, usize>,
+    loaded: Vec<bool>,
+    // the cone collected last contains the variables marked with generation
+    marks: Vec<usize>,
+    generation: usize,
+}
+
+impl<'a> Cones<'a> {
+    fn new(miter: &'a Miter) -> Self {
+        let mut solver = Solver::new();
+        solver.reserve(miter.cnf.vars);
+        solver.add_clause(&[Cnf::constant(true)]);
+        Cones {
+            solver,
+            definitions: &miter.definitions,
+            definition_of: miter.definitions.iter().enumerate().map(|(idx, d)| (d.var, idx)).collect(),
+            loaded: vec![false; miter.cnf.vars],
+            marks: vec![0; miter.cnf.vars],
+            generation: 0,
+        }
+    }
+
+    fn load(&mut self, var: usize) {
+        let mut stack = vec![var];
+        while let Some(var) = stack.pop() {
+            if self.loaded[var] {
+                continue;
+            }
+            self.loaded[var] = true;
+            let Some(&idx) = self.definition_of.get(&var) else {
+                continue;
+            };
+            let d = self.definitions[idx];
+            let r = Lit::new(d.var, false);
+            match d.xor {
+                true => {
+                    self.solver.add_clause(&[!r, d.a, d.b]);
+                    self.solver.add_clause(&[!r, !d.a, !d.b]);
+                    self.solver.add_clause(&[r, !d.a, d.b]);
+                    self.solver.add_clause(&[r, d.a, !d.b]);
+                }
+                false => {
+                    self.solver.add_clause(&[!r, d.a]);
+                    self.solver.add_clause(&[!r, d.b]);
+                    self.solver.add_clause(&[r, !d.a, !d.b]);
+                }
+            }
+            stack.push(d.a.var());
+            stack.push(d.b.var());
+        }
+    }
+
+    // loads the fanin cones of vars and lets the solver decide on their variables only,
+    // closer ones first
+    fn restrict(&mut self, vars: &[usize]) {
+        self.generation += 1;
+        let mut cone = Vec::new();
+        let mut queue: VecDeque<usize> = vars.iter().copied().collect();
+        while let Some(var) = queue.pop_front() {
+            if self.marks[var] == self.generation {
+                continue;
+            }
+            self.marks[var] = self.generation;
+            self.load(var);
+            cone.push(var);
+            if let Some(&idx) = self.definition_of.get(&var) {
+                queue.push_back(self.definitions[idx].a.var());
+                queue.push_back(self.definitions[idx].b.var());
+            }
+        }
+        self.solver.restrict_decisions(&cone);
+    }
+}
+
+// sat sweeping, gates with the same values for random input patterns are candidates for
+// equivalence, every gate of the second circuit is proven equivalent to one of its first
+// candidates in the first circuit with a limited number of conflicts and then merged with
+// it by two clauses, that turns the final query into mostly unit propagation for circuits
+// sharing structure, inputs found while disproving a candidate become new patterns
+// separating the gates, gates of the same circuit are not compared as proving them equal
+// often takes reasoning over whole iterations of the divider and does not help the miter
+fn sweep(miter: &Miter, cones: &mut Cones) {
+    const WORDS: usize = 4;
+    const CANDIDATES: usize = 3;
+    const CONFLICT_LIMIT: usize = 100;
+    let Some(last) = miter.definitions.last() else {
+        return;
+    };
+    // the variables after the last gate only compare the outputs
+    let vars = last.var + 1;
+    let mut signatures: Vec<Vec<u64>> = vec![Vec::new(); vars];
+    let mut defined = vec![false; vars];
+    for d in &miter.definitions {
+        defined[d.var] = true;
+    }
+    // the constant, the inputs, the register outputs and the variables of undriven lines
+    let mut leaves: Vec<usize> = vec![0];
+    signatures[0] = vec![u64::MAX; WORDS];
+    for var in 1..vars {
+        if !defined[var] {
+            signatures[var] = (0..WORDS).map(|_| random::<u64>()).collect();
+            leaves.push(var);
+        }
+    }
+    let value = |signatures: &Vec<Vec<u64>>, l: Lit, word: usize| -> u64 {
+        let v = signatures[l.var()][word];
+        if l.is_negated() { !v } else { v }
+    };
+    for d in &miter.definitions {
+        signatures[d.var] = (0..WORDS)
+            .map(|word| {
+                let (a, b) = (value(&signatures, d.a, word), value(&signatures, d.b, word));
+                if d.xor { a ^ b } else { a & b }
+            })
+            .collect();
+    }
+    // signatures equal up to inversion share a key, phase tells if the signature is inverted
+    let key = |signature: &Vec<u64>| -> (Vec<u64>, bool) {
+        let phase = signature[0] & 0x1 == 1;
+        (signature.iter().map(|&w| if phase { !w } else { w }).collect(), phase)
+    };
+    let classes = |signatures: &Vec<Vec<u64>>, candidates: &[usize]| -> HashMap<Vec<u64>, Vec<usize>> {
+        let mut classes: HashMap<Vec<u64>, Vec<usize>> = HashMap::new();
+        for &var in candidates {
+            classes.entry(key(&signatures[var]).0).or_default().push(var);
+        }
+        classes
+    };
+
+    let candidates: Vec<usize> = leaves.iter().copied().chain(miter.definitions[..miter.first].iter().map(|d| d.var)).collect();
+    let mut members = classes(&signatures, &candidates);
+    // number of patterns found by the solver in the last word of every signature
+    let mut found = 64;
+    // gates of the first circuit by operation and inputs, inverted inputs of xor gates invert
+    // their output instead
+    let normalized = |d: &Definition| -> ((bool, Lit, Lit), bool) {
+        let (a, b, phase) = match d.xor {
+            true => (Lit::new(d.a.var(), false), Lit::new(d.b.var(), false), d.a.is_negated() != d.b.is_negated()),
+            false => (d.a, d.b, false),
+        };
+        ((d.xor, a.min(b), a.max(b)), phase)
+    };
+    let structure: HashMap<(bool, Lit, Lit), Lit> = miter.definitions[..miter.first]
+        .iter()
+        .map(|d| {
+            let (k, phase) = normalized(d);
+            (k, Lit::new(d.var, phase))
+        })
+        .collect();
+    // the literal every gate of the second circuit was merged with
+    let mut merged: HashMap<usize, Lit> = HashMap::new();
+    let others = miter.definitions.len() - miter.first;
+    for (idx, d) in miter.definitions[miter.first..].iter().enumerate() {
+        // a gate whose inputs are merged with the inputs of a gate of the first circuit doing
+        // the same is merged with it without asking the solver
+        let input = |l: Lit| match defined[l.var()] {
+            true => merged.get(&l.var()).map(|m| m.xor(l.is_negated())),
+            false => Some(l),
+        };
+        if let (Some(a), Some(b)) = (input(d.a), input(d.b)) {
+            let (k, phase) = normalized(&Definition { var: d.var, xor: d.xor, a, b });
+            if let Some(&twin) = structure.get(&k) {
+                let (var, target) = (Lit::new(d.var, false), twin.xor(phase));
+                cones.solver.add_clause(&[!var, target]);
+                cones.solver.add_clause(&[var, !target]);
+                merged.insert(d.var, target);
+                continue;
+            }
+        }
+        // the gate at the same relative position of the first circuit is the likely twin
+        let expected = miter.definitions[idx * miter.first / others].var;
+        let mut tried = 0;
+        loop {
+            let (k, phase) = key(&signatures[d.var]);
+            let Some(mut class) = members.get(&k).cloned() else {
+                break;
+            };
+            class.sort_by_key(|&var| var.abs_diff(expected));
+            let Some(&r) = class.get(tried) else {
+                break;
+            };
+            if tried == CANDIDATES {
+                break;
+            }
+            let var = Lit::new(d.var, false);
+            let target = Lit::new(r, phase != key(&signatures[r]).1);
+            cones.restrict(&[d.var, r]);
+            let solver = &mut cones.solver;
+            let first = solver.solve(&[var, !target], Some(CONFLICT_LIMIT));
+            let second = match first {
+                SatResult::Unsat => solver.solve(&[!var, target], Some(CONFLICT_LIMIT)),
+                result => result,
+            };
+            match second {
+                SatResult::Unsat => {
+                    solver.add_clause(&[!var, target]);
+                    solver.add_clause(&[var, !target]);
+                    merged.insert(d.var, target);
+                    break;
+                }
+                SatResult::Sat => {
+                    // the inputs of the model are simulated, gates outside the loaded cones
+                    // have no meaningful value in it
+                    if found == 64 {
+                        for signature in signatures.iter_mut() {
+                            signature.push(0);
+                        }
+                        found = 0;
+                    }
+                    let word = signatures[0].len() - 1;
+                    for &leaf in &leaves[1..] {
+                        if solver.model_value(leaf) {
+                            signatures[leaf][word] |= 1 << found;
+                        }
+                    }
+                    signatures[0][word] |= 1 << found;
+                    for g in &miter.definitions {
+                        let (a, b) = (value(&signatures, g.a, word), value(&signatures, g.b, word));
+                        let v = if g.xor { a ^ b } else { a & b };
+                        signatures[g.var][word] |= v & (1 << found);
+                    }
+                    found += 1;
+                    members = classes(&signatures, &candidates);
+                    // the pattern separates the gates unless the solver is wrong
+                    if key(&signatures[d.var]).0 == key(&signatures[r]).0 {
+                        panic!("sat sweeping found no difference between {} and {r}", d.var);
+                    }
+                    tried = 0;
+                }
+                SatResult::Unknown => tried += 1,
+            }
+        }
+    }
+}
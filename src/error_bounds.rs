@@ -0,0 +1,176 @@
+use std::fmt;
+
+use crate::data::{Bit, Circuit};
+use crate::dividers::Estimate;
+use crate::simulation::{pack, unpack, Simulation};
+
+// leading bits of the normalized divisor the initial estimates are evaluated for exhaustively,
+// the estimators only look at a few of them, for longer divisors the lower bits are bounded
+const ANALYZED_BITS: usize = 16;
+
+// the relative errors |1 - x * d| of an initial estimate x for the normalized divisors d in
+// [0.5, 1) of a width, exact up to ANALYZED_BITS bits and upper bounds beyond, a newton
+// iteration squares the relative error
+#[derive(Debug, PartialEq, Clone)]
+pub struct ErrorBound {
+    pub estimator: Estimate,
+    pub bits: usize,
+    pub max_error: f64,
+    // the leading ANALYZED_BITS bits of a divisor with the largest error
+    pub worst_divisor: u128,
+    pub exact: bool,
+    // the correction step gets every quotient right without any iteration, only known for
+    // exact bounds
+    estimate_suffices: bool,
+}
+
+impl ErrorBound {
+    // bits of the reciprocal the initial estimate gets right for every divisor
+    pub fn precise_bits(&self) -> f64 {
+        -self.max_error.log2()
+    }
+
+    // the fewest newton iterations after which the relative error e is below 2^-bits, lost_bits
+    // of precision are made up for the truncated products, the correction step takes the
+    // quotient floor(X * R / 2^bits) with the reciprocal X of the divisor D restored to bits
+    // fractional bits and fixes it if it is off by one, e moves X by 2^bits * e / D < 1 / D, so
+    // no further than the truncated reciprocal as 2^bits / D is a multiple of 1 / D, a relative
+    // error below the inverse of the quotient is not enough, the 6 bit flip estimate alone gives
+    // 5 for 63 / 9, without any iteration the correction step may cope with the estimate anyway
+    pub fn iterations(&self, lost_bits: usize) -> usize {
+        if self.max_error >= 1.0 {
+            panic!("newton iterations do not converge for the {:?} estimate, relative error {}", self.estimator, self.max_error);
+        }
+        if self.estimate_suffices {
+            return 0;
+        }
+        let mut its = 0;
+        while self.precise_bits() * ((1_u128 << its) as f64) < (self.bits + lost_bits) as f64 {
+            its += 1;
+        }
+        its
+    }
+}
+
+impl fmt::Display for ErrorBound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bound = if self.exact { "" } else { "at most " };
+        write!(
+            f,
+            "{:?} estimate for {} bits: relative error {bound}2^-{:.2} for divisors 0x{:x}.., {} iterations needed",
+            self.estimator,
+            self.bits,
+            self.precise_bits(),
+            self.worst_divisor,
+            self.iterations(0)
+        )
+    }
+}
+
+impl Circuit {
+    // the initial estimate on its own, input D is the normalized divisor, output X the estimate
+    fn estimator_circuit(estimator: Estimate, bits: usize) -> Circuit {
+        let mut circuit = Circuit::new();
+        let divisor: Vec<Bit> = (0..bits).map(|_| circuit.new_line()).collect();
+        let estimate = circuit.initial_estimate(&divisor, estimator);
+        circuit.add_as_io(&divisor, "D", false);
+        circuit.add_as_io(&estimate, "X", true);
+        circuit
+    }
+
+    // the number of leading divisor bits the estimate bits from lowest up depend on
+    fn leading_divisor_bits_read(&self, lowest: usize) -> usize {
+        let divisor = &self.inputs.iter().find(|io| io.name == "D").unwrap().bits;
+        // the lowest divisor bit in the fanin of every line
+        let mut fanin = vec![usize::MAX; self.stats.line_count];
+        for (idx, bit) in divisor.iter().enumerate() {
+            if let Bit::Var(l) = bit {
+                fanin[l.n] = idx;
+            }
+        }
+        for wire in &self.wires {
+            fanin[wire.out.n] = wire.gate.inputs().iter().map(|l| fanin[l.n]).min().unwrap();
+        }
+        let estimate = &self.outputs.iter().find(|io| io.name == "X").unwrap().bits;
+        match estimate[lowest..].iter().filter_map(|bit| if let Bit::Var(l) = bit { Some(fanin[l.n]) } else { None }).min() {
+            Some(idx) if idx != usize::MAX => divisor.len() - idx,
+            _ => 0,
+        }
+    }
+
+    // simulates the estimator for every combination of the leading divisor bits, with the
+    // lower bits cleared that is exact for the divisors ending in zeros, the lower bits of any other divisor d move it by less than
+    // one unit u of the last analyzed bit and, as they only reach the estimate x below the
+    // next unit, x by less than 2u, so its error lies between 1 - x * d and 1 - (x + 2u) * (d + u)
+    pub fn estimator_error_bound(estimator: Estimate, bits: usize) -> ErrorBound {
+        let analyzed = bits.min(ANALYZED_BITS);
+        let lower = bits - analyzed;
+        let circuit = Circuit::estimator_circuit(estimator, bits);
+        let mut sim = Simulation::new(&circuit);
+        let one = 1_i128 << (2 * analyzed);
+        // an estimate that reads no more than the analyzed bits only has to be simulated for
+        // the prefixes it reads, the analyzed divisors sharing a prefix share the estimate,
+        // the bound leaves the lowest analyzed estimate bit open anyway unless it is exact
+        let read = circuit.leading_divisor_bits_read(if lower > 0 { lower + 1 } else { 0 });
+        let simulated = if read > analyzed { analyzed } else { read.max(1) };
+        let fills: &[u64] = if read > analyzed { &[0, u64::MAX] } else { &[0] };
+        let mut estimates = vec![Vec::new(); fills.len()];
+        let prefixes: Vec<u128> = (1 << (simulated - 1)..1 << simulated).collect();
+        for chunk in prefixes.chunks(64) {
+            for (&fill, estimates) in fills.iter().zip(estimates.iter_mut()) {
+                let mut words = vec![fill; bits - simulated];
+                words.extend(pack(chunk, simulated));
+                sim.set_input("D", &words);
+                sim.eval();
+                estimates.extend(unpack(&sim.output("X")[lower..]).into_iter().take(chunk.len()));
+            }
+        }
+        let prefix = |d: i128| ((d >> (analyzed - simulated)) - (1 << (simulated - 1))) as usize;
+
+        let mut worst = (0, 0);
+        for d in 1_i128 << (analyzed - 1)..1 << analyzed {
+            let x = estimates[0][prefix(d)] as i128;
+            let error = (one - x * d).abs();
+            if error > worst.0 {
+                worst = (error, d as u128);
+            }
+            if lower > 0 {
+                if read > analyzed && estimates[0][prefix(d)] >> 1 != estimates[1][prefix(d)] >> 1 {
+                    panic!("the {estimator:?} estimate depends on divisor bits below the leading {analyzed} bits");
+                }
+                let low = x & !1;
+                let bound = (one - low * d).abs().max((one - (low + 2) * (d + 1)).abs());
+                if bound > worst.0 {
+                    worst = (bound, d as u128);
+                }
+            }
+        }
+
+        // without an iteration the correction step takes the quotient q' = floor(X * R / 2^bits)
+        // of the estimate x of d = D << k restored to the reciprocal X = x >> (bits - k) of D,
+        // for X = 2^bits / D - b with b > 0 q' >= q - 1 holds for every dividend R if it holds for
+        // the largest multiple m of D, that is m * b < 2^bits, for b < 0 q' <= q + 1 holds if
+        // (2^bits - 1) * -b < 2^bits
+        let largest = (1_i128 << analyzed) - 1;
+        let estimate_suffices = lower == 0
+            && (1..=largest).all(|divisor| {
+                let length = 128 - divisor.leading_zeros() as usize;
+                let restored = estimates[0][prefix(divisor << (bits - length))] as i128 >> length;
+                let below = (1_i128 << bits) - divisor * restored;
+                let multiple = largest - largest % divisor;
+                if below >= 0 {
+                    multiple * below < divisor << bits
+                } else {
+                    largest * -below < divisor << bits
+                }
+            });
+        ErrorBound {
+            estimator,
+            bits,
+            max_error: worst.0 as f64 / one as f64,
+            worst_divisor: worst.1,
+            exact: lower == 0,
+            estimate_suffices,
+        }
+    }
+}
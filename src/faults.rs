@@ -0,0 +1,263 @@
+use std::fmt;
+
+use crate::data::{Bit, Circuit, Gate, Line};
+use crate::simulation::{eval_gate, pack, Simulation};
+
+// a line stuck at a constant value, every input bit and gate output can be stuck at 0 or 1
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Fault {
+    pub line: Line,
+    pub stuck_at: bool,
+}
+
+// how many stuck-at faults a set of input vectors detects, a fault is detected if some
+// vector makes an output differ from the fault free circuit
+#[derive(Debug, PartialEq, Clone)]
+pub struct FaultCoverage {
+    pub vectors: usize,
+    pub faults: usize,
+    pub detected: usize,
+    // vectors that are the first to detect some fault, the others could be left out
+    pub useful_vectors: usize,
+    // path, faults and undetected faults of the components with undetected faults
+    pub components: Vec<(String, usize, usize)>,
+    // names of the undetected faults like _42_ stuck-at-1 or D[3] stuck-at-0
+    pub undetected: Vec<String>,
+}
+
+impl FaultCoverage {
+    pub fn coverage(&self) -> f64 {
+        match self.faults {
+            0 => 1.0,
+            faults => self.detected as f64 / faults as f64,
+        }
+    }
+}
+
+impl fmt::Display for FaultCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} vectors detect {} of {} stuck-at faults, coverage {:.2}%, {} vectors detect new faults",
+            self.vectors,
+            self.detected,
+            self.faults,
+            100.0 * self.coverage(),
+            self.useful_vectors
+        )?;
+        if self.components.is_empty() {
+            return Ok(());
+        }
+        let width = self.components.iter().map(|(path, _, _)| path.len()).max().unwrap_or(0).max(9);
+        writeln!(f, "{:width$} {:>8} {:>10} {:>9}", "component", "faults", "undetected", "coverage")?;
+        for (path, faults, undetected) in &self.components {
+            let coverage = 100.0 * (faults - undetected) as f64 / *faults as f64;
+            writeln!(f, "{path:width$} {faults:>8} {undetected:>10} {coverage:>8.2}%")?;
+        }
+        // the list of a poor test set gets long
+        let shown = self.undetected.len().min(20);
+        write!(f, "undetected: {}", self.undetected[..shown].join(", "))?;
+        match self.undetected.len() - shown {
+            0 => writeln!(f),
+            more => writeln!(f, " and {more} more"),
+        }
+    }
+}
+
+// true if one of the gate's inputs differs from the fault free circuit
+fn touched(gate: Gate, changed: &[bool]) -> bool {
+    match gate {
+        Gate::Not(l) => changed[l.n],
+        Gate::And(l1, l2) | Gate::Or(l1, l2) | Gate::Xor(l1, l2) => changed[l1.n] || changed[l2.n],
+    }
+}
+
+impl Circuit {
+    // stuck-at-0 and stuck-at-1 of every input bit followed by those of every gate output
+    pub fn faults(&self) -> Vec<Fault> {
+        let input_lines = self.inputs.iter().flat_map(|io| io.bits.iter()).filter_map(|bit| match bit {
+            Bit::Var(l) => Some(*l),
+            _ => None,
+        });
+        input_lines
+            .chain(self.wires.iter().map(|wire| wire.out))
+            .flat_map(|line| [false, true].map(|stuck_at| Fault { line, stuck_at }))
+            .collect()
+    }
+
+    // one random number per input and vector
+    pub fn random_vectors(&self, count: usize) -> Vec<Vec<u128>> {
+        let masks: Vec<u128> = self
+            .inputs
+            .iter()
+            .map(|io| match io.bits.len() {
+                128.. => u128::MAX,
+                bits => (1 << bits) - 1,
+            })
+            .collect();
+        (0..count).map(|_| masks.iter().map(|mask| rand::random::<u128>() & mask).collect()).collect()
+    }
+
+    // vectors in the format of a $readmemh file, one line per vector holding a hexadecimal
+    // number for every input in the order of the module's ports, // starts a comment
+    pub fn parse_vectors(&self, text: &str) -> Vec<Vec<u128>> {
+        let mut vectors = Vec::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.split("//").next().unwrap_or("");
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.is_empty() {
+                continue;
+            }
+            if fields.len() != self.inputs.len() {
+                panic!("line {} holds {} numbers, the circuit has {} inputs", idx + 1, fields.len(), self.inputs.len());
+            }
+            let vector: Vec<u128> = fields
+                .iter()
+                .zip(&self.inputs)
+                .map(|(field, io)| {
+                    let n = u128::from_str_radix(&field.replace('_', ""), 16)
+                        .unwrap_or_else(|_| panic!("line {} holds {field}, which is no hexadecimal number of at most 128 bits", idx + 1));
+                    if io.bits.len() < 128 && n >> io.bits.len() != 0 {
+                        panic!("line {} holds {field}, which does not fit the {} bits of input {}", idx + 1, io.bits.len(), io.name);
+                    }
+                    n
+                })
+                .collect();
+            vectors.push(vector);
+        }
+        vectors
+    }
+
+    // for every line the index of the first gate its value can reach, the gates behind its
+    // driver in topological order
+    pub fn first_reachable_wire(&self) -> Vec<usize> {
+        let mut first_wire = vec![0; self.stats.line_count];
+        for (idx, wire) in self.wires.iter().enumerate() {
+            first_wire[wire.out.n] = idx + 1;
+        }
+        first_wire
+    }
+
+    // like d[3] stuck-at-0 with the line named as in the verilog output
+    pub fn fault_name(&self, fault: Fault) -> String {
+        format!("{} stuck-at-{}", fault.line.to_verilog(&self.io_lines), fault.stuck_at as u8)
+    }
+
+    // parallel pattern fault simulation of the faults not detected yet, the fault free circuit
+    // is simulated for 64 vectors at once and every fault only reevaluates the gates its line
+    // changes, in topological order starting behind the faulty gate, marks the detected faults
+    // and returns for every vector if it is the first to detect one of them
+    pub fn fault_simulate(&self, faults: &[Fault], vectors: &[Vec<u128>], detected: &mut [bool]) -> Vec<bool> {
+        if !self.registers.is_empty() {
+            panic!("only combinational circuits can be fault simulated, this one has {} registers", self.registers.len());
+        }
+        let first_wire = self.first_reachable_wire();
+        let output_lines: Vec<usize> = self
+            .outputs
+            .iter()
+            .flat_map(|io| io.bits.iter())
+            .filter_map(|bit| match bit {
+                Bit::Var(l) => Some(l.n),
+                _ => None,
+            })
+            .collect();
+
+        let mut useful = vec![false; vectors.len()];
+        let mut sim = Simulation::new(self);
+        let mut changed = vec![false; self.stats.line_count];
+        for (chunk_idx, chunk) in vectors.chunks(64).enumerate() {
+            for (input_idx, io) in self.inputs.iter().enumerate() {
+                let numbers: Vec<u128> = chunk.iter().map(|vector| vector[input_idx]).collect();
+                sim.set_input(&io.name, &pack(&numbers, io.bits.len()));
+            }
+            sim.eval();
+            let good = sim.values();
+            let mut values = good.to_vec();
+            let mask = match chunk.len() {
+                64 => u64::MAX,
+                patterns => (1 << patterns) - 1,
+            };
+            for (fault, is_detected) in faults.iter().zip(detected.iter_mut()) {
+                if *is_detected {
+                    continue;
+                }
+                let line = fault.line.n;
+                let stuck = if fault.stuck_at { u64::MAX } else { 0 };
+                // a fault no vector activates changes nothing
+                if (good[line] ^ stuck) & mask == 0 {
+                    continue;
+                }
+                values[line] = stuck;
+                changed[line] = true;
+                let mut changed_lines = vec![line];
+                for wire in &self.wires[first_wire[line]..] {
+                    if touched(wire.gate, &changed) {
+                        let v = eval_gate(wire.gate, &values);
+                        if (v ^ good[wire.out.n]) & mask != 0 {
+                            values[wire.out.n] = v;
+                            changed[wire.out.n] = true;
+                            changed_lines.push(wire.out.n);
+                        }
+                    }
+                }
+                let difference = output_lines
+                    .iter()
+                    .filter(|&&l| changed[l])
+                    .fold(0, |difference, &l| difference | ((values[l] ^ good[l]) & mask));
+                if difference != 0 {
+                    *is_detected = true;
+                    useful[64 * chunk_idx + difference.trailing_zeros() as usize] = true;
+                }
+                for l in changed_lines {
+                    values[l] = good[l];
+                    changed[l] = false;
+                }
+            }
+        }
+        useful
+    }
+
+    // path, faults and marked faults of every component with marked faults, including the
+    // faults of its subcomponents, the faults of inputs belong to top
+    pub fn faults_by_component(&self, faults: &[Fault], marked: &[bool]) -> Vec<(String, usize, usize)> {
+        let mut scope_of_line = vec![0; self.stats.line_count];
+        for wire in &self.wires {
+            scope_of_line[wire.out.n] = wire.scope;
+        }
+        let mut counts = vec![(0, 0); self.scopes.len()];
+        for (fault, is_marked) in faults.iter().zip(marked) {
+            let scope = scope_of_line[fault.line.n];
+            counts[scope].0 += 1;
+            counts[scope].1 += *is_marked as usize;
+        }
+        // children have higher indices than their parents
+        for scope in (1..self.scopes.len()).rev() {
+            let (faults, marked) = counts[scope];
+            let parent = self.scopes[scope].parent;
+            counts[parent].0 += faults;
+            counts[parent].1 += marked;
+        }
+        self.component_paths()
+            .into_iter()
+            .zip(counts)
+            .filter(|&(_, (_, marked))| marked > 0)
+            .map(|(path, (faults, marked))| (path, faults, marked))
+            .collect()
+    }
+
+    // the stuck-at faults the vectors detect, see fault_simulate
+    pub fn fault_coverage(&self, vectors: &[Vec<u128>]) -> FaultCoverage {
+        let faults = self.faults();
+        let mut detected = vec![false; faults.len()];
+        let useful = self.fault_simulate(&faults, vectors, &mut detected);
+        let undetected: Vec<bool> = detected.iter().map(|d| !d).collect();
+        FaultCoverage {
+            vectors: vectors.len(),
+            faults: faults.len(),
+            detected: detected.iter().filter(|&&d| d).count(),
+            useful_vectors: useful.iter().filter(|&&u| u).count(),
+            components: self.faults_by_component(&faults, &undetected),
+            undetected: faults.iter().zip(&undetected).filter(|&(_, &u)| u).map(|(&fault, _)| self.fault_name(fault)).collect(),
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Write;
+
+use crate::data::{Bit, Circuit, Gate, Line, Scope};
+
+// gates created between enter_scope and leave_scope belong to a new component nested in the
+// current one, the hierarchical verilog output turns every component into its own module
+impl Circuit {
+    pub fn enter_scope(&mut self, name: &'static str) {
+        self.scopes.push(Scope {
+            name,
+            parent: self.scope,
+        });
+        self.scope = self.scopes.len() - 1;
+    }
+
+    pub fn leave_scope(&mut self) {
+        self.scope = self.scopes[self.scope].parent;
+    }
+
+    // builds f as a component named `name`
+    pub fn scoped<T>(&mut self, name: &'static str, f: impl FnOnce(&mut Circuit) -> T) -> T {
+        self.enter_scope(name);
+        let r = f(self);
+        self.leave_scope();
+        r
+    }
+
+    // like to_verilog, but every component with gates becomes a submodule, components with
+    // the same structure share one module, a module named like its component gets a
+    // number appended if another component of that name has a different structure
+    pub fn hierarchical_verilog(&mut self, name: &str) -> String {
+        let scope_count = self.scopes.len();
+        let mut depth = vec![0; scope_count];
+        for idx in 1..scope_count {
+            depth[idx] = depth[self.scopes[idx].parent] + 1;
+        }
+
+        // the scope every line driven by a gate is created in, all other lines belong to the top
+        let defined_in: HashMap<usize, usize> = self.wires.iter().map(|w| (w.out.n, w.scope)).collect();
+        let mut inputs: Vec<HashSet<usize>> = vec![HashSet::new(); scope_count];
+        let mut outputs: Vec<HashSet<usize>> = vec![HashSet::new(); scope_count];
+        // a line used outside of its scope leaves every scope up to the common ancestor and
+        // enters every scope down to its user
+        let mut connect = |n: usize, user: usize| {
+            let mut from = defined_in.get(&n).copied().unwrap_or(0);
+            let mut to = user;
+            while from != to {
+                if depth[from] >= depth[to] {
+                    outputs[from].insert(n);
+                    from = self.scopes[from].parent;
+                } else {
+                    inputs[to].insert(n);
+                    to = self.scopes[to].parent;
+                }
+            }
+        };
+        for wire in &self.wires {
+            for l in wire.gate.inputs() {
+                connect(l.n, wire.scope);
+            }
+        }
+        let register_inputs = self.registers.iter().map(|r| r.d);
+        for bit in register_inputs.chain(self.outputs.iter().flat_map(|o| o.bits.iter().copied())) {
+            if let Bit::Var(l) = bit {
+                connect(l.n, 0);
+            }
+        }
+
+        // the wires and subcomponents of every scope in the order they got built
+        let mut first_wire = vec![usize::MAX; scope_count];
+        let mut items: Vec<Vec<Item>> = vec![Vec::new(); scope_count];
+        for (idx, wire) in self.wires.iter().enumerate() {
+            first_wire[wire.scope] = first_wire[wire.scope].min(idx);
+            items[wire.scope].push(Item::Wire(idx));
+        }
+        for scope in (1..scope_count).rev() {
+            let parent = self.scopes[scope].parent;
+            first_wire[parent] = first_wire[parent].min(first_wire[scope]);
+            if first_wire[scope] != usize::MAX {
+                items[parent].push(Item::Scope(scope));
+            }
+        }
+        let position = |item: &Item| match *item {
+            Item::Wire(idx) => idx,
+            Item::Scope(scope) => first_wire[scope],
+        };
+        for scope_items in &mut items {
+            scope_items.sort_by_key(position);
+        }
+
+        // children have higher indices than their parents, so going downwards every module
+        // is defined before its first instantiation
+        let mut ports: Vec<(Vec<usize>, Vec<usize>)> = vec![(Vec::new(), Vec::new()); scope_count];
+        let mut module_names: Vec<String> = vec![String::new(); scope_count];
+        let mut modules: HashMap<(&'static str, String), String> = HashMap::new();
+        let mut name_count: HashMap<&'static str, usize> = HashMap::new();
+        let mut submodules = String::new();
+        for scope in (1..scope_count).rev() {
+            if first_wire[scope] == usize::MAX {
+                continue;
+            }
+            let mut names: HashMap<usize, String> = HashMap::new();
+            let mut scope_inputs = Vec::new();
+            let mut scope_outputs = Vec::new();
+            let mut internal = 0;
+            for item in &items[scope] {
+                let defined = match *item {
+                    Item::Wire(idx) => vec![self.wires[idx].out.n],
+                    Item::Scope(child) => ports[child].1.clone(),
+                };
+                for n in defined {
+                    if outputs[scope].contains(&n) {
+                        names.insert(n, format!("y[{}]", scope_outputs.len()));
+                        scope_outputs.push(n);
+                    } else {
+                        names.insert(n, format!("w[{internal}]"));
+                        internal += 1;
+                    }
+                }
+            }
+            for item in &items[scope] {
+                let used = match *item {
+                    Item::Wire(idx) => self.wires[idx].gate.inputs().iter().map(|l| l.n).collect(),
+                    Item::Scope(child) => ports[child].0.clone(),
+                };
+                for n in used {
+                    names.entry(n).or_insert_with(|| {
+                        scope_inputs.push(n);
+                        format!("x[{}]", scope_inputs.len() - 1)
+                    });
+                }
+            }
+
+            let mut body = String::new();
+            for (kind, label, width) in [("input", "x", scope_inputs.len()), ("output", "y", scope_outputs.len()), ("wire", "w", internal)] {
+                if width > 0 {
+                    body.push_str(&format!("{kind} [{}:0] {label};\n", width - 1));
+                }
+            }
+            body.push_str(&self.scope_body(&items[scope], &module_names, &ports, |n| names[&n].clone()));
+            body.push_str("endmodule\n");
+
+            let scope_name = self.scopes[scope].name;
+            let module_name = modules.entry((scope_name, body.clone())).or_insert_with(|| {
+                let count = name_count.entry(scope_name).or_insert(0);
+                let module_name = match *count {
+                    0 => scope_name.to_string(),
+                    count => format!("{scope_name}_{count}"),
+                };
+                *count += 1;
+                let port_names: Vec<&str> = [("x", scope_inputs.len()), ("y", scope_outputs.len())]
+                    .iter()
+                    .filter(|(_, width)| *width > 0)
+                    .map(|(port, _)| *port)
+                    .collect();
+                submodules.push_str(&format!("\nmodule {module_name}({});\n{body}", port_names.join(", ")));
+                module_name
+            });
+            module_names[scope] = module_name.clone();
+            ports[scope] = (scope_inputs, scope_outputs);
+        }
+
+        let mut s = String::new();
+        s.push_str(&self.verilog_header(name));
+        s.push_str(&self.io_labels());
+        for item in &items[0] {
+            let defined = match *item {
+                Item::Wire(idx) => vec![self.wires[idx].out.n],
+                Item::Scope(child) => ports[child].1.clone(),
+            };
+            for n in defined {
+                if !self.io_lines.contains_key(&n) {
+                    s.push_str(&format!("wire _{n}_;\n"));
+                }
+            }
+        }
+        s.push_str(&self.register_labels());
+        s.push_str(&self.constant_assigns());
+        let lines: HashMap<usize, Line> = self
+            .wires
+            .iter()
+            .map(|w| w.out)
+            .chain(self.registers.iter().map(|r| r.q))
+            .map(|l| (l.n, l))
+            .collect();
+        s.push_str(&self.scope_body(&items[0], &module_names, &ports, |n| {
+            lines
+                .get(&n)
+                .copied()
+                .unwrap_or(Line { level: 0, n })
+                .to_verilog(&self.io_lines)
+        }));
+        s.push_str(&self.register_block());
+        s.push_str(&self.output_assigns());
+        s.push_str("endmodule\n");
+        s.push_str(&submodules);
+        s
+    }
+
+    pub fn write_hierarchical_to_file(&mut self, file_name: &str, module_name: &str) -> std::io::Result<()> {
+        let mut file = File::create(file_name)?;
+        write!(file, "{}", self.hierarchical_verilog(module_name))?;
+        Ok(())
+    }
+
+    // the assigns of the gates and the instantiations of the subcomponents of a scope
+    fn scope_body(
+        &self,
+        items: &[Item],
+        module_names: &[String],
+        ports: &[(Vec<usize>, Vec<usize>)],
+        name: impl Fn(usize) -> String,
+    ) -> String {
+        let mut s = String::new();
+        let mut instances = 0;
+        for item in items {
+            match *item {
+                Item::Wire(idx) => {
+                    let wire = &self.wires[idx];
+                    let gate = match wire.gate {
+                        Gate::Not(l) => format!("~{}", name(l.n)),
+                        Gate::And(l1, l2) => format!("{} & {}", name(l1.n), name(l2.n)),
+                        Gate::Or(l1, l2) => format!("{} | {}", name(l1.n), name(l2.n)),
+                        Gate::Xor(l1, l2) => format!("{} ^ {}", name(l1.n), name(l2.n)),
+                    };
+                    s.push_str(&format!("assign {} = {gate};\n", name(wire.out.n)));
+                }
+                Item::Scope(child) => {
+                    // concatenations list the highest bit first
+                    let connections: Vec<String> = [("x", &ports[child].0), ("y", &ports[child].1)]
+                        .iter()
+                        .filter(|(_, lines)| !lines.is_empty())
+                        .map(|(port, lines)| {
+                            let bits: Vec<String> = lines.iter().rev().map(|&n| name(n)).collect();
+                            format!(".{port}({{{}}})", bits.join(", "))
+                        })
+                        .collect();
+                    s.push_str(&format!("{} u{instances} ({});\n", module_names[child], connections.join(", ")));
+                    instances += 1;
+                }
+            }
+        }
+        s
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Item {
+    // index into circuit.wires
+    Wire(usize),
+    // index into circuit.scopes
+    Scope(usize),
+}
@@ -0,0 +1,381 @@
+use std::io::{Error, ErrorKind, Result};
+
+use crate::cuts::{truth_mask, var_truth};
+
+// the error of a malformed liberty file
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("liberty: {message}"))
+}
+
+// a combinational cell with a single output, truth is the function of the output in terms
+// of the inputs like the truth table of a cut, delay is the largest delay of all timing arcs
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cell {
+    pub name: String,
+    pub area: f64,
+    pub delay: f64,
+    pub inputs: Vec<String>,
+    pub output: String,
+    pub truth: u64,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Library {
+    pub name: String,
+    pub cells: Vec<Cell>,
+}
+
+// group statement of a liberty file like cell(NAND2) { ... }
+#[derive(Debug, PartialEq, Clone, Default)]
+struct Group {
+    kind: String,
+    args: Vec<String>,
+    // simple attributes like area : 1.33;
+    attributes: Vec<(String, String)>,
+    // complex attributes like values("1, 2", "3, 4");
+    complex: Vec<(String, Vec<String>)>,
+    groups: Vec<Group>,
+}
+
+impl Group {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    fn groups<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = &'a Group> + 'a {
+        self.groups.iter().filter(move |g| g.kind == kind)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    Word(String),
+    Str(String),
+    Punct(char),
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+    while idx < chars.len() {
+        let c = chars[idx];
+        if c.is_whitespace() || c == '\\' {
+            idx += 1;
+        } else if c == '/' && chars.get(idx + 1) == Some(&'*') {
+            idx += 2;
+            while idx + 1 < chars.len() && !(chars[idx] == '*' && chars[idx + 1] == '/') {
+                idx += 1;
+            }
+            idx += 2;
+        } else if c == '/' && chars.get(idx + 1) == Some(&'/') {
+            while idx < chars.len() && chars[idx] != '\n' {
+                idx += 1;
+            }
+        } else if c == '"' {
+            let start = idx + 1;
+            idx = start;
+            while idx < chars.len() && chars[idx] != '"' {
+                idx += 1;
+            }
+            tokens.push(Token::Str(chars[start..idx.min(chars.len())].iter().collect()));
+            idx += 1;
+        } else if "{}():;,".contains(c) {
+            tokens.push(Token::Punct(c));
+            idx += 1;
+        } else {
+            let start = idx;
+            while idx < chars.len() && !chars[idx].is_whitespace() && !"{}():;,\"".contains(chars[idx]) {
+                idx += 1;
+            }
+            tokens.push(Token::Word(chars[start..idx].iter().collect()));
+        }
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    idx: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.idx).cloned();
+        self.idx += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.idx)
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        match self.next() {
+            Some(Token::Punct(p)) if p == c => Ok(()),
+            token => Err(invalid(format!("expected '{c}' but found {token:?}"))),
+        }
+    }
+
+    // the text of a value, numbers and identifiers are words, everything else strings
+    fn value(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Token::Word(w)) | Some(Token::Str(w)) => Ok(w),
+            token => Err(invalid(format!("expected a value but found {token:?}"))),
+        }
+    }
+
+    // statements until the closing brace of the current group, or the end of the text at the top
+    fn statements(&mut self, group: &mut Group) -> Result<()> {
+        while let Some(token) = self.peek() {
+            if token == &Token::Punct('}') {
+                self.next();
+                return Ok(());
+            }
+            let name = self.value()?;
+            match self.next() {
+                Some(Token::Punct(':')) => {
+                    let mut value = self.value()?;
+                    // expressions like 0.5 * 2 are kept as a single value
+                    while let Some(Token::Word(_)) | Some(Token::Str(_)) = self.peek() {
+                        value.push(' ');
+                        value.push_str(&self.value()?);
+                    }
+                    if self.peek() == Some(&Token::Punct(';')) {
+                        self.next();
+                    }
+                    group.attributes.push((name, value));
+                }
+                Some(Token::Punct('(')) => {
+                    let mut args = Vec::new();
+                    while self.peek() != Some(&Token::Punct(')')) {
+                        if self.peek() == Some(&Token::Punct(',')) {
+                            self.next();
+                            continue;
+                        }
+                        args.push(self.value()?);
+                    }
+                    self.expect(')')?;
+                    match self.peek() {
+                        Some(Token::Punct('{')) => {
+                            self.next();
+                            let mut child = Group {
+                                kind: name,
+                                args,
+                                ..Group::default()
+                            };
+                            self.statements(&mut child)?;
+                            group.groups.push(child);
+                        }
+                        _ => {
+                            if self.peek() == Some(&Token::Punct(';')) {
+                                self.next();
+                            }
+                            group.complex.push((name, args));
+                        }
+                    }
+                }
+                token => return Err(invalid(format!("unexpected {token:?} after {name}"))),
+            }
+        }
+        // only the statements outside of any group end with the text
+        match group.kind.as_str() {
+            "" => Ok(()),
+            kind => Err(invalid(format!("missing '}}' of group {kind}"))),
+        }
+    }
+}
+
+// the truth table of a liberty function like "!(A & B) | C'" over the given pins
+struct Expression<'a> {
+    chars: Vec<char>,
+    idx: usize,
+    pins: &'a [String],
+}
+
+impl Expression<'_> {
+    fn skip_spaces(&mut self) {
+        while self.idx < self.chars.len() && self.chars[self.idx].is_whitespace() {
+            self.idx += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_spaces();
+        self.chars.get(self.idx).copied()
+    }
+
+    // or has the lowest precedence, then and, then xor, then not
+    fn or(&mut self) -> Result<u64> {
+        let mut r = self.and()?;
+        while let Some('|' | '+') = self.peek() {
+            self.idx += 1;
+            r |= self.and()?;
+        }
+        Ok(r)
+    }
+
+    fn and(&mut self) -> Result<u64> {
+        let mut r = self.xor()?;
+        loop {
+            match self.peek() {
+                Some('&' | '*') => {
+                    self.idx += 1;
+                    r &= self.xor()?;
+                }
+                // two operands next to each other are and-ed
+                Some(c) if c == '!' || c == '(' || c.is_alphanumeric() || c == '_' => r &= self.xor()?,
+                _ => return Ok(r),
+            }
+        }
+    }
+
+    fn xor(&mut self) -> Result<u64> {
+        let mut r = self.unary()?;
+        while let Some('^') = self.peek() {
+            self.idx += 1;
+            r ^= self.unary()?;
+        }
+        Ok(r)
+    }
+
+    fn unary(&mut self) -> Result<u64> {
+        let mask = truth_mask(self.pins.len());
+        let mut r = match self.peek() {
+            Some('!') => {
+                self.idx += 1;
+                return Ok(!self.unary()? & mask);
+            }
+            Some('(') => {
+                self.idx += 1;
+                let r = self.or()?;
+                if self.peek() != Some(')') {
+                    return Err(invalid("missing ')' in function".to_string()));
+                }
+                self.idx += 1;
+                r
+            }
+            Some(_) => {
+                let start = self.idx;
+                while self.idx < self.chars.len() && (self.chars[self.idx].is_alphanumeric() || "_[]".contains(self.chars[self.idx])) {
+                    self.idx += 1;
+                }
+                let name: String = self.chars[start..self.idx].iter().collect();
+                match name.as_str() {
+                    "0" => 0,
+                    "1" => mask,
+                    _ => match self.pins.iter().position(|p| p == &name) {
+                        Some(i) => var_truth(i, self.pins.len()),
+                        None => return Err(invalid(format!("unknown pin {name} in function"))),
+                    },
+                }
+            }
+            None => return Err(invalid("incomplete function".to_string())),
+        };
+        while let Some('\'') = self.peek() {
+            self.idx += 1;
+            r = !r & mask;
+        }
+        Ok(r)
+    }
+}
+
+fn parse_function(function: &str, pins: &[String]) -> Result<u64> {
+    let mut expression = Expression {
+        chars: function.chars().collect(),
+        idx: 0,
+        pins,
+    };
+    let r = expression.or()?;
+    if expression.peek().is_some() {
+        return Err(invalid(format!("could not parse function {function}")));
+    }
+    Ok(r)
+}
+
+// a scalar delay of a timing arc, the intrinsic delays of the linear delay model or
+// the middle entries of the delay tables
+fn arc_delay(timing: &Group) -> f64 {
+    let mut delay: f64 = 0.0;
+    for name in ["intrinsic_rise", "intrinsic_fall"] {
+        if let Some(v) = timing.attribute(name).and_then(|v| v.parse::<f64>().ok()) {
+            delay = delay.max(v);
+        }
+    }
+    for table in timing.groups.iter().filter(|g| g.kind == "cell_rise" || g.kind == "cell_fall") {
+        let values: Vec<f64> = table
+            .complex
+            .iter()
+            .filter(|(name, _)| name == "values")
+            .flat_map(|(_, rows)| rows.iter())
+            .flat_map(|row| row.split(',').map(str::trim).filter_map(|v| v.parse::<f64>().ok()).collect::<Vec<_>>())
+            .collect();
+        if let Some(&v) = values.get(values.len() / 2) {
+            delay = delay.max(v);
+        }
+    }
+    delay
+}
+
+impl Library {
+    pub fn read(file_name: &str) -> Result<Library> {
+        Library::parse(&std::fs::read_to_string(file_name)?)
+    }
+
+    // keeps the combinational cells with a single output and at most 6 inputs,
+    // sequential cells and cells marked dont_use are skipped
+    pub fn parse(text: &str) -> Result<Library> {
+        let mut parser = Parser {
+            tokens: tokenize(text),
+            idx: 0,
+        };
+        let mut root = Group::default();
+        parser.statements(&mut root)?;
+        let library = root
+            .groups("library")
+            .next()
+            .ok_or_else(|| invalid("no library group found".to_string()))?;
+
+        let mut cells = Vec::new();
+        for cell in library.groups("cell") {
+            let sequential = ["ff", "latch", "statetable", "ff_bank", "latch_bank"]
+                .iter()
+                .any(|kind| cell.groups(kind).next().is_some());
+            if sequential || cell.attribute("dont_use") == Some("true") {
+                continue;
+            }
+            let mut inputs = Vec::new();
+            let mut outputs = Vec::new();
+            for pin in cell.groups("pin") {
+                for name in &pin.args {
+                    match pin.attribute("direction") {
+                        Some("input") => inputs.push(name.clone()),
+                        Some("output") => outputs.push((name.clone(), pin)),
+                        _ => (),
+                    }
+                }
+            }
+            let [(output, pin)] = outputs.as_slice() else {
+                continue;
+            };
+            let Some(function) = pin.attribute("function") else {
+                continue;
+            };
+            if inputs.is_empty() || inputs.len() > 6 {
+                continue;
+            }
+            cells.push(Cell {
+                name: cell.args.first().cloned().unwrap_or_default(),
+                area: cell.attribute("area").and_then(|a| a.parse().ok()).unwrap_or(0.0),
+                delay: pin.groups("timing").map(arc_delay).fold(0.0, f64::max),
+                truth: parse_function(function, &inputs)?,
+                inputs,
+                output: output.clone(),
+            });
+        }
+        Ok(Library {
+            name: library.args.first().cloned().unwrap_or_default(),
+            cells,
+        })
+    }
+}
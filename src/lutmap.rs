@@ -0,0 +1,296 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+
+use clap::ValueEnum;
+
+use crate::cuts::Cut;
+use crate::data::{Adder, Bit, Circuit, Line, Mul, IO};
+use crate::dividers::{DivInfo, Estimate};
+
+// the non-dominated cuts of a line the priority cuts are chosen from, with more the luts of
+// the dividers shrink by less than half a percent
+const LUT_CANDIDATES: usize = 32;
+
+// a lookup table driving line output, bit m of truth is its value if input i has the value
+// of bit i of m
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Lut {
+    pub inputs: Vec<usize>,
+    pub output: usize,
+    pub truth: u64,
+}
+
+// the luts with at most k inputs replacing the gates of a circuit in the order of the gates
+// they replace, depth is the largest number of luts between inputs, registers and outputs
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LutNetlist {
+    pub k: usize,
+    pub luts: Vec<Lut>,
+    pub depth: usize,
+}
+
+impl fmt::Display for LutNetlist {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LUT-{} count: {}, LUT depth: {}", self.k, self.luts.len(), self.depth)
+    }
+}
+
+// lut count and depth of one configuration for 4, 5 and 6 input luts
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct LutDesignPoint {
+    pub adder: Adder,
+    pub multiplier: Mul,
+    pub estimator: Estimate,
+    pub luts: [(usize, usize); 3],
+}
+
+// all configurations of a sweep sorted by the number of 6-input luts
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LutDesignSpace {
+    pub points: Vec<LutDesignPoint>,
+}
+
+impl fmt::Display for LutDesignSpace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<6} {:<10} {:<11} {:>8} {:>6} {:>8} {:>6} {:>8} {:>6}",
+            "adder", "multiplier", "estimator", "lut4", "depth", "lut5", "depth", "lut6", "depth"
+        )?;
+        for p in &self.points {
+            writeln!(
+                f,
+                "{:<6} {:<10} {:<11} {:>8} {:>6} {:>8} {:>6} {:>8} {:>6}",
+                format!("{:?}", p.adder),
+                format!("{:?}", p.multiplier),
+                format!("{:?}", p.estimator),
+                p.luts[0].0,
+                p.luts[0].1,
+                p.luts[1].0,
+                p.luts[1].1,
+                p.luts[2].0,
+                p.luts[2].1
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Circuit {
+    // covers the gates with k-input luts, the first pass picks for every line the cut with
+    // the least depth, the second keeps that depth at every lut needed by the outputs and
+    // registers and otherwise picks the cut with the least area flow, that is one lut plus
+    // the luts its leaves need shared among their fanouts
+    pub fn map_to_luts(&self, k: usize) -> LutNetlist {
+        let wire_of_line: HashMap<usize, usize> = self.wires.iter().enumerate().map(|(idx, w)| (w.out.n, idx)).collect();
+        let register_inputs = self.registers.iter().map(|r| r.d);
+        let outputs = self.outputs.iter().flat_map(|o| o.bits.iter().copied());
+        let ends: Vec<usize> = register_inputs
+            .chain(outputs)
+            .filter_map(|bit| match bit {
+                Bit::Var(l) => wire_of_line.get(&l.n).copied(),
+                _ => None,
+            })
+            .collect();
+
+        let (choices, levels) = self.select_cuts(k, &HashMap::new(), &[]);
+        let depth = ends.iter().map(|&idx| levels[&self.wires[idx].out.n]).max().unwrap_or(0);
+
+        // the latest level every lut of the first cover may have without increasing the depth
+        let used = cover(&choices, &ends, &wire_of_line);
+        let mut required: HashMap<usize, usize> = ends.iter().map(|&idx| (self.wires[idx].out.n, depth)).collect();
+        for idx in (0..self.wires.len()).rev().filter(|idx| used.contains(idx)) {
+            let level = required[&self.wires[idx].out.n] - 1;
+            for &leaf in &choices[idx].leaves {
+                let r = required.entry(leaf).or_insert(level);
+                *r = (*r).min(level);
+            }
+        }
+
+        let (mut choices, _) = self.select_cuts(k, &required, &choices);
+        let used = cover(&choices, &ends, &wire_of_line);
+        let luts = (0..self.wires.len())
+            .filter(|idx| used.contains(idx))
+            .map(|idx| {
+                let cut = std::mem::take(&mut choices[idx]);
+                Lut {
+                    inputs: cut.leaves,
+                    output: self.wires[idx].out.n,
+                    truth: cut.truth,
+                }
+            })
+            .collect();
+        LutNetlist { k, luts, depth }
+    }
+
+    // the chosen cut and the level of every line driven by a gate, every line keeps the
+    // cut of its gate and the 8 best cuts for its fanouts to build on, without required
+    // levels the cut with the least depth is best, otherwise the one with the least area flow
+    // among those meeting the required level, the cut chosen by a previous pass is always a
+    // candidate, so the required levels are met if they came from its cover
+    fn select_cuts(&self, k: usize, required: &HashMap<usize, usize>, previous: &[Cut]) -> (Vec<Cut>, HashMap<usize, usize>) {
+        let fanouts = self.fanouts();
+        let mut levels: HashMap<usize, usize> = HashMap::with_capacity(self.wires.len());
+        let mut flow: HashMap<usize, f64> = HashMap::with_capacity(self.wires.len());
+        let mut choices = Vec::with_capacity(self.wires.len());
+        self.enumerate_cuts_with(k, LUT_CANDIDATES, |idx, mut cuts| {
+            if let Some(cut) = previous.get(idx) {
+                if !cuts.contains(cut) {
+                    cuts.push(cut.clone());
+                }
+            }
+            let limit = required.get(&self.wires[idx].out.n).copied().unwrap_or(usize::MAX);
+            let mut ranked: Vec<(bool, usize, f64, usize, usize)> = cuts
+                .iter()
+                .enumerate()
+                .map(|(c, cut)| {
+                    let level = 1 + cut.leaves.iter().map(|l| levels.get(l).copied().unwrap_or(0)).max().unwrap_or(0);
+                    let cut_flow = 1.0
+                        + cut
+                            .leaves
+                            .iter()
+                            .map(|l| flow.get(l).copied().unwrap_or(0.0) / fanouts.get(l).copied().unwrap_or(1).max(1) as f64)
+                            .sum::<f64>();
+                    match required.is_empty() {
+                        true => (false, level, cut_flow, level, c),
+                        // late cuts are only taken if every cut is late, the earliest of them
+                        false => (level > limit, if level > limit { level } else { 0 }, cut_flow, level, c),
+                    }
+                })
+                .collect();
+            ranked.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)).then(a.2.total_cmp(&b.2)).then((a.3, a.4).cmp(&(b.3, b.4))));
+
+            let (_, _, best_flow, best_level, best) = ranked[0];
+            levels.insert(self.wires[idx].out.n, best_level);
+            flow.insert(self.wires[idx].out.n, best_flow);
+            choices.push(cuts[best].clone());
+            let mut kept = vec![cuts[0].clone()];
+            kept.extend(ranked.iter().filter(|r| r.4 != 0).take(8).map(|r| cuts[r.4].clone()));
+            kept
+        });
+        (choices, levels)
+    }
+
+    // lut count and depth of the divider described by info with every adder, multiplier and
+    // estimator, sorted by the number of 6-input luts, a truncating divider only with the dadda tree
+    pub fn lut_design_space(info: DivInfo) -> LutDesignSpace {
+        let mut points = Vec::new();
+        for &adder in Adder::value_variants() {
+            for &multiplier in Mul::value_variants().iter().filter(|&&m| m == Mul::DadaTree || !info.truncates()) {
+                for &estimator in Estimate::value_variants() {
+                    let mut info = info;
+                    info.defaultadder = adder;
+                    info.defaultmult = multiplier;
+                    info.estimator = estimator;
+                    let mut circuit = Circuit::get_divider_circuit(info);
+                    circuit.remove_dead_ends();
+                    let mut luts = [(0, 0); 3];
+                    for (k, l) in (4..=6).zip(luts.iter_mut()) {
+                        let netlist = circuit.map_to_luts(k);
+                        *l = (netlist.luts.len(), netlist.depth);
+                    }
+                    points.push(LutDesignPoint {
+                        adder,
+                        multiplier,
+                        estimator,
+                        luts,
+                    });
+                }
+            }
+        }
+        points.sort_by_key(|p| (p.luts[2].0, p.luts[2].1));
+        LutDesignSpace { points }
+    }
+
+    // the lut netlist in the berkeley logic interchange format, every lut becomes a .names
+    // table listing the input patterns for which it is one and every register a .latch,
+    // registers with reset get a lut clearing their input
+    pub fn blif(&self, netlist: &LutNetlist, name: &str) -> String {
+        let line = |n: usize| Line { level: 0, n }.to_verilog(&self.io_lines);
+        let bits = |ios: &[IO]| -> Vec<String> {
+            ios.iter()
+                .flat_map(|io| (0..io.bits.len()).map(move |idx| format!("{}[{}]", io.name, idx)))
+                .collect()
+        };
+        let mut s = format!(".model {name}\n");
+        s.push_str(&format!(".inputs {}\n", bits(&self.inputs).join(" ")));
+        s.push_str(&format!(".outputs {}\n", bits(&self.outputs).join(" ")));
+
+        let constant = |bit: Bit, target: &str| match bit {
+            Bit::Zero => format!(".names {target}\n"),
+            Bit::One => format!(".names {target}\n1\n"),
+            Bit::Var(l) => format!(".names {} {target}\n1 1\n", l.to_verilog(&self.io_lines)),
+        };
+        if let Some(clock) = self.clock {
+            for register in &self.registers {
+                let q = register.q.to_verilog(&self.io_lines);
+                let d = match (self.reset, register.reset) {
+                    (Some(reset), true) => {
+                        let d = format!("{q}_d");
+                        s.push_str(&constant(register.d, &format!("{q}_next")));
+                        s.push_str(&format!(".names {} {q}_next {d}\n01 1\n", reset.to_verilog(&self.io_lines)));
+                        d
+                    }
+                    _ => match register.d {
+                        Bit::Var(l) => l.to_verilog(&self.io_lines),
+                        bit => {
+                            s.push_str(&constant(bit, &format!("{q}_d")));
+                            format!("{q}_d")
+                        }
+                    },
+                };
+                s.push_str(&format!(".latch {d} {q} re {} 0\n", clock.to_verilog(&self.io_lines)));
+            }
+        }
+
+        for lut in &netlist.luts {
+            let mut names: Vec<String> = lut.inputs.iter().map(|&n| line(n)).collect();
+            names.push(line(lut.output));
+            s.push_str(&format!(".names {}\n", names.join(" ")));
+            for m in 0..(1_usize << lut.inputs.len()) {
+                if (lut.truth >> m) & 0x1 == 1 {
+                    let pattern: String = (0..lut.inputs.len()).map(|i| if (m >> i) & 0x1 == 1 { '1' } else { '0' }).collect();
+                    match pattern.is_empty() {
+                        true => s.push_str("1\n"),
+                        false => s.push_str(&format!("{pattern} 1\n")),
+                    }
+                }
+            }
+        }
+
+        // output bits not driven by a lut of their own
+        for output in &self.outputs {
+            for (idx, &bit) in output.bits.iter().enumerate() {
+                let driven = match bit {
+                    Bit::Var(l) => self.io_lines.get(&l.n).is_some_and(|n| n.is_output),
+                    _ => false,
+                };
+                if !driven {
+                    s.push_str(&constant(bit, &format!("{}[{}]", output.name, idx)));
+                }
+            }
+        }
+        s.push_str(".end\n");
+        s
+    }
+
+    pub fn write_blif_to_file(&self, netlist: &LutNetlist, file_name: &str, model_name: &str) -> std::io::Result<()> {
+        let mut file = File::create(file_name)?;
+        write!(file, "{}", self.blif(netlist, model_name))?;
+        Ok(())
+    }
+}
+
+// the wires whose chosen cuts are needed to compute the ends
+fn cover(choices: &[Cut], ends: &[usize], wire_of_line: &HashMap<usize, usize>) -> HashSet<usize> {
+    let mut used = HashSet::new();
+    let mut stack = ends.to_vec();
+    while let Some(idx) = stack.pop() {
+        if used.insert(idx) {
+            stack.extend(choices[idx].leaves.iter().filter_map(|l| wire_of_line.get(l).copied()));
+        }
+    }
+    used
+}
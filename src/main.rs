@@ -1,12 +1,37 @@
 mod adders;
+mod approximate;
+mod atpg;
+mod bdd;
+mod breakdown;
 mod circuit_tests;
+mod cost;
+mod cuts;
 mod data;
 mod dividers;
+mod equivalence;
+mod error_bounds;
+mod faults;
 mod helpers;
+mod hierarchy;
+mod liberty;
+mod lutmap;
 mod multipliers;
+mod optimize;
+mod pipeline;
 mod primitives;
+mod properties;
+mod residue;
+mod retiming;
+mod sat;
+mod simulation;
+mod squarers;
+mod techmap;
+mod timing;
+mod tmr;
 mod cli;
+use clap::ValueEnum;
 use data::{Circuit};
+use dividers::Estimate;
 use std::time::Instant;
 
 fn main() -> std::io::Result<()> {
@@ -14,9 +39,52 @@ fn main() -> std::io::Result<()> {
     env::set_var("RUST_BACKTRACE", "1");
 
     let (divider_builder, remove_dead_ends, additional_args) = cli::parse();
+    let pipelining = cli::get_pipelining(&additional_args);
+    let passes = additional_args.optimize.clone();
+    let retiming = additional_args.retime;
+    let hierarchical = additional_args.hierarchical;
+    let breakdown = additional_args.breakdown;
+    let timing = additional_args.timing;
+    let timing_json = additional_args.timing_json.clone();
+    let costs = match &additional_args.cost_file {
+        Some(file_name) => additional_args.cost_model.costs().read(file_name)?,
+        None => additional_args.cost_model.costs(),
+    };
+    let design_space = additional_args.design_space;
+    let liberty = additional_args.liberty.clone();
+    let luts = additional_args.luts;
+    let lut_space = additional_args.lut_space;
+    let blif = additional_args.blif.clone();
+    let lut_size = additional_args.lut_size as usize;
+    let check_equivalence = additional_args.check_equivalence;
+    let miter_dimacs = additional_args.miter_dimacs.clone();
+    let property_dimacs = additional_args.property_dimacs.clone();
+    let property_smt2 = additional_args.property_smt2.clone();
+    let bdd_order = additional_args.bdd;
+    let error_bounds = additional_args.error_bounds;
+    let fault_vectors = additional_args.fault_vectors.clone();
+    let random_vectors = additional_args.random_vectors;
+    let atpg = additional_args.atpg;
+    let atpg_vectors = additional_args.atpg_vectors.clone();
+    let testbench = additional_args.testbench.clone();
+    let atpg_conflicts = additional_args.atpg_conflicts;
+    let tmr = additional_args.tmr.clone();
+    let error_stats = additional_args.error_stats || divider_builder.approximate();
+    let error_samples = additional_args.error_samples;
+    let approximation_space = additional_args.approximation_space;
+    let mut reference_builder = divider_builder;
+    reference_builder.defaultadder = additional_args.reference_adder.unwrap_or(divider_builder.defaultadder);
+    reference_builder.defaultmult = additional_args.reference_multiplier.unwrap_or(divider_builder.defaultmult);
     let (output_filename, module_name) = cli::get_file_and_module_name(additional_args);
 
     let mut time = Instant::now();
+    if error_bounds {
+        for estimator in Estimate::value_variants() {
+            println!("{}", Circuit::estimator_error_bound(*estimator, divider_builder.number_bits));
+        }
+        println!("Bounding estimate errors took {:#?} µs", time.elapsed().as_micros());
+        time = Instant::now();
+    }
     let mut circuit = Circuit::get_divider_circuit(divider_builder);
     println!("Generating circuit took {:#?} µs", time.elapsed().as_micros());
 
@@ -26,11 +94,147 @@ fn main() -> std::io::Result<()> {
         println!("Removing dead ends took {:#?} µs", time.elapsed().as_micros());
     }
 
+    if !passes.is_empty() {
+        time = Instant::now();
+        let report = circuit.optimize(&passes);
+        println!("Optimizing took {:#?} µs", time.elapsed().as_micros());
+        print!("{}", report);
+    }
+
+    let errors = error_stats.then(|| {
+        time = Instant::now();
+        let errors = circuit.error_stats(error_samples);
+        println!("Measuring errors took {:#?} µs", time.elapsed().as_micros());
+        errors
+    });
+
+    if check_equivalence || miter_dimacs.is_some() {
+        let mut reference = Circuit::get_divider_circuit(reference_builder);
+        if remove_dead_ends {
+            reference.remove_dead_ends();
+        }
+        if let Some(file_name) = miter_dimacs {
+            std::fs::write(&file_name, circuit.miter(&reference).dimacs())?;
+        }
+        if check_equivalence {
+            time = Instant::now();
+            let result = match bdd_order {
+                Some(order) => circuit.check_equivalence_bdd(&reference, order),
+                None => circuit.check_equivalence(&reference),
+            };
+            println!("Checking equivalence took {:#?} µs", time.elapsed().as_micros());
+            print!("{}", result);
+        }
+    }
+
+    if let Some(order) = bdd_order {
+        time = Instant::now();
+        let wrong = circuit.wrong_quotients(order);
+        println!("Building bdds took {:#?} µs", time.elapsed().as_micros());
+        print!("{}", wrong);
+    }
+    if let Some(file_name) = property_dimacs {
+        std::fs::write(&file_name, circuit.divider_property().dimacs())?;
+    }
+    if let Some(file_name) = property_smt2 {
+        std::fs::write(&file_name, circuit.divider_property_smt2())?;
+    }
+    if fault_vectors.is_some() || random_vectors.is_some() {
+        let mut vectors = match fault_vectors {
+            Some(file_name) => circuit.parse_vectors(&std::fs::read_to_string(file_name)?),
+            None => Vec::new(),
+        };
+        vectors.extend(circuit.random_vectors(random_vectors.unwrap_or(0)));
+        time = Instant::now();
+        let coverage = circuit.fault_coverage(&vectors);
+        println!("Fault simulation took {:#?} µs", time.elapsed().as_micros());
+        print!("{}", coverage);
+    }
+    if atpg || atpg_vectors.is_some() || testbench.is_some() {
+        time = Instant::now();
+        let result = circuit.atpg(random_vectors.unwrap_or(256), atpg_conflicts);
+        println!("Generating test patterns took {:#?} µs", time.elapsed().as_micros());
+        print!("{}", result);
+        if let Some(file_name) = atpg_vectors {
+            std::fs::write(&file_name, circuit.vectors_text(&result.vectors))?;
+        }
+        if let Some(file_name) = testbench {
+            std::fs::write(&file_name, circuit.testbench(&module_name, &result.vectors))?;
+        }
+    }
+
+    if let Some(pipelining) = pipelining {
+        time = Instant::now();
+        let latency = circuit.pipeline_by(pipelining);
+        println!("Pipelining took {:#?} µs, latency: {} cycles", time.elapsed().as_micros(), latency);
+    }
+
+    if retiming {
+        time = Instant::now();
+        let report = circuit.retime(&costs);
+        println!("Retiming took {:#?} µs, {}", time.elapsed().as_micros(), report);
+    }
+
+    if let Some(vote_after) = tmr {
+        time = Instant::now();
+        let report = circuit.triplicate(&vote_after);
+        println!("Triplicating took {:#?} µs, {}", time.elapsed().as_micros(), report);
+    }
+
 
     time = Instant::now();
-    circuit.write_to_file(&output_filename, &module_name)?;
+    if let Some(file_name) = liberty {
+        let library = liberty::Library::read(&file_name)?;
+        let netlist = circuit.map_to_library(&library)?;
+        println!("Mapping to {} took {:#?} µs, {}", library.name, time.elapsed().as_micros(), netlist);
+        time = Instant::now();
+        circuit.write_mapped_to_file(&netlist, &library, &output_filename, &module_name)?;
+    } else if hierarchical {
+        circuit.write_hierarchical_to_file(&output_filename, &module_name)?;
+    } else {
+        circuit.write_to_file(&output_filename, &module_name)?;
+    }
     println!("Writing circuit to file took {:#?} µs saved as <{}>", time.elapsed().as_micros(), output_filename);
     println!("Gatter count: {}, Max depth: {}", circuit.stats.gatter_count, circuit.stats.level_count);
+    if let Some(errors) = errors {
+        println!("Quotient errors over {}", errors);
+    }
+    if circuit.stats.register_count > 0 {
+        println!("Register count: {}", circuit.stats.register_count);
+    }
+    println!("{}", circuit.cost(&costs));
+    if breakdown {
+        print!("{}", circuit.breakdown(&costs));
+    }
+    if timing.is_some() || timing_json.is_some() {
+        let report = circuit.timing_report(timing.unwrap_or(1), &costs);
+        if timing.is_some() {
+            print!("{}", report);
+        }
+        if let Some(file_name) = timing_json {
+            std::fs::write(&file_name, report.to_json())?;
+        }
+    }
+    if design_space {
+        print!("{}", Circuit::design_space(divider_builder, &costs));
+    }
+    if approximation_space {
+        print!("{}", Circuit::approximation_space(divider_builder, error_samples));
+    }
+    if luts {
+        for k in 4..=6 {
+            println!("{}", circuit.map_to_luts(k));
+        }
+    }
+    if let Some(file_name) = blif {
+        time = Instant::now();
+        let netlist = circuit.map_to_luts(lut_size);
+        circuit.write_blif_to_file(&netlist, &file_name, &module_name)?;
+        println!("Mapping to luts took {:#?} µs, {} saved as <{}>", time.elapsed().as_micros(), netlist, file_name);
+    }
+    if lut_space {
+        print!("{}", Circuit::lut_design_space(divider_builder));
+    }
 
     Ok(())
 }
@@ -1,4 +1,4 @@
-use crate::data::{Bit, Bit::Zero, Circuit, Adder};
+use crate::data::{Bit, Bit::One, Bit::Zero, Circuit, Adder, Mul, Truncation};
 use std::{collections::VecDeque};
 
 impl Circuit {
@@ -14,6 +14,10 @@ impl Circuit {
         let max_rows = n1.min(n2);
         let columns = if s.len() >= n1 + n2 {
             s.len()
+        } else if !s.is_empty() {
+            // m1 * m2 + s reaches 2^(n1 + n2) for a summand of n1 + n2 bits, like 15 * 15 + 255,
+            // so the result keeps the carry on top of the product
+            n1 + n2
         } else {
             n1 + n2 - 1
         };
@@ -34,14 +38,14 @@ impl Circuit {
             summands[idx].push_back(s[idx]);
         }
 
-        let mut sorted = self
-            .info
-            .mul_numbers_map
-            .clone()
-            .into_iter()
-            .collect::<Vec<(usize, usize)>>();
-        sorted.sort();
+        self.dadda_tree(summands, max_rows, adder)
+    }
 
+    // reduces the columns of a partial product array with half and full adders until only two
+    // rows are left (dadda scheme) and merges these two rows with the given adder,
+    // carries out of the last column are dropped so in that case only the lowest
+    // summands.len() bits of the result are correct
+    pub fn dadda_tree(&mut self, mut summands: Vec<VecDeque<Bit>>, max_rows: usize, adder: Adder) -> Vec<Bit> {
         let mut b_idx = *match self.info.mul_numbers_map.get(&max_rows) {
             Some(u) => u,
             None => {
@@ -68,7 +72,9 @@ impl Circuit {
                     };
 
                     summands[i].push_front(s_new.s);
-                    summands[i + 1].push_front(s_new.c);
+                    if i + 1 < summands.len() {
+                        summands[i + 1].push_front(s_new.c);
+                    }
                 }
             }
 
@@ -92,107 +98,231 @@ impl Circuit {
         adder.add(self, s1, s2, Zero)
     }
 
-    pub fn square_u(&mut self, m1: Vec<Bit>, ignore: usize, adder: Adder) -> Vec<Bit> {
+    // radix-4 (modified) booth multiplier, m2 gets recoded into digits of {-2, -1, 0, 1, 2}
+    // which halves the number of partial products compared to the plain and-array,
+    // the partial products are reduced by the dadda tree
+    pub fn booth_mul(&mut self, m1: Vec<Bit>, m2: Vec<Bit>, s: Option<Vec<Bit>>, adder: Adder) -> Vec<Bit> {
+        let s = s.unwrap_or_default();
+        let (n1, n2) = (m1.len(), m2.len());
+        // the summand may cause an additional carry on top of the product
+        let columns = if s.is_empty() {
+            n1 + n2
+        } else {
+            (n1 + n2).max(s.len()) + 1
+        };
+        if columns == 0 {
+            return vec![];
+        }
+
+        let mut summands = self.booth_partial_products(&m1, &m2, columns);
+        for idx in 0..s.len() {
+            summands[idx].push_back(s[idx]);
+        }
+
+        let max_rows = summands.iter().map(|column| column.len()).max().unwrap_or(0);
+        let mut p = self.dadda_tree(summands, max_rows, adder);
+        p.truncate(columns);
+        p
+    }
+
+    // builds the booth partial products of m1 * m2 modulo 2^columns sorted by columns
+    // a negative partial product is formed by inverting its bits and adding one in its lowest column,
+    // sign extension is prevented by placing the inverted sign bit above every row and adding the
+    // constant -sum(2^(m1.len() + 1 + 2i)) once, which is precomputed and only consists of One bits
+    pub fn booth_partial_products(&mut self, m1: &[Bit], m2: &[Bit], columns: usize) -> Vec<VecDeque<Bit>> {
         let n1 = m1.len();
-        let new_len = 2 * n1;
-        let max_rows = (n1 / 2) + 1;
-        let mut summands: Vec<VecDeque<Bit>> = Vec::with_capacity((new_len) as usize);
-        for _ in 0..(new_len - 1) {
-            summands.push(VecDeque::with_capacity(max_rows));
+        let groups = m2.len() / 2 + 1;
+        let mut summands: Vec<VecDeque<Bit>> = Vec::with_capacity(columns);
+        for _ in 0..columns {
+            summands.push(VecDeque::with_capacity(groups + 2));
         }
 
-        for a in 0..n1 {
-            for b in a..n1 {
-                let s = self.and(m1[a], m1[b]);
-                if a == b {
-                    summands[a + b].push_back(s);
-                } else {
-                    summands[a + b + 1].push_back(s);
+        let mut sign_weights = vec![0; columns];
+        for i in 0..groups {
+            let y_high = m2.get(2 * i + 1).copied().unwrap_or(Zero);
+            let y_mid = m2.get(2 * i).copied().unwrap_or(Zero);
+            let y_low = if i == 0 { Zero } else { m2[2 * i - 1] };
+
+            // encode the digit -2 * y_high + y_mid + y_low
+            let one = self.xor(y_mid, y_low);
+            let not_one = self.not(one);
+            let two = self.xor(y_high, y_mid);
+            let two = self.and(two, not_one);
+            let neg = y_high;
+
+            for j in 0..(n1 + 1) {
+                let column = 2 * i + j;
+                if column >= columns {
+                    break;
                 }
+                let x = m1.get(j).copied().unwrap_or(Zero);
+                let x_prev = if j == 0 { Zero } else { m1[j - 1] };
+                let single = self.and(x, one);
+                let double = self.and(x_prev, two);
+                let pp = self.or(single, double);
+                let pp = self.xor(pp, neg);
+                summands[column].push_back(pp);
             }
-        }
 
-        for i in (new_len - ignore)..(new_len - 1) {
-            summands[i].clear();
+            if 2 * i < columns {
+                summands[2 * i].push_back(neg);
+            }
+            let sign_column = 2 * i + n1 + 1;
+            if sign_column < columns {
+                let not_neg = self.not(neg);
+                summands[sign_column].push_back(not_neg);
+                sign_weights[sign_column] += 1;
+            }
         }
 
-        let mut sorted = self
-            .info
-            .mul_numbers_map
-            .clone()
-            .into_iter()
-            .collect::<Vec<(usize, usize)>>();
-        sorted.sort();
+        Self::push_negative_constant(&mut summands, &sign_weights);
+        summands
+    }
 
-        let mut b_idx = *match self.info.mul_numbers_map.get(&max_rows) {
-            Some(u) => u,
-            None => {
-                self.info.update_for_value(max_rows);
-                self.info
-                    .mul_numbers_map
-                    .get(&max_rows)
-                    .expect("this number should have been already inserted into the map!")
+    // adds the constant -sum(weights[c] * 2^c) modulo 2^summands.len() to the partial products,
+    // the two's complement is known at generation time so only its One bits are pushed
+    pub fn push_negative_constant(summands: &mut [VecDeque<Bit>], weights: &[usize]) {
+        let mut bits = vec![false; summands.len()];
+        let mut sum_carry = 0;
+        for (column, bit) in bits.iter_mut().enumerate() {
+            let v = sum_carry + weights.get(column).copied().unwrap_or(0);
+            *bit = v & 1 == 1;
+            sum_carry = v >> 1;
+        }
+
+        let mut carry = true;
+        for bit in bits.iter_mut() {
+            let inverted = !*bit;
+            *bit = inverted ^ carry;
+            carry &= inverted;
+        }
+        for (column, &bit) in bits.iter().enumerate() {
+            if bit {
+                summands[column].push_back(One);
             }
+        }
+    }
+
+    // karatsuba multiplier, both factors are split into halves m = m_high * 2^h + m_low so that only
+    // three multiplications of half the size are needed:
+    // m1 * m2 = z2 * 2^2h + ((a_low + a_high) * (b_low + b_high) - z2 - z0) * 2^h + z0
+    // the recursion stops at info.karatsuba_threshold bits where info.karatsuba_leaf takes over
+    pub fn karatsuba_mul(&mut self, m1: Vec<Bit>, m2: Vec<Bit>, s: Option<Vec<Bit>>, adder: Adder) -> Vec<Bit> {
+        let s = s.unwrap_or_default();
+        let (n1, n2) = (m1.len(), m2.len());
+        // the halves plus their carry have to be shorter than the factors, which needs at least 4 bits
+        let threshold = self.info.karatsuba_threshold.max(3);
+        if n1.min(n2) <= threshold {
+            let leaf = self.karatsuba_leaf();
+            let s = if s.is_empty() { None } else { Some(s) };
+            return leaf.mul_u(self, m1, m2, s, adder);
+        }
+
+        let h = n1.max(n2).div_ceil(2);
+        let (a_low, a_high) = Self::karatsuba_split(&m1, h);
+        let (b_low, b_high) = Self::karatsuba_split(&m2, h);
+
+        let parts = if a_high.is_empty() || b_high.is_empty() {
+            // one factor is too short to be split, two multiplications are cheaper here
+            let (long_low, long_high, short) = if a_high.is_empty() {
+                (b_low, b_high, m1)
+            } else {
+                (a_low, a_high, m2)
+            };
+            let z0 = self.karatsuba_mul(long_low, short.clone(), None, adder);
+            let z1 = self.karatsuba_mul(long_high, short, None, adder);
+            vec![(0, z0), (h, z1)]
+        } else {
+            let z0 = self.karatsuba_mul(a_low.clone(), b_low.clone(), None, adder);
+            let z2 = self.karatsuba_mul(a_high.clone(), b_high.clone(), None, adder);
+            let a_sum = adder.add(self, a_low, a_high, Zero);
+            let b_sum = adder.add(self, b_low, b_high, Zero);
+            let t = self.karatsuba_mul(a_sum, b_sum, None, adder);
+            let z1 = self.karatsuba_middle(t, &z0, &z2, adder);
+            vec![(0, z0), (h, z1), (2 * h, z2)]
         };
-        let mut boundry = self.info.list_mul_numbers[b_idx];
-        loop {
-            for i in 0..(summands.len() - 1) {
-                while summands[i].len() > boundry {
-                    let remaining = summands[i].len() - boundry;
-                    let s1 = summands[i].pop_back().expect("VecDeque shouldnt be empty");
-                    let s2 = summands[i].pop_back().expect("VecDeque shouldnt be empty");
-                    let s_new = if remaining > 1 {
-                        let s3 = summands[i].pop_back().expect("VecDeque shouldnt be empty");
-                        self.full_adder(s1, s2, s3)
-                    } else {
-                        self.half_adder(s1, s2)
-                    };
 
-                    summands[i].push_front(s_new.s);
-                    summands[i + 1].push_front(s_new.c);
-                }
-            }
+        let columns = if s.is_empty() {
+            n1 + n2
+        } else {
+            (n1 + n2).max(s.len()) + 1
+        };
+        self.karatsuba_combine(parts, s, columns, adder)
+    }
 
-            if b_idx == 0 {
-                break;
-            }
-            b_idx -= 1;
-            boundry = self.info.list_mul_numbers[b_idx];
+    // square specialized karatsuba recursion: the three sub products are squares themselves
+    // m^2 = z2 * 2^2h + ((m_low + m_high)^2 - z2 - z0) * 2^h + z0
+    pub fn karatsuba_square(&mut self, m: Vec<Bit>, adder: Adder) -> Vec<Bit> {
+        let n = m.len();
+        if n <= self.info.karatsuba_threshold.max(3) {
+            let leaf = self.karatsuba_leaf();
+            return leaf.square_u(self, m, 0, adder);
         }
 
-        let mut s1 = Vec::new();
-        let mut s2 = Vec::new();
-        for summand in &mut summands {
-            let l1 = summand.pop_front().unwrap_or(Zero);
-            let l2 = summand.pop_front().unwrap_or(Zero);
-            s1.push(l1);
-            s2.push(l2);
+        let h = n.div_ceil(2);
+        let (low, high) = Self::karatsuba_split(&m, h);
+        let z0 = self.karatsuba_square(low.clone(), adder);
+        let z2 = self.karatsuba_square(high.clone(), adder);
+        let sum = adder.add(self, low, high, Zero);
+        let t = self.karatsuba_square(sum, adder);
+        let z1 = self.karatsuba_middle(t, &z0, &z2, adder);
+
+        self.karatsuba_combine(vec![(0, z0), (h, z1), (2 * h, z2)], vec![], 2 * n, adder)
+    }
+
+    #[inline]
+    fn karatsuba_leaf(&self) -> Mul {
+        match self.info.karatsuba_leaf {
+            Mul::Karatsuba => Mul::DadaTree,
+            leaf => leaf,
         }
+    }
 
-        // call adder here
-        let mut r = adder.add(self, s1, s2, Zero);
+    #[inline]
+    fn karatsuba_split(m: &[Bit], h: usize) -> (Vec<Bit>, Vec<Bit>) {
+        let split = h.min(m.len());
+        (m[..split].to_vec(), m[split..].to_vec())
+    }
+
+    // t - z0 - z2, never negative as t >= z0 + z2
+    #[inline]
+    fn karatsuba_middle(&mut self, t: Vec<Bit>, z0: &[Bit], z2: &[Bit], adder: Adder) -> Vec<Bit> {
+        let n = t.len();
+        let mut z1 = adder.sub(self, t, z0.to_vec(), Zero);
+        z1.truncate(n);
+        let mut z1 = adder.sub(self, z1, z2.to_vec(), Zero);
+        z1.truncate(n);
+        z1
+    }
 
-        // needs to get fixed inside adder Option to not compute last carry
-        if ignore > 0 {
-            r.truncate(new_len - ignore);
+    // adds the shifted sub products (and the summand s) inside a single dadda tree
+    fn karatsuba_combine(&mut self, parts: Vec<(usize, Vec<Bit>)>, s: Vec<Bit>, columns: usize, adder: Adder) -> Vec<Bit> {
+        let mut summands: Vec<VecDeque<Bit>> = Vec::with_capacity(columns);
+        for _ in 0..columns {
+            summands.push(VecDeque::with_capacity(parts.len() + 1));
+        }
+        for (shift, part) in parts.iter().chain(std::iter::once(&(0, s))) {
+            for (idx, &bit) in part.iter().enumerate() {
+                if shift + idx < columns && bit != Zero {
+                    summands[shift + idx].push_back(bit);
+                }
+            }
         }
-        r
+
+        let max_rows = summands.iter().map(|column| column.len()).max().unwrap_or(0);
+        let mut p = self.dadda_tree(summands, max_rows, adder);
+        p.truncate(columns);
+        p
     }
 
-    // computes: 
-    // result = minuend - factor1 * factor2 
+    // computes:
+    // result = minuend - factor1 * factor2
     // in a fused multiplication circuit
     // analog to fused-multiply-add
-    #[allow(dead_code)]
-    pub fn fused_mul_subtraction(&mut self, mut minuend: Vec<Bit>, f1: Vec<Bit>, f2: Vec<Bit>, fill_from_least_significant: bool) -> Vec<Bit> {
+    // the minuend is padded with zeroes to the length of the product, either below or above its bits
+    pub fn fused_mul_subtraction(&mut self, mut minuend: Vec<Bit>, f1: Vec<Bit>, f2: Vec<Bit>, fill_from_least_significant: bool, mul: Mul, adder: Adder) -> Vec<Bit> {
         let final_number_bits = f1.len() + f2.len();
-        // if minuend.len() > final_number_bits {
-        //     panic!("minuend has not the right number of bits! to many!");
-        // }
-        let (mut number, overflowed) = final_number_bits.overflowing_sub( minuend.len());
-        if overflowed {
-            number = 0;
-        }
+        let number = final_number_bits.saturating_sub(minuend.len());
         let mut tail = Bit::zeroes(number);
         if fill_from_least_significant {
             tail.append(&mut minuend);
@@ -200,12 +330,236 @@ impl Circuit {
         } else {
             minuend.append(&mut tail);
         }
-        self.not_all(&mut minuend);
-        let mut p = self.mul_unsigned_clean(f1, f2, Some(minuend), Adder::KSA);
-        p.pop(); //#fixme! there seem to be certain bits lengths of the input vectors where the last bit is messed up
-        self.not_all(&mut p);
+        self.fused_mul_sub(minuend, f1, f2, mul, adder)
+    }
+
+    // a + b * c, the result has one bit more than the longest of a and b * c
+    pub fn fused_mul_add(&mut self, a: Vec<Bit>, b: Vec<Bit>, c: Vec<Bit>, mul: Mul, adder: Adder) -> Vec<Bit> {
+        let width = a.len().max(b.len() + c.len()) + 1;
+        self.fused_products(a, &[(b, c, false)], width, mul, adder)
+    }
+
+    // a - b * c modulo 2^max(a.len(), b.len() + c.len()),
+    // so the result is only the plain difference if a >= b * c
+    pub fn fused_mul_sub(&mut self, a: Vec<Bit>, b: Vec<Bit>, c: Vec<Bit>, mul: Mul, adder: Adder) -> Vec<Bit> {
+        let width = a.len().max(b.len() + c.len());
+        self.fused_products(a, &[(b, c, true)], width, mul, adder)
+    }
+
+    // a * b + c * d, the result has one bit more than the longest of both products
+    #[allow(dead_code)]
+    pub fn fused_dot_add(&mut self, a: Vec<Bit>, b: Vec<Bit>, c: Vec<Bit>, d: Vec<Bit>, mul: Mul, adder: Adder) -> Vec<Bit> {
+        let width = (a.len() + b.len()).max(c.len() + d.len()) + 1;
+        self.fused_products(Vec::new(), &[(a, b, false), (c, d, false)], width, mul, adder)
+    }
+
+    // a * b - c * d modulo 2^max(a.len() + b.len(), c.len() + d.len())
+    #[allow(dead_code)]
+    pub fn fused_dot_sub(&mut self, a: Vec<Bit>, b: Vec<Bit>, c: Vec<Bit>, d: Vec<Bit>, mul: Mul, adder: Adder) -> Vec<Bit> {
+        let width = (a.len() + b.len()).max(c.len() + d.len());
+        self.fused_products(Vec::new(), &[(a, b, false), (c, d, true)], width, mul, adder)
+    }
+
+    // sums the addend and all (f1, f2, negate) products modulo 2^width in a single dadda tree,
+    // a negated product contributes its inverted partial product bits, the weights of all inverted
+    // bits are subtracted again by one precomputed constant, only dadda and booth multipliers
+    // hand over their partial products, array and karatsuba multipliers finish every product
+    // on their own and the tree merely adds them, so with those it is multiply then add
+    fn fused_products(&mut self, addend: Vec<Bit>, products: &[(Vec<Bit>, Vec<Bit>, bool)], width: usize, mul: Mul, adder: Adder) -> Vec<Bit> {
+        if width == 0 {
+            return Vec::new();
+        }
+        let mut summands: Vec<VecDeque<Bit>> = Vec::with_capacity(width);
+        for _ in 0..width {
+            summands.push(VecDeque::new());
+        }
+        let mut inverted_weights = vec![0; width];
+
+        for (f1, f2, negate) in products {
+            let rows = mul.partial_products(self, f1, f2, width, adder);
+            for (column, row) in rows.into_iter().enumerate().take(width) {
+                for bit in row {
+                    if *negate {
+                        let inverted = self.not(bit);
+                        summands[column].push_back(inverted);
+                        inverted_weights[column] += 1;
+                    } else {
+                        summands[column].push_back(bit);
+                    }
+                }
+            }
+        }
+        if products.iter().any(|(_, _, negate)| *negate) {
+            Self::push_negative_constant(&mut summands, &inverted_weights);
+        }
+
+        for (column, &bit) in addend.iter().enumerate().take(width) {
+            summands[column].push_back(bit);
+        }
+
+        let max_rows = summands.iter().map(|column| column.len()).max().unwrap_or(0);
+        let mut p = self.dadda_tree(summands, max_rows, adder);
+        p.truncate(width);
+        p
+    }
+
+    // the plain and-array of m1 * m2 modulo 2^columns sorted by columns
+    pub fn and_partial_products(&mut self, m1: &[Bit], m2: &[Bit], columns: usize) -> Vec<VecDeque<Bit>> {
+        let mut summands: Vec<VecDeque<Bit>> = Vec::with_capacity(columns);
+        for _ in 0..columns {
+            summands.push(VecDeque::with_capacity(m1.len().min(m2.len())));
+        }
+        for (a, &x) in m1.iter().enumerate() {
+            for (b, &y) in m2.iter().enumerate() {
+                if a + b < columns {
+                    let s = self.and(x, y);
+                    summands[a + b].push_back(s);
+                }
+            }
+        }
+        summands
+    }
+
+    // fixed-width multiplier, computes m1 * m2 without its lowest `drop` bits
+    // only the partial products of the columns above drop - guard are built, the guard columns are
+    // chosen so that the compensated error of the omitted columns stays below half a unit of the result
+    pub fn truncated_mul(&mut self, m1: Vec<Bit>, m2: Vec<Bit>, drop: usize, truncation: Truncation, adder: Adder) -> Vec<Bit> {
+        let (n1, n2) = (m1.len(), m2.len());
+        if n1 == 0 || n2 == 0 {
+            return Bit::zeroes((n1 + n2).saturating_sub(drop));
+        }
+        let heights = Self::mul_column_heights(n1, n2);
+        self.truncated_product(&heights, drop, truncation, adder, |circuit, column| {
+            let mut bits = Vec::with_capacity(n1.min(n2));
+            for a in column.saturating_sub(n2 - 1)..=column.min(n1 - 1) {
+                bits.push(circuit.and(m1[a], m2[column - a]));
+            }
+            bits
+        })
+    }
+
+    // number of partial products in every column of the and-array of an n1 x n2 multiplication
+    pub fn mul_column_heights(n1: usize, n2: usize) -> Vec<usize> {
+        let width = n1 + n2;
+        (0..width)
+            .map(|c| (c + 1).min(n1).min(n2).min((width - 1).saturating_sub(c)))
+            .collect()
+    }
+
+    // smallest number of guard columns below `drop` for which the compensated truncation error
+    // stays below half a unit of the result, returns (guard, constant, error bound in units of 2^drop)
+    pub fn truncation_guard_columns(heights: &[usize], drop: usize, truncation: Truncation) -> (usize, u64, f64) {
+        let drop = drop.min(heights.len());
+        for guard in 0..drop {
+            let keep_from = drop - guard;
+            let (constant, bound) = Self::truncation_correction(heights, keep_from, truncation);
+            let bound = bound / 2f64.powi(guard as i32);
+            if bound <= 0.5 {
+                return (guard, constant, bound);
+            }
+        }
+        (drop, 0, 0.0)
+    }
+
+    // how much the sum of the columns from keep_from upwards (in units of 2^keep_from) can grow
+    // before it reaches 2^heights.len() if every partial product is set, as they are for operands
+    // of all ones, none if the partial products of all columns can already exceed the width,
+    // then the caller ignores the top bits of a value known to be small enough like square_u
+    fn truncation_headroom(heights: &[usize], keep_from: usize) -> Option<u64> {
+        // the capacity left in units of the current column, going down from 2^width, once it
+        // is far from zero the few partial products of the lower columns cannot change its sign
+        let limit = 1i128 << 100;
+        let mut capacity = 1i128;
+        let mut headroom = 0;
+        for c in (0..heights.len()).rev() {
+            capacity = (2 * capacity - heights[c] as i128).clamp(-limit, limit);
+            if c == keep_from {
+                headroom = capacity - 1;
+            }
+        }
+        if keep_from >= heights.len() {
+            headroom = 0;
+        }
+        (capacity >= 1).then_some(headroom.clamp(0, u64::MAX as i128) as u64)
+    }
+
+    // correction constant (in units of 2^keep_from) and the bound of the remaining absolute error
+    // (in units of 2^keep_from) when all partial products below column keep_from are omitted
+    // the omitted value lies in [0, m] where m = sum(heights[c] * 2^c) over all omitted columns c,
+    // the constant is chosen to center the remaining error around zero but limited to the
+    // headroom, so the compensated sum of a product never wraps around
+    pub fn truncation_correction(heights: &[usize], keep_from: usize, truncation: Truncation) -> (u64, f64) {
+        let headroom = Self::truncation_headroom(heights, keep_from).unwrap_or(u64::MAX);
+        let omitted = |upto: usize| -> f64 {
+            (0..upto.min(heights.len()))
+                .map(|c| heights[c] as f64 * 2f64.powi(c as i32 - keep_from as i32))
+                .sum()
+        };
+        match truncation {
+            Truncation::None => (0, 0.0),
+            Truncation::Constant => {
+                // error = constant - omitted in [constant - m, constant]
+                let m = omitted(keep_from);
+                let constant = (m / 2.0).round().min(headroom as f64);
+                (constant as u64, (m - constant).max(constant))
+            }
+            Truncation::Variable => {
+                if keep_from == 0 {
+                    return (0, 0.0);
+                }
+                // the bits s of column keep_from - 1 are counted twice, so
+                // error = constant + s - rest with s in [0, h / 2] and rest in [0, m]
+                let m = omitted(keep_from - 1);
+                let s_max = heights.get(keep_from - 1).copied().unwrap_or(0) as f64 / 2.0;
+                // the doubled bits take up to h units of the headroom, without enough of it
+                // the column cannot be doubled
+                let doubled = heights.get(keep_from - 1).copied().unwrap_or(0) as u64;
+                if doubled > headroom {
+                    return (0, f64::INFINITY);
+                }
+                let constant = ((m - s_max) / 2.0).round().max(0.0).min(headroom.saturating_sub(doubled) as f64);
+                (constant as u64, (m - constant).max(constant + s_max))
+            }
+        }
+    }
+
+    // builds the columns from drop - guard upwards, compensates the omitted ones and
+    // returns the sum without the guard columns
+    pub fn truncated_product<F>(&mut self, heights: &[usize], drop: usize, truncation: Truncation, adder: Adder, mut column_bits: F) -> Vec<Bit>
+    where
+        F: FnMut(&mut Circuit, usize) -> Vec<Bit>,
+    {
+        let width = heights.len();
+        let drop = drop.min(width);
+        let (guard, constant, _) = Self::truncation_guard_columns(heights, drop, truncation);
+        let keep_from = drop - guard;
+        let columns = width - keep_from;
+        if columns == 0 {
+            return vec![];
+        }
+
+        let mut summands: Vec<VecDeque<Bit>> = Vec::with_capacity(columns);
+        for column in keep_from..width {
+            summands.push(VecDeque::from(column_bits(self, column)));
+        }
+        if truncation == Truncation::Variable && keep_from > 0 {
+            for bit in column_bits(self, keep_from - 1) {
+                summands[0].push_back(bit);
+            }
+        }
+        for (idx, summand) in summands.iter_mut().enumerate().take(u64::BITS as usize) {
+            if (constant >> idx) & 0x1 == 1 {
+                summand.push_back(One);
+            }
+        }
+
+        // the constant stays within the headroom, so the sum never carries out of the width
+        let max_rows = summands.iter().map(|column| column.len()).max().unwrap_or(0);
+        let mut p = self.dadda_tree(summands, max_rows, adder);
+        p.truncate(columns);
+        p.drain(0..guard);
         p
-    } 
+    }
 
     // naive version of an array multiplier depth O(n)
     #[allow(dead_code)]
@@ -0,0 +1,760 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt;
+
+use clap::ValueEnum;
+
+use crate::cuts::Cut;
+use crate::data::{Bit, Circuit, Gate, Line, Wire};
+
+// optimization passes, every pass keeps the function of the outputs and registers and ends
+// with removing the gates nothing depends on anymore
+// ConstProp: replaces gates with a constant value, like the and of a line and its inverse,
+// including constants hidden in the cuts of up to 4 inputs, and the zero and one wires
+// DoubleNot: connects the inverse of an inverter to the line it inverts and removes the
+// inverters of both inputs of a xor
+// Strash: merges gates computing the same function of the same lines, found by hashing the
+// inputs of every gate and the truth tables of its cuts with up to 4 leaves
+// Rewrite: replaces the gates only needed by a gate, seen from one of its cuts with up to 4
+// leaves, by a smaller implementation of the cut's function, the implementations are built
+// once for every npn class of 4-input functions, the depth of the gate does not grow
+// Balance: rebuilds trees of and, or and xor gates whose inner gates have no other fanout,
+// always combining the two inputs arriving first, on a deepest path gates with other
+// fanouts are duplicated into a tree if that lowers its depth
+#[derive(Debug, PartialEq, Eq, Clone, Copy, ValueEnum)]
+pub enum Pass {
+    ConstProp,
+    DoubleNot,
+    Strash,
+    Rewrite,
+    Balance,
+}
+
+impl fmt::Display for Pass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_possible_value().unwrap().get_name())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PassStats {
+    pub pass: Pass,
+    pub gates_before: usize,
+    pub gates_after: usize,
+    pub depth_before: usize,
+    pub depth_after: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct OptimizationReport {
+    pub passes: Vec<PassStats>,
+}
+
+impl fmt::Display for OptimizationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<12} {:>8} {:>8} {:>8} {:>8}", "pass", "gates", "after", "depth", "after")?;
+        for p in &self.passes {
+            writeln!(
+                f,
+                "{:<12} {:>8} {:>8} {:>8} {:>8}",
+                p.pass.to_string(),
+                p.gates_before,
+                p.gates_after,
+                p.depth_before,
+                p.depth_after
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn level(bit: Bit) -> usize {
+    match bit {
+        Bit::Var(l) => l.level,
+        _ => 0,
+    }
+}
+
+// the bit replacing line n in a rebuilt circuit, inputs and registers stay the same
+fn mapped(bits: &HashMap<usize, Bit>, n: usize) -> Bit {
+    bits.get(&n).copied().unwrap_or(Bit::Var(Line { level: 0, n }))
+}
+
+impl Circuit {
+    pub fn optimize(&mut self, passes: &[Pass]) -> OptimizationReport {
+        let mut report = OptimizationReport { passes: Vec::new() };
+        for &pass in passes {
+            self.update_stats();
+            let (gates_before, depth_before) = (self.stats.gatter_count, self.stats.level_count);
+            match pass {
+                Pass::ConstProp => self.propagate_constants(),
+                Pass::DoubleNot => self.remove_double_negations(),
+                Pass::Strash => self.hash_gates(),
+                Pass::Rewrite => self.rewrite(),
+                Pass::Balance => self.balance(),
+            }
+            report.passes.push(PassStats {
+                pass,
+                gates_before,
+                gates_after: self.stats.gatter_count,
+                depth_before,
+                depth_after: self.stats.level_count,
+            });
+        }
+        report
+    }
+
+    // builds the circuit again gate by gate, build gets the index of the old wire, the
+    // bits replacing its inputs and the bits replacing all lines so far and returns the bit
+    // replacing its output, new gates belong to the component of the old one
+    fn rebuild(&mut self, mut build: impl FnMut(&mut Circuit, usize, &[Bit], &HashMap<usize, Bit>) -> Bit) {
+        let wires = std::mem::take(&mut self.wires);
+        let mut bits: HashMap<usize, Bit> = HashMap::with_capacity(wires.len());
+        if let Some(l) = self.zero_wire {
+            bits.insert(l.n, Bit::Zero);
+        }
+        if let Some(l) = self.one_wire {
+            bits.insert(l.n, Bit::One);
+        }
+        for (idx, wire) in wires.iter().enumerate() {
+            let inputs: Vec<Bit> = wire.gate.inputs().iter().map(|l| mapped(&bits, l.n)).collect();
+            self.scope = wire.scope;
+            let bit = build(self, idx, &inputs, &bits);
+            bits.insert(wire.out.n, bit);
+        }
+        self.scope = 0;
+
+        for bit in self.outputs.iter_mut().flat_map(|o| o.bits.iter_mut()) {
+            if let Bit::Var(l) = *bit {
+                *bit = mapped(&bits, l.n);
+            }
+        }
+        for register in &mut self.registers {
+            if let Bit::Var(l) = register.d {
+                register.d = mapped(&bits, l.n);
+            }
+        }
+        self.rename_outputs();
+        self.remove_dead_ends();
+    }
+
+    // the gate of the old wire built with the primitives, which fold constant inputs
+    fn build_gate(&mut self, gate: &Gate, inputs: &[Bit]) -> Bit {
+        match gate {
+            Gate::Not(_) => self.not(inputs[0]),
+            Gate::And(..) => self.and(inputs[0], inputs[1]),
+            Gate::Or(..) => self.or(inputs[0], inputs[1]),
+            Gate::Xor(..) => self.xor(inputs[0], inputs[1]),
+        }
+    }
+
+    // the line inverted by the gate driving bit if it is an inverter
+    fn inverted(&self, bit: Bit, gates: &HashMap<usize, usize>) -> Option<Line> {
+        let Bit::Var(l) = bit else {
+            return None;
+        };
+        match self.wires[*gates.get(&l.n)?].gate {
+            Gate::Not(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    fn propagate_constants(&mut self) {
+        let wires = self.wires.clone();
+        let cuts = self.enumerate_cuts(4, 8);
+        // index of the gate driving every new line
+        let mut gates: HashMap<usize, usize> = HashMap::new();
+        self.rebuild(|c, idx, inputs, _| {
+            let gate = wires[idx].gate;
+            let bit = if let Some(cut) = cuts[idx].iter().find(|cut| cut.leaves.is_empty()) {
+                if cut.truth == 0 {
+                    Bit::Zero
+                } else {
+                    Bit::One
+                }
+            } else {
+                let complement = match (inputs.first(), inputs.get(1)) {
+                    (Some(&Bit::Var(a)), Some(&Bit::Var(b))) => {
+                        c.inverted(Bit::Var(a), &gates).is_some_and(|i| i.n == b.n)
+                            || c.inverted(Bit::Var(b), &gates).is_some_and(|i| i.n == a.n)
+                    }
+                    _ => false,
+                };
+                match (gate, complement) {
+                    (Gate::And(..), true) => Bit::Zero,
+                    (Gate::Or(..) | Gate::Xor(..), true) => Bit::One,
+                    _ => c.build_gate(&gate, inputs),
+                }
+            };
+            if let Bit::Var(l) = bit {
+                if c.wires.last().is_some_and(|w| w.out == l) {
+                    gates.insert(l.n, c.wires.len() - 1);
+                }
+            }
+            bit
+        });
+    }
+
+    fn remove_double_negations(&mut self) {
+        let wires = self.wires.clone();
+        let mut gates: HashMap<usize, usize> = HashMap::new();
+        self.rebuild(|c, idx, inputs, _| {
+            let bit = match wires[idx].gate {
+                Gate::Not(_) => match c.inverted(inputs[0], &gates) {
+                    Some(l) => Bit::Var(l),
+                    None => c.not(inputs[0]),
+                },
+                Gate::Xor(..) => match (c.inverted(inputs[0], &gates), c.inverted(inputs[1], &gates)) {
+                    (Some(a), Some(b)) => c.xor(Bit::Var(a), Bit::Var(b)),
+                    _ => c.xor(inputs[0], inputs[1]),
+                },
+                gate => c.build_gate(&gate, inputs),
+            };
+            if let Bit::Var(l) = bit {
+                if c.wires.last().is_some_and(|w| w.out == l) {
+                    gates.insert(l.n, c.wires.len() - 1);
+                }
+            }
+            bit
+        });
+    }
+
+    fn hash_gates(&mut self) {
+        let wires = self.wires.clone();
+        let cuts = self.enumerate_cuts(4, 8);
+        // the first old line with a cut of the same leaves and truth table
+        let mut functions: HashMap<&Cut, usize> = HashMap::new();
+        // the new line of every gate type with the same inputs, 0 is not, 1 and, 2 or, 3 xor
+        let mut structures: HashMap<(usize, usize, usize), Bit> = HashMap::new();
+        self.rebuild(|c, idx, inputs, bits| {
+            if let Some(&other) = cuts[idx].iter().find_map(|cut| functions.get(cut)) {
+                return mapped(bits, other);
+            }
+            for cut in &cuts[idx] {
+                functions.entry(cut).or_insert(wires[idx].out.n);
+            }
+            let key = match (wires[idx].gate, inputs) {
+                (Gate::Not(_), [Bit::Var(a)]) => Some((0, a.n, a.n)),
+                (gate, [Bit::Var(a), Bit::Var(b)]) => {
+                    let kind = match gate {
+                        Gate::And(..) => 1,
+                        Gate::Or(..) => 2,
+                        _ => 3,
+                    };
+                    Some((kind, a.n.min(b.n), a.n.max(b.n)))
+                }
+                _ => None,
+            };
+            match key.and_then(|key| structures.get(&key).copied()) {
+                Some(bit) => bit,
+                None => {
+                    let bit = c.build_gate(&wires[idx].gate, inputs);
+                    if let Some(key) = key {
+                        structures.insert(key, bit);
+                    }
+                    bit
+                }
+            }
+        });
+    }
+
+    fn rewrite(&mut self) {
+        let wires = self.wires.clone();
+        let cuts = self.enumerate_cuts(4, 8);
+        let wire_of_line: HashMap<usize, usize> = wires.iter().enumerate().map(|(idx, w)| (w.out.n, idx)).collect();
+        let mut references = self.fanouts();
+        let mut classes = NpnClasses::default();
+        self.rebuild(|c, idx, inputs, bits| {
+            let current = 1 + inputs.iter().map(|&b| level(b)).max().unwrap_or(0);
+            let mut best: Option<(usize, usize, &Cut)> = None;
+            for cut in &cuts[idx] {
+                let saved = mffc_size(&wires, &wire_of_line, &mut references, wires[idx].out.n, &cut.leaves);
+                let leaves: Vec<usize> = cut.leaves.iter().map(|&n| level(mapped(bits, n))).collect();
+                let (cost, new_level) = classes.cost(truth4(cut.truth, cut.leaves.len()), &leaves);
+                if cost < saved && new_level <= current && best.is_none_or(|(gain, _, _)| saved - cost > gain) {
+                    best = Some((saved - cost, new_level, cut));
+                }
+            }
+            match best {
+                Some((_, _, cut)) => {
+                    let leaves: Vec<Bit> = cut.leaves.iter().map(|&n| mapped(bits, n)).collect();
+                    classes.build(c, truth4(cut.truth, cut.leaves.len()), &leaves)
+                }
+                None => c.build_gate(&wires[idx].gate, inputs),
+            }
+        });
+    }
+
+    fn balance(&mut self) {
+        let wires = self.wires.clone();
+        let wire_of_line: HashMap<usize, usize> = wires.iter().enumerate().map(|(idx, w)| (w.out.n, idx)).collect();
+        let fanouts = self.fanouts();
+        // gates inside a tree, they have a single fanout which is a gate of the same type
+        let mut inner: HashSet<usize> = HashSet::new();
+        for wire in &wires {
+            for l in wire.gate.inputs() {
+                if let Some(&input) = wire_of_line.get(&l.n) {
+                    let same = std::mem::discriminant(&wires[input].gate) == std::mem::discriminant(&wire.gate);
+                    if same && fanouts[&l.n] == 1 && !matches!(wire.gate, Gate::Not(_)) {
+                        inner.insert(input);
+                    }
+                }
+            }
+        }
+        // the gates on a deepest path, the number of levels after every gate is found backwards
+        let mut tail: HashMap<usize, usize> = HashMap::with_capacity(wires.len());
+        for wire in wires.iter().rev() {
+            let after = tail.get(&wire.out.n).copied().unwrap_or(0) + 1;
+            for l in wire.gate.inputs() {
+                let t = tail.entry(l.n).or_insert(0);
+                *t = (*t).max(after);
+            }
+        }
+        let depth = self.stats.level_count;
+        let critical = |idx: usize| wires[idx].out.level + tail.get(&wires[idx].out.n).copied().unwrap_or(0) >= depth;
+        self.rebuild(|c, idx, inputs, bits| {
+            let gate = wires[idx].gate;
+            if inner.contains(&idx) {
+                // built as part of the tree it belongs to
+                return Bit::Zero;
+            }
+            if matches!(gate, Gate::Not(_)) {
+                return c.not(inputs[0]);
+            }
+            let same = |n: &usize| {
+                wire_of_line
+                    .get(n)
+                    .is_some_and(|&input| std::mem::discriminant(&wires[input].gate) == std::mem::discriminant(&gate))
+            };
+            let leaves = |lines: &[usize]| simplify(&gate, lines.iter().map(|&n| mapped(bits, n)).collect());
+            let mut lines = Vec::new();
+            tree_leaves(&wires, &wire_of_line, &inner, idx, &mut lines);
+            let mut depth = tree_level(&leaves(&lines));
+            // on a deepest path the latest leaf driven by a gate of the same type with other
+            // fanouts is put into the tree as well, duplicating its gates, as long as that
+            // lowers the depth
+            for _ in 0..if critical(idx) { 4 } else { 0 } {
+                let Some(position) = (0..lines.len()).filter(|&p| same(&lines[p])).max_by_key(|&p| level(mapped(bits, lines[p]))) else {
+                    break;
+                };
+                let mut expanded = lines.clone();
+                let n = expanded.remove(position);
+                tree_leaves(&wires, &wire_of_line, &inner, wire_of_line[&n], &mut expanded);
+                let expanded_depth = tree_level(&leaves(&expanded));
+                if expanded.len() > 16 || expanded_depth >= depth {
+                    break;
+                }
+                (lines, depth) = (expanded, expanded_depth);
+            }
+
+            // the two leaves arriving first are combined first, which gives the tree with
+            // the least depth, on equal levels the older one goes first
+            let mut nodes = leaves(&lines);
+            let mut queue: BinaryHeap<Reverse<(usize, usize)>> = nodes.iter().enumerate().map(|(i, &bit)| Reverse((level(bit), i))).collect();
+            while queue.len() > 1 {
+                let (Reverse((_, a)), Reverse((_, b))) = (queue.pop().unwrap(), queue.pop().unwrap());
+                let bit = c.build_gate(&gate, &[nodes[a], nodes[b]]);
+                nodes.push(bit);
+                queue.push(Reverse((level(bit), nodes.len() - 1)));
+            }
+            queue.pop().map_or(Bit::Zero, |Reverse((_, i))| nodes[i])
+        });
+    }
+}
+
+// the lines entering the tree of same gates rooted at wire idx
+fn tree_leaves(wires: &[Wire], wire_of_line: &HashMap<usize, usize>, inner: &HashSet<usize>, idx: usize, leaves: &mut Vec<usize>) {
+    for l in wires[idx].gate.inputs() {
+        match wire_of_line.get(&l.n) {
+            Some(&input) if inner.contains(&input) => tree_leaves(wires, wire_of_line, inner, input, leaves),
+            _ => leaves.push(l.n),
+        }
+    }
+}
+
+// the leaves of a tree of and, or or xor gates without the ones not changing its value,
+// a leaf appearing twice cancels itself in a xor and is needed once in an and or an or
+fn simplify(gate: &Gate, leaves: Vec<Bit>) -> Vec<Bit> {
+    let mut r: Vec<Bit> = Vec::with_capacity(leaves.len());
+    for bit in leaves {
+        match (gate, r.iter().position(|&b| b == bit)) {
+            (Gate::Xor(..), Some(p)) => {
+                r.remove(p);
+            }
+            (_, Some(_)) => (),
+            (_, None) => r.push(bit),
+        }
+    }
+    r
+}
+
+// the depth of the tree built from leaves by always combining the two leaves arriving first
+fn tree_level(leaves: &[Bit]) -> usize {
+    let mut queue: BinaryHeap<Reverse<usize>> = leaves.iter().map(|&b| Reverse(level(b))).collect();
+    while queue.len() > 1 {
+        let (Reverse(a), Reverse(b)) = (queue.pop().unwrap(), queue.pop().unwrap());
+        queue.push(Reverse(a.max(b) + 1));
+    }
+    queue.pop().map_or(0, |Reverse(l)| l)
+}
+
+// the number of gates that become unused if the gate driving line n is replaced by a
+// function of the leaves, the gate itself included, references are restored afterwards
+fn mffc_size(wires: &[Wire], wire_of_line: &HashMap<usize, usize>, references: &mut HashMap<usize, usize>, n: usize, leaves: &[usize]) -> usize {
+    let mut released = Vec::new();
+    let mut stack = vec![n];
+    let mut count = 0;
+    while let Some(n) = stack.pop() {
+        count += 1;
+        for l in wires[wire_of_line[&n]].gate.inputs() {
+            if leaves.contains(&l.n) || !wire_of_line.contains_key(&l.n) {
+                continue;
+            }
+            let r = references.get_mut(&l.n).unwrap();
+            *r -= 1;
+            released.push(l.n);
+            if *r == 0 {
+                stack.push(l.n);
+            }
+        }
+    }
+    for n in released {
+        *references.get_mut(&n).unwrap() += 1;
+    }
+    count
+}
+
+// the function of a cut with up to 4 leaves as function of 4 inputs
+fn truth4(truth: u64, leaves: usize) -> u16 {
+    let mask = (1 << leaves) - 1;
+    (0..16).fold(0, |t, m| t | ((((truth >> (m & mask)) & 0x1) as u16) << m))
+}
+
+const VARS4: [u16; 4] = [0xaaaa, 0xcccc, 0xf0f0, 0xff00];
+
+// a formula of the inputs of a 4-input function
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+enum Expr {
+    Const(bool),
+    Leaf(usize),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Xor(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    // equal parts of the formula are built once
+    fn gates(&self) -> usize {
+        fn count<'a>(e: &'a Expr, seen: &mut HashSet<&'a Expr>) -> usize {
+            if !seen.insert(e) {
+                return 0;
+            }
+            match e {
+                Expr::Const(_) | Expr::Leaf(_) => 0,
+                Expr::Not(a) => 1 + count(a, seen),
+                Expr::And(a, b) | Expr::Or(a, b) | Expr::Xor(a, b) => 1 + count(a, seen) + count(b, seen),
+            }
+        }
+        count(self, &mut HashSet::new())
+    }
+
+    // the level of the output if input i has level levels[i]
+    fn level(&self, levels: &[usize]) -> usize {
+        match self {
+            Expr::Const(_) => 0,
+            Expr::Leaf(i) => levels[*i],
+            Expr::Not(e) => 1 + e.level(levels),
+            Expr::And(a, b) | Expr::Or(a, b) | Expr::Xor(a, b) => 1 + a.level(levels).max(b.level(levels)),
+        }
+    }
+
+    fn build(&self, c: &mut Circuit, inputs: &[Bit], built: &mut HashMap<Expr, Bit>) -> Bit {
+        if let Some(&bit) = built.get(self) {
+            return bit;
+        }
+        let bit = match self {
+            Expr::Const(false) => Bit::Zero,
+            Expr::Const(true) => Bit::One,
+            Expr::Leaf(i) => inputs[*i],
+            Expr::Not(e) => {
+                let e = e.build(c, inputs, built);
+                c.not(e)
+            }
+            Expr::And(a, b) => {
+                let (a, b) = (a.build(c, inputs, built), b.build(c, inputs, built));
+                c.and(a, b)
+            }
+            Expr::Or(a, b) => {
+                let (a, b) = (a.build(c, inputs, built), b.build(c, inputs, built));
+                c.or(a, b)
+            }
+            Expr::Xor(a, b) => {
+                let (a, b) = (a.build(c, inputs, built), b.build(c, inputs, built));
+                c.xor(a, b)
+            }
+        };
+        built.insert(self.clone(), bit);
+        bit
+    }
+}
+
+// g(y) = out ^ f(x) with x[i] = y[perm[i]] ^ bit i of negate, applying it to the npn class
+// representative g gives back f
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Transform {
+    perm: [usize; 4],
+    negate: usize,
+    out: bool,
+}
+
+fn transform(t: u16, tr: &Transform) -> u16 {
+    let mut g = 0;
+    for m in 0..16 {
+        let x = (0..4).fold(0, |x, i| x | ((((m >> tr.perm[i]) & 0x1) ^ ((tr.negate >> i) & 0x1)) << i));
+        let bit = ((t >> x) & 0x1) ^ tr.out as u16;
+        g |= bit << m;
+    }
+    g
+}
+
+// the smallest formulas found for the representatives of the npn classes seen so far
+#[derive(Default)]
+struct NpnClasses {
+    canonical: HashMap<u16, (u16, Transform)>,
+    formulas: HashMap<u16, Expr>,
+    synthesized: HashMap<u16, Expr>,
+}
+
+impl NpnClasses {
+    // the representative is the smallest truth table of the class, among the transforms
+    // leading to it the one with the fewest inverters is used
+    fn class(&mut self, t: u16) -> (u16, Transform) {
+        if let Some(&c) = self.canonical.get(&t) {
+            return c;
+        }
+        let mut perms = Vec::new();
+        for a in 0..4 {
+            for b in (0..4).filter(|&b| b != a) {
+                for c in (0..4).filter(|&c| c != a && c != b) {
+                    perms.push([a, b, c, 6 - a - b - c]);
+                }
+            }
+        }
+        let mut best: Option<(u16, u32, Transform)> = None;
+        for &perm in &perms {
+            for negate in 0..16 {
+                for out in [false, true] {
+                    let tr = Transform { perm, negate, out };
+                    let g = transform(t, &tr);
+                    let inverters = (negate as u32).count_ones() + out as u32;
+                    if best.is_none_or(|(b, i, _)| (g, inverters) < (b, i)) {
+                        best = Some((g, inverters, tr));
+                    }
+                }
+            }
+        }
+        let (g, _, tr) = best.unwrap();
+        self.canonical.insert(t, (g, tr));
+        (g, tr)
+    }
+
+    fn formula(&mut self, t: u16) -> Expr {
+        let (g, tr) = self.class(t);
+        if !self.formulas.contains_key(&g) {
+            let formula = self.synthesize(g);
+            self.formulas.insert(g, formula);
+        }
+        let formula = &self.formulas[&g];
+        // the representative reads input perm[i] as x[i] ^ negate[i]
+        let mut leaves: Vec<Expr> = vec![Expr::Const(false); 4];
+        for i in 0..4 {
+            leaves[tr.perm[i]] = match (tr.negate >> i) & 0x1 {
+                1 => Expr::Not(Box::new(Expr::Leaf(i))),
+                _ => Expr::Leaf(i),
+            };
+        }
+        let f = substitute(formula, &leaves);
+        match tr.out {
+            true => normalize(&Expr::Not(Box::new(f))),
+            false => normalize(&f),
+        }
+    }
+
+    // gate count and output level of the formula of t for inputs with the given levels
+    fn cost(&mut self, t: u16, levels: &[usize]) -> (usize, usize) {
+        let f = self.formula(t);
+        let mut levels = levels.to_vec();
+        levels.resize(4, 0);
+        (f.gates(), f.level(&levels))
+    }
+
+    fn build(&mut self, c: &mut Circuit, t: u16, inputs: &[Bit]) -> Bit {
+        let mut inputs = inputs.to_vec();
+        inputs.resize(4, Bit::Zero);
+        self.formula(t).build(c, &inputs, &mut HashMap::new())
+    }
+
+    // the smaller one of a formula for t and the inverse of a formula for !t
+    fn synthesize(&mut self, t: u16) -> Expr {
+        let direct = self.decompose(t);
+        let inverse = normalize(&Expr::Not(Box::new(self.decompose(!t))));
+        match inverse.gates() < direct.gates() {
+            true => inverse,
+            false => direct,
+        }
+    }
+
+    // tries splitting t into functions of fewer inputs combined by a single gate and
+    // the shannon expansions by every input
+    fn decompose(&mut self, t: u16) -> Expr {
+        if let Some(e) = self.synthesized.get(&t) {
+            return e.clone();
+        }
+        let cofactors = |t: u16, i: usize| -> (u16, u16) {
+            let (v, s) = (VARS4[i], 1 << i);
+            let one = t & v;
+            let zero = t & !v;
+            (zero | (zero << s), one | (one >> s))
+        };
+        let support: Vec<usize> = (0..4).filter(|&i| cofactors(t, i).0 != cofactors(t, i).1).collect();
+        let e = if t == 0 {
+            Expr::Const(false)
+        } else if t == 0xffff {
+            Expr::Const(true)
+        } else if let Some(&i) = support.iter().find(|&&i| t == VARS4[i]) {
+            Expr::Leaf(i)
+        } else if let Some(&i) = support.iter().find(|&&i| t == !VARS4[i]) {
+            Expr::Not(Box::new(Expr::Leaf(i)))
+        } else {
+            let mut candidates = Vec::new();
+            // t = g(a) op h(b) for a split of the inputs into a and b
+            for subset in 1..(1_usize << support.len()) - 1 {
+                let a: Vec<usize> = (0..support.len()).filter(|j| (subset >> j) & 0x1 == 1).map(|j| support[j]).collect();
+                // the cofactor of every assignment to a, each one a function of b
+                let mut rows: Vec<(usize, u16)> = Vec::new();
+                for assignment in 0..(1_usize << a.len()) {
+                    let mut row = t;
+                    for (j, &i) in a.iter().enumerate() {
+                        let (zero, one) = cofactors(row, i);
+                        row = if (assignment >> j) & 0x1 == 1 { one } else { zero };
+                    }
+                    rows.push((assignment, row));
+                }
+                let distinct: Vec<u16> = rows.iter().fold(Vec::new(), |mut d, &(_, r)| {
+                    if !d.contains(&r) {
+                        d.push(r);
+                    }
+                    d
+                });
+                if distinct.len() != 2 {
+                    continue;
+                }
+                // g is one for the assignments whose cofactor is `on`
+                let indicator = |on: u16| -> u16 {
+                    let mut g = 0;
+                    for &(assignment, row) in &rows {
+                        if row == on {
+                            let mut term = 0xffff;
+                            for (j, &i) in a.iter().enumerate() {
+                                term &= if (assignment >> j) & 0x1 == 1 { VARS4[i] } else { !VARS4[i] };
+                            }
+                            g |= term;
+                        }
+                    }
+                    g
+                };
+                let (r0, r1) = (distinct[0], distinct[1]);
+                for (h, other) in [(r0, r1), (r1, r0)] {
+                    if other == 0 {
+                        candidates.push((1, indicator(h), h));
+                    } else if other == 0xffff {
+                        candidates.push((2, indicator(other), h));
+                    }
+                }
+                if r1 == !r0 {
+                    candidates.push((3, indicator(r1), r0));
+                }
+            }
+            let mut best: Option<Expr> = None;
+            let mut consider = |e: Expr| {
+                if best.as_ref().is_none_or(|b| e.gates() < b.gates()) {
+                    best = Some(e);
+                }
+            };
+            for (op, g, h) in candidates {
+                let (g, h) = (Box::new(self.synthesize(g)), Box::new(self.synthesize(h)));
+                consider(normalize(&match op {
+                    1 => Expr::And(g, h),
+                    2 => Expr::Or(g, h),
+                    _ => Expr::Xor(g, h),
+                }));
+            }
+            for &i in &support {
+                let (zero, one) = cofactors(t, i);
+                let x = Box::new(Expr::Leaf(i));
+                let (f0, f1, d) = (self.synthesize(zero), self.synthesize(one), self.synthesize(zero ^ one));
+                // x ? f1 : f0 as or of two ands and as f0 ^ (x & (f0 ^ f1))
+                consider(normalize(&Expr::Or(
+                    Box::new(Expr::And(x.clone(), Box::new(f1))),
+                    Box::new(Expr::And(Box::new(Expr::Not(x.clone())), Box::new(f0.clone()))),
+                )));
+                consider(normalize(&Expr::Xor(Box::new(f0), Box::new(Expr::And(x, Box::new(d))))));
+            }
+            best.unwrap()
+        };
+        self.synthesized.insert(t, e.clone());
+        e
+    }
+}
+
+// the formula with the inverters moved towards the output, through a xor and with the laws
+// of de morgan if both inputs of an and or an or are inverted
+fn normalize(e: &Expr) -> Expr {
+    // the formula and whether its value is inverted
+    fn push(e: &Expr) -> (Expr, bool) {
+        let not = |e: Expr, inverted: bool| match inverted {
+            true => Box::new(Expr::Not(Box::new(e))),
+            false => Box::new(e),
+        };
+        match e {
+            Expr::Const(v) => (Expr::Const(*v), false),
+            Expr::Leaf(i) => (Expr::Leaf(*i), false),
+            Expr::Not(a) => {
+                let (a, inverted) = push(a);
+                (a, !inverted)
+            }
+            Expr::Xor(a, b) => {
+                let ((a, ia), (b, ib)) = (push(a), push(b));
+                (Expr::Xor(Box::new(a), Box::new(b)), ia ^ ib)
+            }
+            Expr::And(a, b) => match (push(a), push(b)) {
+                ((a, true), (b, true)) => (Expr::Or(Box::new(a), Box::new(b)), true),
+                ((a, ia), (b, ib)) => (Expr::And(not(a, ia), not(b, ib)), false),
+            },
+            Expr::Or(a, b) => match (push(a), push(b)) {
+                ((a, true), (b, true)) => (Expr::And(Box::new(a), Box::new(b)), true),
+                ((a, ia), (b, ib)) => (Expr::Or(not(a, ia), not(b, ib)), false),
+            },
+        }
+    }
+    match push(e) {
+        (e, true) => Expr::Not(Box::new(e)),
+        (e, false) => e,
+    }
+}
+
+// the formula with every input i replaced by leaves[i]
+fn substitute(e: &Expr, leaves: &[Expr]) -> Expr {
+    match e {
+        Expr::Const(v) => Expr::Const(*v),
+        Expr::Leaf(i) => leaves[*i].clone(),
+        Expr::Not(a) => match substitute(a, leaves) {
+            Expr::Not(inner) => *inner,
+            a => Expr::Not(Box::new(a)),
+        },
+        Expr::And(a, b) => Expr::And(Box::new(substitute(a, leaves)), Box::new(substitute(b, leaves))),
+        Expr::Or(a, b) => Expr::Or(Box::new(substitute(a, leaves)), Box::new(substitute(b, leaves))),
+        Expr::Xor(a, b) => Expr::Xor(Box::new(substitute(a, leaves)), Box::new(substitute(b, leaves))),
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use crate::data::{Bit, Circuit, Gate, Line, Register, Wire};
+
+// how the pipeline depth is chosen
+// Stages: the combinational depth is split evenly into the given number of stages
+// Levels: every stage holds at most the given number of gate levels
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Pipelining {
+    Stages(usize),
+    Levels(usize),
+}
+
+impl Circuit {
+    pub fn pipeline_by(&mut self, pipelining: Pipelining) -> usize {
+        match pipelining {
+            Pipelining::Stages(stages) => self.pipeline_stages(stages),
+            Pipelining::Levels(levels) => self.pipeline(levels),
+        }
+    }
+
+    // cuts the circuit into `stages` stages of equal depth, see pipeline
+    pub fn pipeline_stages(&mut self, stages: usize) -> usize {
+        let depth = self.wires.iter().map(|w| w.out.level).max().unwrap_or(0);
+        let levels_per_stage = depth.div_ceil(stages.max(1)).max(1);
+        self.pipeline(levels_per_stage)
+    }
+
+    // inserts register ranks at every `levels_per_stage` gate levels, a gate at level l
+    // belongs to stage (l - 1) / levels_per_stage, the inputs to stage 0,
+    // every gate input coming from an earlier stage is delayed by one register per stage
+    // boundary and all outputs are delayed to the last stage, so all of them emerge on the same
+    // cycle, adds the ports clk, rst, in_valid and out_valid, out_valid follows in_valid with
+    // the latency of the pipeline and is cleared by rst
+    // returns the latency in clock cycles
+    pub fn pipeline(&mut self, levels_per_stage: usize) -> usize {
+        if !self.registers.is_empty() {
+            panic!("circuit already contains registers!");
+        }
+        let levels_per_stage = levels_per_stage.max(1);
+        let stage_of = |level: usize| level.saturating_sub(1) / levels_per_stage;
+        let depth = self.wires.iter().map(|w| w.out.level).max().unwrap_or(0);
+        let latency = stage_of(depth);
+
+        // stage and pipelined version of every line driven by a gate, all other lines are
+        // inputs which live in stage 0 and keep level 0
+        let mut stages: HashMap<usize, usize> = HashMap::with_capacity(self.wires.len());
+        let mut lines: HashMap<usize, Line> = HashMap::with_capacity(self.wires.len());
+        // delayed copies of a line, delayed[&(n, k)] is line n delayed by k cycles
+        let mut delayed: HashMap<(usize, usize), Line> = HashMap::new();
+
+        let wires = std::mem::take(&mut self.wires);
+        for wire in &wires {
+            let stage = stage_of(wire.out.level);
+            let mut input = |circuit: &mut Circuit, l: Line| {
+                circuit.delayed_line(&lines, &stages, &mut delayed, l, stage)
+            };
+            let gate = match wire.gate {
+                Gate::Not(l) => Gate::Not(input(self, l)),
+                Gate::And(l1, l2) => Gate::And(input(self, l1), input(self, l2)),
+                Gate::Or(l1, l2) => Gate::Or(input(self, l1), input(self, l2)),
+                Gate::Xor(l1, l2) => Gate::Xor(input(self, l1), input(self, l2)),
+            };
+            let out = Line {
+                level: gate.get_next_level(),
+                n: wire.out.n,
+            };
+            self.wires.push(Wire::new(out, gate, wire.scope));
+            stages.insert(out.n, stage);
+            lines.insert(out.n, out);
+        }
+
+        let mut outputs = std::mem::take(&mut self.outputs);
+        for output in &mut outputs {
+            for bit in &mut output.bits {
+                if let Bit::Var(l) = *bit {
+                    *bit = Bit::Var(self.delayed_line(&lines, &stages, &mut delayed, l, latency));
+                }
+            }
+        }
+        self.outputs = outputs;
+
+        self.rename_outputs();
+
+        self.add_clock_and_reset();
+        let in_valid = self.new_line();
+        self.add_as_io(&vec![in_valid], "in_valid", false);
+
+        let mut valid = in_valid;
+        for _ in 0..latency {
+            let q = self.new_register_lines(1);
+            self.add_registers(&q, &[valid], true);
+            valid = q[0];
+        }
+        self.add_as_io(&vec![valid], "out_valid", true);
+
+        self.update_stats();
+        latency
+    }
+
+    // the pipelined version of line l as seen by a gate in stage `stage`
+    fn delayed_line(
+        &mut self,
+        lines: &HashMap<usize, Line>,
+        stages: &HashMap<usize, usize>,
+        delayed: &mut HashMap<(usize, usize), Line>,
+        l: Line,
+        stage: usize,
+    ) -> Line {
+        let from = stages.get(&l.n).copied().unwrap_or(0);
+        let mut line = lines.get(&l.n).copied().unwrap_or(Line { level: 0, n: l.n });
+        for k in 1..=stage.saturating_sub(from) {
+            line = match delayed.get(&(l.n, k)) {
+                Some(&q) => q,
+                None => {
+                    let q = self.new_line_constant_value();
+                    self.registers.push(Register {
+                        q,
+                        d: Bit::Var(line),
+                        reset: false,
+                    });
+                    delayed.insert((l.n, k), q);
+                    q
+                }
+            };
+        }
+        line
+    }
+}
@@ -31,7 +31,7 @@ impl Circuit {
                         level: gate.get_next_level(),
                         n: self.stats.add_line(),
                     };
-                    self.wires.push(Wire::new(line, gate));
+                    self.wires.push(Wire::new(line, gate, self.scope));
                     bit = Var(line);
                 }
                 else {
@@ -51,7 +51,7 @@ impl Circuit {
                     level: gate.get_next_level(),
                     n: self.stats.add_line(),
                 };
-                self.wires.push(Wire::new(line, gate));
+                self.wires.push(Wire::new(line, gate, self.scope));
                 bit = Var(line);
             }
             (One, Var(j2)) => {
@@ -61,7 +61,7 @@ impl Circuit {
                     level: gate.get_next_level(),
                     n: self.stats.add_line(),
                 };
-                self.wires.push(Wire::new(line, gate));
+                self.wires.push(Wire::new(line, gate, self.scope));
                 bit = Var(line);
             }
             (Zero, Zero) => {
@@ -92,7 +92,7 @@ impl Circuit {
                         level: gate.get_next_level(),
                         n: self.stats.add_line(),
                     };
-                    self.wires.push(Wire::new(line, gate));
+                    self.wires.push(Wire::new(line, gate, self.scope));
                     bit = Var(line);
                 } else {
                     bit = i1;
@@ -135,7 +135,7 @@ impl Circuit {
                         level: gate.get_next_level(),
                         n: self.stats.add_line(),
                     };
-                    self.wires.push(Wire::new(line, gate));
+                    self.wires.push(Wire::new(line, gate, self.scope));
                     bit = Var(line);
                 } else {
                     bit = i1;
@@ -177,7 +177,7 @@ impl Circuit {
                     level: gate.get_next_level(),
                     n: self.stats.add_line(),
                 };
-                self.wires.push(Wire::new(line, gate));
+                self.wires.push(Wire::new(line, gate, self.scope));
                 bit = Var(line);
             }
             One => {
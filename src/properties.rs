@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+
+use crate::data::{Bit, Circuit, Gate, Line, IO};
+use crate::equivalence::{input_comments, Ports};
+use crate::sat::{Cnf, Lit};
+
+// the property every division circuit has to fulfill for all inputs
+const DIVIDER_PROPERTY: &str = "Valid implies Q * D + R_n1 == R_0 and R_n1 < D";
+
+// a combinational divider together with the negation of its property, satisfiable if and
+// only if the divider is wrong for some inputs
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DividerProperty {
+    pub cnf: Cnf,
+    pub inputs: Ports,
+}
+
+impl DividerProperty {
+    // the formula in the dimacs format, the comments name the variables of the inputs so a
+    // model is a counterexample that can be read back
+    pub fn dimacs(&self) -> String {
+        let mut comments = vec![format!("unsatisfiable if and only if {DIVIDER_PROPERTY}")];
+        comments.extend(input_comments(&self.inputs));
+        self.cnf.dimacs(&comments)
+    }
+}
+
+// gates folding constant and equal inputs, so zero extended words add no variables
+fn and(cnf: &mut Cnf, a: Lit, b: Lit) -> Lit {
+    let (zero, one) = (Cnf::constant(false), Cnf::constant(true));
+    if a == zero || b == zero || a == !b {
+        zero
+    } else if a == one || a == b {
+        b
+    } else if b == one {
+        a
+    } else {
+        cnf.and(a, b)
+    }
+}
+
+fn or(cnf: &mut Cnf, a: Lit, b: Lit) -> Lit {
+    !and(cnf, !a, !b)
+}
+
+fn xor(cnf: &mut Cnf, a: Lit, b: Lit) -> Lit {
+    let one = Cnf::constant(true);
+    if a == b || a == !b {
+        Cnf::constant(a != b)
+    } else if a.var() == 0 {
+        b.xor(a == one)
+    } else if b.var() == 0 {
+        a.xor(b == one)
+    } else {
+        cnf.xor(a, b)
+    }
+}
+
+// the word zero extended to width bits
+fn extend(word: &[Lit], width: usize) -> Vec<Lit> {
+    (0..width).map(|i| word.get(i).copied().unwrap_or(Cnf::constant(false))).collect()
+}
+
+// ripple carry sum of two words of the same width without the carry out
+fn add(cnf: &mut Cnf, a: &[Lit], b: &[Lit]) -> Vec<Lit> {
+    let mut carry = Cnf::constant(false);
+    let mut sum = Vec::with_capacity(a.len());
+    for (&a, &b) in a.iter().zip(b) {
+        let half = xor(cnf, a, b);
+        sum.push(xor(cnf, half, carry));
+        let (generate, propagate) = (and(cnf, a, b), and(cnf, half, carry));
+        carry = or(cnf, generate, propagate);
+    }
+    sum
+}
+
+// shift and add product of two words of the same width, truncated to that width
+fn mul(cnf: &mut Cnf, a: &[Lit], b: &[Lit]) -> Vec<Lit> {
+    let mut product = extend(&[], a.len());
+    for (shift, &b) in b.iter().enumerate() {
+        let mut partial = extend(&[], shift);
+        for &a in &a[..a.len() - shift] {
+            partial.push(and(cnf, a, b));
+        }
+        product = add(cnf, &product, &partial);
+    }
+    product
+}
+
+// true if a is less than b, the borrow out of a - b
+fn less(cnf: &mut Cnf, a: &[Lit], b: &[Lit]) -> Lit {
+    let mut borrow = Cnf::constant(false);
+    for (&a, &b) in a.iter().zip(b) {
+        let same = !xor(cnf, a, b);
+        let (smaller, kept) = (and(cnf, !a, b), and(cnf, same, borrow));
+        borrow = or(cnf, smaller, kept);
+    }
+    borrow
+}
+
+fn equal(cnf: &mut Cnf, a: &[Lit], b: &[Lit]) -> Lit {
+    let mut differences = Vec::new();
+    for (&a, &b) in a.iter().zip(b) {
+        let difference = xor(cnf, a, b);
+        if difference == Cnf::constant(true) {
+            return Cnf::constant(false);
+        } else if difference != Cnf::constant(false) {
+            differences.push(difference);
+        }
+    }
+    !cnf.any(&differences)
+}
+
+fn port<'a, T>(ports: &'a [(String, Vec<T>)], name: &str) -> &'a [T] {
+    match ports.iter().find(|(n, _)| n == name) {
+        Some((_, bits)) => bits,
+        None => panic!("divider has no port {name}"),
+    }
+}
+
+fn port_width(ios: &[IO], name: &str) -> usize {
+    match ios.iter().find(|io| io.name == name) {
+        Some(io) => io.bits.len(),
+        None => panic!("divider has no port {name}"),
+    }
+}
+
+impl Circuit {
+    // the width needed to compute Q * D + R_n1 without an overflow
+    fn property_width(&self) -> usize {
+        let (r_0, d) = (port_width(&self.inputs, "R_0"), port_width(&self.inputs, "D"));
+        let (q, r_n1) = (port_width(&self.outputs, "Q"), port_width(&self.outputs, "R_n1"));
+        port_width(&self.outputs, "Valid");
+        (q + d).max(r_0).max(r_n1) + 1
+    }
+
+    // tseitin encoding of the divider and the negation of its property
+    pub fn divider_property(&self) -> DividerProperty {
+        let width = self.property_width();
+        let mut cnf = Cnf::new();
+        let (inputs, outputs) = self.encode(&mut cnf);
+        let (r_0, d) = (extend(port(&inputs, "R_0"), width), extend(port(&inputs, "D"), width));
+        let (q, r_n1) = (extend(port(&outputs, "Q"), width), extend(port(&outputs, "R_n1"), width));
+        let valid = port(&outputs, "Valid")[0];
+        let product = mul(&mut cnf, &q, &d);
+        let sum = add(&mut cnf, &product, &r_n1);
+        let restored = equal(&mut cnf, &sum, &r_0);
+        let smaller = less(&mut cnf, &r_n1, &d);
+        let holds = and(&mut cnf, restored, smaller);
+        cnf.add_clause(&[valid]);
+        cnf.add_clause(&[!holds]);
+        DividerProperty { cnf, inputs }
+    }
+
+    // the divider and the negation of its property as smt-lib2 formula over bit vectors for
+    // solvers like bitwuzla or z3, unsat if and only if the divider is correct, every line is
+    // a boolean constant named by its index
+    pub fn divider_property_smt2(&self) -> String {
+        if !self.registers.is_empty() {
+            panic!("only combinational circuits can be encoded, this one has {} registers", self.registers.len());
+        }
+        let width = self.property_width();
+        let mut s = format!("; unsat if and only if {DIVIDER_PROPERTY}\n(set-logic QF_BV)\n");
+        let mut defined = HashSet::new();
+        let name = |l: Line| match (self.zero_wire, self.one_wire) {
+            (Some(zero), _) if zero.n == l.n => "false".to_string(),
+            (_, Some(one)) if one.n == l.n => "true".to_string(),
+            _ => format!("l{}", l.n),
+        };
+        for input in &self.inputs {
+            s.push_str(&format!("(declare-const {} (_ BitVec {}))\n", input.name, input.bits.len()));
+            for (i, bit) in input.bits.iter().enumerate() {
+                if let Bit::Var(l) = bit {
+                    s.push_str(&format!("(define-fun {} () Bool (= ((_ extract {i} {i}) {}) #b1))\n", name(*l), input.name));
+                    defined.insert(l.n);
+                }
+            }
+        }
+        for wire in &self.wires {
+            for l in wire.gate.inputs() {
+                if defined.insert(l.n) && name(l).starts_with('l') {
+                    s.push_str(&format!("(declare-const {} Bool)\n", name(l)));
+                }
+            }
+            let term = match wire.gate {
+                Gate::Not(l) => format!("(not {})", name(l)),
+                Gate::And(l1, l2) => format!("(and {} {})", name(l1), name(l2)),
+                Gate::Or(l1, l2) => format!("(or {} {})", name(l1), name(l2)),
+                Gate::Xor(l1, l2) => format!("(xor {} {})", name(l1), name(l2)),
+            };
+            s.push_str(&format!("(define-fun {} () Bool {term})\n", name(wire.out)));
+            defined.insert(wire.out.n);
+        }
+        for output in &self.outputs {
+            let bits: Vec<String> = output
+                .bits
+                .iter()
+                .rev()
+                .map(|&bit| match bit {
+                    Bit::Zero => "#b0".to_string(),
+                    Bit::One => "#b1".to_string(),
+                    Bit::Var(l) => format!("(ite {} #b1 #b0)", name(l)),
+                })
+                .collect();
+            let word = match bits.len() {
+                1 => bits[0].clone(),
+                _ => format!("(concat {})", bits.join(" ")),
+            };
+            s.push_str(&format!("(define-fun {} () (_ BitVec {}) {word})\n", output.name, bits.len()));
+        }
+        let extend = |name: &str, ios: &[IO]| format!("((_ zero_extend {}) {name})", width - port_width(ios, name));
+        let (r_0, d) = (extend("R_0", &self.inputs), extend("D", &self.inputs));
+        let (q, r_n1) = (extend("Q", &self.outputs), extend("R_n1", &self.outputs));
+        s.push_str("(assert (= ((_ extract 0 0) Valid) #b1))\n");
+        s.push_str(&format!("(assert (not (and (= (bvadd (bvmul {q} {d}) {r_n1}) {r_0}) (bvult {r_n1} {d}))))\n"));
+        s.push_str("(check-sat)\n");
+        s
+    }
+}
@@ -0,0 +1,60 @@
+use crate::data::{Adder, Bit, Bit::Zero, Circuit};
+use crate::dividers::DivInfo;
+
+impl Circuit {
+    // a + b modulo 2^k - 1 for k bit residues, the carry out of the sum is added back in, the
+    // result stays below 2^k as a + b is at most 2^(k + 1) - 2
+    fn residue_add(&mut self, a: Vec<Bit>, b: Vec<Bit>, adder: Adder) -> Vec<Bit> {
+        let k = a.len().max(b.len());
+        let mut sum = adder.add(self, a, b, Zero);
+        let carry = sum.pop().unwrap();
+        let mut folded = adder.add(self, sum, vec![carry], Zero);
+        folded.truncate(k);
+        folded
+    }
+
+    // the number modulo 2^k - 1 as k bits, the sum of its k bit digits as 2^k is 1 modulo
+    // 2^k - 1, zero has the two representations 0 and 2^k - 1
+    pub fn residue(&mut self, bits: &[Bit], k: usize, adder: Adder) -> Vec<Bit> {
+        let mut digits: Vec<Vec<Bit>> = bits.chunks(k).map(|digit| digit.to_vec()).collect();
+        let mut residue = digits.pop().unwrap_or_default();
+        residue.resize(k, Zero);
+        while let Some(digit) = digits.pop() {
+            residue = self.residue_add(residue, digit, adder);
+        }
+        residue
+    }
+
+    // the residue with 2^k - 1 replaced by 0, so equal residues have equal bits
+    fn canonical_residue(&mut self, residue: Vec<Bit>) -> Vec<Bit> {
+        let inverted: Vec<Bit> = residue.iter().map(|&bit| self.not(bit)).collect();
+        let any_zero = self.or_of_all(inverted);
+        residue.into_iter().map(|bit| self.and(bit, any_zero)).collect()
+    }
+
+    // concurrent error detection of a divider, high if q * d + r differs from the dividend
+    // modulo 2^k - 1 or r is not smaller than d, the product of the residues stands in for the
+    // residue of the product, a fault shows unless it changes the result by a multiple of
+    // 2^k - 1 and keeps the remainder in range
+    pub fn residue_check(&mut self, dividend: &[Bit], divisor: &[Bit], q: &[Bit], r: &[Bit], k: usize, info: &DivInfo) -> Bit {
+        let adder = info.defaultadder;
+        if k < 2 {
+            panic!("residue checks need a modulus of 2^k - 1 with k >= 2, not k = {k}");
+        }
+        let residue_q = self.residue(q, k, adder);
+        let residue_d = self.residue(divisor, k, adder);
+        let product = info.defaultmult.mul_u(self, residue_q, residue_d, None, adder);
+        let residue_product = self.residue(&product, k, adder);
+        let residue_r = self.residue(r, k, adder);
+        let computed = self.residue_add(residue_product, residue_r, adder);
+        let computed = self.canonical_residue(computed);
+        let expected = self.residue(dividend, k, adder);
+        let expected = self.canonical_residue(expected);
+        let differences: Vec<Bit> = computed.iter().zip(&expected).map(|(&a, &b)| self.xor(a, b)).collect();
+        let mismatch = self.or_of_all(differences);
+        // the borrow of r - d is set if r < d
+        let in_range = adder.sub(self, r.to_vec(), divisor.to_vec(), Zero).pop().unwrap();
+        let out_of_range = self.not(in_range);
+        self.or(mismatch, out_of_range)
+    }
+}
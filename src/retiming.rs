@@ -0,0 +1,300 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::cost::GateCosts;
+use crate::data::{Bit, Circuit, Gate, Line, Register, Wire};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RetimingReport {
+    pub period_before: usize,
+    pub period_after: usize,
+    pub registers_before: usize,
+    pub registers_after: usize,
+}
+
+impl std::fmt::Display for RetimingReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "clock period: {} -> {}, register count: {} -> {}",
+            self.period_before, self.period_after, self.registers_before, self.registers_after
+        )
+    }
+}
+
+// vertex for all inputs of the circuit (and the outputs of registers that are not moved)
+const SOURCE: usize = 0;
+// vertex for all outputs of the circuit (and the inputs of registers that are not moved)
+const SINK: usize = 1;
+
+struct Edge {
+    from: usize,
+    to: usize,
+    registers: i64,
+}
+
+// the graph of leiserson and saxe, wire i is the vertex i + 2 and every gate input or output bit
+// is an edge from the gate driving it, the registers in between are the weight of the edge
+struct RetimingGraph {
+    delays: Vec<usize>,
+    edges: Vec<Edge>,
+    out_edges: Vec<Vec<usize>>,
+}
+
+impl RetimingGraph {
+    fn weight(&self, e: &Edge, r: &[i64]) -> i64 {
+        e.registers + r[e.to] - r[e.from]
+    }
+
+    // arrival times of all vertices in the retimed graph, only edges without registers propagate
+    fn arrival(&self, r: &[i64]) -> Vec<usize> {
+        let mut indegree = vec![0; self.delays.len()];
+        for e in &self.edges {
+            if e.from != SOURCE && self.weight(e, r) == 0 {
+                indegree[e.to] += 1;
+            }
+        }
+        let mut arrival = self.delays.clone();
+        let mut queue: VecDeque<usize> = (SINK + 1..self.delays.len())
+            .filter(|&v| indegree[v] == 0)
+            .collect();
+        while let Some(u) = queue.pop_front() {
+            for &idx in &self.out_edges[u] {
+                let e = &self.edges[idx];
+                if self.weight(e, r) != 0 {
+                    continue;
+                }
+                arrival[e.to] = arrival[e.to].max(arrival[u] + self.delays[e.to]);
+                indegree[e.to] -= 1;
+                if indegree[e.to] == 0 {
+                    queue.push_back(e.to);
+                }
+            }
+        }
+        arrival
+    }
+
+    // FEAS: every gate whose arrival time exceeds the period gets one more register moved from
+    // its outputs to its inputs, the retiming found like this is the smallest one for the period
+    // so an edge without registers left proves that the period is not reachable
+    // inputs and outputs are fixed which keeps the latency of the circuit
+    fn feasible(&self, period: usize) -> Option<Vec<i64>> {
+        let mut r = vec![0; self.delays.len()];
+        for _ in 0..self.delays.len() {
+            let arrival = self.arrival(&r);
+            let mut changed = false;
+            for v in (SINK + 1)..self.delays.len() {
+                if arrival[v] > period {
+                    r[v] += 1;
+                    changed = true;
+                }
+            }
+            if !changed {
+                return Some(r);
+            }
+            if self.edges.iter().any(|e| self.weight(e, &r) < 0) {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+impl Circuit {
+    // longest delay between inputs, outputs and registers
+    pub fn clock_period(&self, costs: &GateCosts) -> usize {
+        self.arrival_times(costs).into_values().max().unwrap_or(0)
+    }
+
+    // moves registers across gates (leiserson and saxe) so that the clock period becomes minimal,
+    // registers with a reset or a constant input stay where they are and count as inputs and
+    // outputs, registers on different fanouts of a line are shared, the delays of the gates
+    // include the fanout they have before retiming
+    pub fn retime(&mut self, costs: &GateCosts) -> RetimingReport {
+        let period_before = self.clock_period(costs);
+        let gate_delays = self.gate_delays(costs);
+        let registers_before = self.registers.len();
+
+        let (movable, fixed): (Vec<Register>, Vec<Register>) = std::mem::take(&mut self.registers)
+            .into_iter()
+            .partition(|r| !r.reset && matches!(r.d, Bit::Var(_)));
+        let movable: HashMap<usize, Line> = movable
+            .iter()
+            .filter_map(|r| match r.d {
+                Bit::Var(d) => Some((r.q.n, d)),
+                _ => None,
+            })
+            .collect();
+        let vertex_of: HashMap<usize, usize> = self
+            .wires
+            .iter()
+            .enumerate()
+            .map(|(idx, w)| (w.out.n, idx + 2))
+            .collect();
+        // follows a line back through movable registers to the line of the gate or input driving it
+        let driver = |mut n: usize| -> (usize, usize, i64) {
+            let mut registers = 0;
+            while let Some(d) = movable.get(&n) {
+                n = d.n;
+                registers += 1;
+                if registers > movable.len() as i64 {
+                    panic!("registers form a loop without any gate!");
+                }
+            }
+            (n, vertex_of.get(&n).copied().unwrap_or(SOURCE), registers)
+        };
+
+        let mut graph = RetimingGraph {
+            delays: vec![0; self.wires.len() + 2],
+            edges: vec![],
+            out_edges: vec![vec![]; self.wires.len() + 2],
+        };
+        let add_edge = |graph: &mut RetimingGraph, n: usize, to: usize| {
+            let (_, from, registers) = driver(n);
+            graph.out_edges[from].push(graph.edges.len());
+            graph.edges.push(Edge { from, to, registers });
+        };
+        for (idx, wire) in self.wires.iter().enumerate() {
+            graph.delays[idx + 2] = gate_delays[idx];
+            for l in wire.gate.inputs() {
+                add_edge(&mut graph, l.n, idx + 2);
+            }
+        }
+        let sinks: Vec<usize> = self
+            .outputs
+            .iter()
+            .flat_map(|o| o.bits.iter())
+            .chain(fixed.iter().map(|r| &r.d))
+            .filter_map(|bit| match bit {
+                Bit::Var(l) => Some(l.n),
+                _ => None,
+            })
+            .collect();
+        for n in sinks {
+            add_edge(&mut graph, n, SINK);
+        }
+
+        // binary search for the smallest period FEAS can reach
+        let mut low = graph.delays.iter().copied().max().unwrap_or(0).max(1);
+        let mut high = period_before.max(low);
+        let mut r = vec![0; graph.delays.len()];
+        while low < high {
+            let period = (low + high) / 2;
+            match graph.feasible(period) {
+                Some(retiming) => {
+                    r = retiming;
+                    high = period;
+                }
+                None => low = period + 1,
+            }
+        }
+
+        // rebuild the circuit with shared register chains behind every line
+        let mut delayed: HashMap<(usize, i64), Line> = HashMap::new();
+        let mut registers = vec![];
+        let mut tap = |circuit: &mut Circuit, n: usize, to_r: i64| -> Line {
+            let (origin, from, w) = driver(n);
+            // the levels get fixed once the gates are sorted again
+            let mut line = Line { level: 0, n: origin };
+            for k in 1..=(w + to_r - r[from]) {
+                line = *delayed.entry((origin, k)).or_insert_with(|| {
+                    let q = circuit.new_line_constant_value();
+                    registers.push(Register { q, d: Bit::Var(line), reset: false });
+                    q
+                });
+            }
+            line
+        };
+        let wires = std::mem::take(&mut self.wires);
+        let mut gates = Vec::with_capacity(wires.len());
+        for (idx, wire) in wires.iter().enumerate() {
+            let to_r = r[idx + 2];
+            let gate = match wire.gate {
+                Gate::Not(l) => Gate::Not(tap(self, l.n, to_r)),
+                Gate::And(l1, l2) => Gate::And(tap(self, l1.n, to_r), tap(self, l2.n, to_r)),
+                Gate::Or(l1, l2) => Gate::Or(tap(self, l1.n, to_r), tap(self, l2.n, to_r)),
+                Gate::Xor(l1, l2) => Gate::Xor(tap(self, l1.n, to_r), tap(self, l2.n, to_r)),
+            };
+            gates.push(Wire::new(wire.out, gate, wire.scope));
+        }
+        let mut outputs = std::mem::take(&mut self.outputs);
+        for bit in outputs.iter_mut().flat_map(|o| o.bits.iter_mut()) {
+            if let Bit::Var(l) = *bit {
+                *bit = Bit::Var(tap(self, l.n, 0));
+            }
+        }
+        let mut fixed = fixed;
+        for register in &mut fixed {
+            if let Bit::Var(l) = register.d {
+                register.d = Bit::Var(tap(self, l.n, 0));
+            }
+        }
+
+        // registers may have moved in front of gates that come earlier in the old order
+        let lines = self.sort_gates(gates);
+        let relevel = |bit: &mut Bit| {
+            if let Bit::Var(l) = *bit {
+                *bit = Bit::Var(lines.get(&l.n).copied().unwrap_or(Line { level: 0, n: l.n }));
+            }
+        };
+        for register in registers.iter_mut().chain(fixed.iter_mut()) {
+            relevel(&mut register.d);
+        }
+        for bit in outputs.iter_mut().flat_map(|o| o.bits.iter_mut()) {
+            relevel(bit);
+        }
+        self.outputs = outputs;
+        self.registers = fixed;
+        self.registers.append(&mut registers);
+        self.rename_outputs();
+        self.update_stats();
+
+        RetimingReport {
+            period_before,
+            period_after: self.clock_period(costs),
+            registers_before,
+            registers_after: self.registers.len(),
+        }
+    }
+
+    // puts the gates into topological order with correct levels and returns the new line of every gate
+    fn sort_gates(&mut self, gates: Vec<Wire>) -> HashMap<usize, Line> {
+        let index: HashMap<usize, usize> = gates.iter().enumerate().map(|(idx, w)| (w.out.n, idx)).collect();
+        let mut indegree = vec![0; gates.len()];
+        let mut fanout: Vec<Vec<usize>> = vec![vec![]; gates.len()];
+        for (idx, wire) in gates.iter().enumerate() {
+            let inputs: HashSet<usize> = wire.gate.inputs().iter().map(|l| l.n).collect();
+            for n in inputs {
+                if let Some(&from) = index.get(&n) {
+                    indegree[idx] += 1;
+                    fanout[from].push(idx);
+                }
+            }
+        }
+
+        let mut queue: VecDeque<usize> = (0..gates.len()).filter(|&idx| indegree[idx] == 0).collect();
+        let mut lines: HashMap<usize, Line> = HashMap::with_capacity(gates.len());
+        while let Some(idx) = queue.pop_front() {
+            let wire = gates[idx];
+            let line = |l: Line| lines.get(&l.n).copied().unwrap_or(l);
+            let gate = match wire.gate {
+                Gate::Not(l) => Gate::Not(line(l)),
+                Gate::And(l1, l2) => Gate::And(line(l1), line(l2)),
+                Gate::Or(l1, l2) => Gate::Or(line(l1), line(l2)),
+                Gate::Xor(l1, l2) => Gate::Xor(line(l1), line(l2)),
+            };
+            let out = Line { level: gate.get_next_level(), n: wire.out.n };
+            self.wires.push(Wire::new(out, gate, wire.scope));
+            lines.insert(out.n, out);
+            for &next in &fanout[idx] {
+                indegree[next] -= 1;
+                if indegree[next] == 0 {
+                    queue.push_back(next);
+                }
+            }
+        }
+        if self.wires.len() != gates.len() {
+            panic!("retiming left a loop without registers!");
+        }
+        lines
+    }
+}
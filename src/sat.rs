@@ -0,0 +1,724 @@
+use std::ops::Not;
+
+// literal of variable var, negated if the lowest bit is set
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub struct Lit(u32);
+
+impl Lit {
+    pub fn new(var: usize, negated: bool) -> Self {
+        Lit((var as u32) << 1 | negated as u32)
+    }
+
+    pub fn var(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    pub fn is_negated(self) -> bool {
+        self.0 & 0x1 == 1
+    }
+
+    // the literal negated if negate is set
+    pub fn xor(self, negate: bool) -> Lit {
+        Lit(self.0 ^ negate as u32)
+    }
+
+    pub fn to_dimacs(self) -> i64 {
+        match self.is_negated() {
+            true => -(self.var() as i64 + 1),
+            false => self.var() as i64 + 1,
+        }
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 0x1)
+    }
+}
+
+// a formula in conjunctive normal form, variable 0 is always true so constants are literals too
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Cnf {
+    pub vars: usize,
+    pub clauses: Vec<Vec<Lit>>,
+}
+
+impl Cnf {
+    pub fn new() -> Self {
+        Cnf {
+            vars: 1,
+            clauses: vec![vec![Lit::new(0, false)]],
+        }
+    }
+
+    pub fn constant(value: bool) -> Lit {
+        Lit::new(0, !value)
+    }
+
+    pub fn new_var(&mut self) -> Lit {
+        self.vars += 1;
+        Lit::new(self.vars - 1, false)
+    }
+
+    pub fn add_clause(&mut self, clause: &[Lit]) {
+        self.clauses.push(clause.to_vec());
+    }
+
+    // tseitin encoding of gates, the returned literal is equivalent to the gate output
+    pub fn and(&mut self, a: Lit, b: Lit) -> Lit {
+        let r = self.new_var();
+        self.add_clause(&[!r, a]);
+        self.add_clause(&[!r, b]);
+        self.add_clause(&[r, !a, !b]);
+        r
+    }
+
+    pub fn xor(&mut self, a: Lit, b: Lit) -> Lit {
+        let r = self.new_var();
+        self.add_clause(&[!r, a, b]);
+        self.add_clause(&[!r, !a, !b]);
+        self.add_clause(&[r, !a, b]);
+        self.add_clause(&[r, a, !b]);
+        r
+    }
+
+    // a literal that is true if any of lits is
+    pub fn any(&mut self, lits: &[Lit]) -> Lit {
+        let r = self.new_var();
+        let mut clause = vec![!r];
+        clause.extend_from_slice(lits);
+        self.add_clause(&clause);
+        for &l in lits {
+            self.add_clause(&[r, !l]);
+        }
+        r
+    }
+
+    // the formula in the dimacs format read by minisat, kissat and most other solvers,
+    // comments are written in front of the header
+    pub fn dimacs(&self, comments: &[String]) -> String {
+        let mut s = String::new();
+        for comment in comments {
+            s.push_str(&format!("c {comment}\n"));
+        }
+        s.push_str(&format!("p cnf {} {}\n", self.vars, self.clauses.len()));
+        for clause in &self.clauses {
+            for l in clause {
+                s.push_str(&format!("{} ", l.to_dimacs()));
+            }
+            s.push_str("0\n");
+        }
+        s
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SatResult {
+    Sat,
+    Unsat,
+    // the conflict limit was reached
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+struct Clause {
+    lits: Vec<Lit>,
+    learnt: bool,
+    lbd: usize,
+    deleted: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Watcher {
+    clause: usize,
+    // a literal of the clause, the clause is satisfied if it is true
+    blocker: Lit,
+}
+
+const UNDEF: u8 = 2;
+
+// conflict driven clause learning solver with two watched literals, first uip learning,
+// vsids decisions, phase saving, luby restarts and deletion of learnt clauses by their
+// literal block distance, clauses can be added between calls of solve, which takes
+// assumptions, so one solver can answer many related queries
+pub struct Solver {
+    clauses: Vec<Clause>,
+    // watches[l] lists the clauses watching !l, they are visited when l becomes true
+    watches: Vec<Vec<Watcher>>,
+    assigns: Vec<u8>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+    trail: Vec<Lit>,
+    trail_lim: Vec<usize>,
+    qhead: usize,
+    activity: Vec<f64>,
+    var_inc: f64,
+    heap: VarHeap,
+    phase: Vec<bool>,
+    // if restricted only variables marked with the current generation are decided on
+    restricted: bool,
+    decision: Vec<usize>,
+    generation: usize,
+    seen: Vec<bool>,
+    model: Vec<bool>,
+    learnts: usize,
+    max_learnts: usize,
+    // false once the clauses are unsatisfiable without any assumption
+    ok: bool,
+    pub conflicts: usize,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Solver {
+            clauses: Vec::new(),
+            watches: Vec::new(),
+            assigns: Vec::new(),
+            levels: Vec::new(),
+            reasons: Vec::new(),
+            trail: Vec::new(),
+            trail_lim: Vec::new(),
+            qhead: 0,
+            activity: Vec::new(),
+            var_inc: 1.0,
+            heap: VarHeap::default(),
+            phase: Vec::new(),
+            restricted: false,
+            decision: Vec::new(),
+            generation: 0,
+            seen: Vec::new(),
+            model: Vec::new(),
+            learnts: 0,
+            max_learnts: 4000,
+            ok: true,
+            conflicts: 0,
+        }
+    }
+
+    // makes sure variables 0..vars exist
+    pub fn reserve(&mut self, vars: usize) {
+        while self.assigns.len() < vars {
+            let var = self.assigns.len();
+            self.assigns.push(UNDEF);
+            self.levels.push(0);
+            self.reasons.push(None);
+            self.activity.push(0.0);
+            self.phase.push(false);
+            self.decision.push(0);
+            self.seen.push(false);
+            self.watches.push(Vec::new());
+            self.watches.push(Vec::new());
+            if !self.restricted {
+                self.heap.insert(var, &self.activity);
+            }
+        }
+    }
+
+    fn is_decision(&self, var: usize) -> bool {
+        !self.restricted || self.decision[var] == self.generation
+    }
+
+    // only vars are decided on until the next call, the others are assigned by propagation
+    // or not at all, so a satisfying assignment is only complete if the other variables
+    // are determined by them, like the gates outside the fanin cone of the decided gates,
+    // the activities are reset so the first decisions are made in the order of vars
+    pub fn restrict_decisions(&mut self, vars: &[usize]) {
+        self.restricted = true;
+        self.generation += 1;
+        self.heap.clear();
+        for (idx, &var) in vars.iter().enumerate() {
+            self.activity[var] = self.var_inc * (vars.len() - idx) as f64 / vars.len() as f64;
+            self.decision[var] = self.generation;
+            if self.assigns[var] == UNDEF && !self.heap.contains(var) {
+                self.heap.insert(var, &self.activity);
+            }
+        }
+    }
+
+    // every variable is decided on again
+    pub fn unrestrict_decisions(&mut self) {
+        self.restricted = false;
+        for var in 0..self.assigns.len() {
+            if self.assigns[var] == UNDEF && !self.heap.contains(var) {
+                self.heap.insert(var, &self.activity);
+            }
+        }
+    }
+
+    fn value(&self, l: Lit) -> u8 {
+        match self.assigns[l.var()] {
+            UNDEF => UNDEF,
+            v => v ^ l.is_negated() as u8,
+        }
+    }
+
+    fn level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    // the value of var in the last satisfying assignment, unassigned variables are false
+    pub fn model_value(&self, var: usize) -> bool {
+        self.model[var]
+    }
+
+    pub fn add_clause(&mut self, clause: &[Lit]) {
+        if !self.ok {
+            return;
+        }
+        self.reserve(clause.iter().map(|l| l.var() + 1).max().unwrap_or(0));
+        let mut lits = clause.to_vec();
+        lits.sort_unstable();
+        lits.dedup();
+        if lits.windows(2).any(|w| w[0] == !w[1]) || lits.iter().any(|&l| self.value(l) == 1) {
+            return;
+        }
+        lits.retain(|&l| self.value(l) == UNDEF);
+        match lits.len() {
+            0 => self.ok = false,
+            1 => {
+                self.enqueue(lits[0], None);
+                self.ok = self.propagate().is_none();
+            }
+            _ => {
+                self.attach(Clause {
+                    lits,
+                    learnt: false,
+                    lbd: 0,
+                    deleted: false,
+                });
+            }
+        }
+    }
+
+    fn attach(&mut self, clause: Clause) -> usize {
+        let idx = self.clauses.len();
+        self.watches[(!clause.lits[0]).index()].push(Watcher {
+            clause: idx,
+            blocker: clause.lits[1],
+        });
+        self.watches[(!clause.lits[1]).index()].push(Watcher {
+            clause: idx,
+            blocker: clause.lits[0],
+        });
+        self.clauses.push(clause);
+        idx
+    }
+
+    fn enqueue(&mut self, l: Lit, reason: Option<usize>) {
+        self.assigns[l.var()] = !l.is_negated() as u8;
+        self.levels[l.var()] = self.level();
+        self.reasons[l.var()] = reason;
+        self.trail.push(l);
+    }
+
+    // unit propagation of the trail, returns a clause with only false literals
+    fn propagate(&mut self) -> Option<usize> {
+        while self.qhead < self.trail.len() {
+            let p = self.trail[self.qhead];
+            self.qhead += 1;
+            let false_lit = !p;
+            let mut watchers = std::mem::take(&mut self.watches[p.index()]);
+            let mut kept = 0;
+            let mut conflict = None;
+            let mut i = 0;
+            while i < watchers.len() {
+                let w = watchers[i];
+                i += 1;
+                if self.clauses[w.clause].deleted {
+                    continue;
+                }
+                if self.value(w.blocker) == 1 {
+                    watchers[kept] = w;
+                    kept += 1;
+                    continue;
+                }
+                let clause = &mut self.clauses[w.clause].lits;
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                if first != w.blocker && self.value(first) == 1 {
+                    watchers[kept] = Watcher { clause: w.clause, blocker: first };
+                    kept += 1;
+                    continue;
+                }
+                // another literal that is not false takes the place of the false one
+                let mut moved = false;
+                for k in 2..self.clauses[w.clause].lits.len() {
+                    let l = self.clauses[w.clause].lits[k];
+                    if self.value(l) != 0 {
+                        self.clauses[w.clause].lits.swap(1, k);
+                        self.watches[(!l).index()].push(Watcher { clause: w.clause, blocker: first });
+                        moved = true;
+                        break;
+                    }
+                }
+                if moved {
+                    continue;
+                }
+                watchers[kept] = Watcher { clause: w.clause, blocker: first };
+                kept += 1;
+                if self.value(first) == 0 {
+                    conflict = Some(w.clause);
+                    self.qhead = self.trail.len();
+                    while i < watchers.len() {
+                        watchers[kept] = watchers[i];
+                        kept += 1;
+                        i += 1;
+                    }
+                } else {
+                    self.enqueue(first, Some(w.clause));
+                }
+            }
+            watchers.truncate(kept);
+            // watchers added to this list while it was taken are kept as well
+            watchers.append(&mut self.watches[p.index()]);
+            self.watches[p.index()] = watchers;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    // the first uip clause learnt from a conflict, the level to jump back to and its
+    // literal block distance
+    fn analyze(&mut self, conflict: usize) -> (Vec<Lit>, usize, usize) {
+        let mut learnt = vec![Lit(0)];
+        let mut pending = 0;
+        let mut p: Option<Lit> = None;
+        let mut clause = conflict;
+        let mut index = self.trail.len();
+        loop {
+            self.bump_clause(clause);
+            let start = if p.is_some() { 1 } else { 0 };
+            for k in start..self.clauses[clause].lits.len() {
+                let q = self.clauses[clause].lits[k];
+                let v = q.var();
+                if !self.seen[v] && self.levels[v] > 0 {
+                    self.seen[v] = true;
+                    self.bump_var(v);
+                    if self.levels[v] >= self.level() {
+                        pending += 1;
+                    } else {
+                        learnt.push(q);
+                    }
+                }
+            }
+            // the next literal of the current level on the trail that is part of the conflict
+            loop {
+                index -= 1;
+                if self.seen[self.trail[index].var()] {
+                    break;
+                }
+            }
+            let l = self.trail[index];
+            p = Some(l);
+            self.seen[l.var()] = false;
+            pending -= 1;
+            if pending == 0 {
+                learnt[0] = !l;
+                break;
+            }
+            clause = self.reasons[l.var()].expect("implied literal without reason");
+        }
+
+        // literals implied by the other literals of the clause are removed
+        let marked: Vec<usize> = learnt[1..].iter().map(|l| l.var()).collect();
+        let mut minimized = vec![learnt[0]];
+        for &l in &learnt[1..] {
+            let redundant = match self.reasons[l.var()] {
+                Some(reason) => self.clauses[reason].lits[1..]
+                    .iter()
+                    .all(|q| self.seen[q.var()] || self.levels[q.var()] == 0),
+                None => false,
+            };
+            if !redundant {
+                minimized.push(l);
+            }
+        }
+        for v in marked {
+            self.seen[v] = false;
+        }
+        let mut learnt = minimized;
+
+        let mut backtrack = 0;
+        if learnt.len() > 1 {
+            let max = (1..learnt.len()).max_by_key(|&k| self.levels[learnt[k].var()]).unwrap();
+            learnt.swap(1, max);
+            backtrack = self.levels[learnt[1].var()];
+        }
+        let mut levels: Vec<usize> = learnt.iter().map(|l| self.levels[l.var()]).collect();
+        levels.sort_unstable();
+        levels.dedup();
+        (learnt, backtrack, levels.len())
+    }
+
+    fn bump_var(&mut self, var: usize) {
+        self.activity[var] += self.var_inc;
+        if self.activity[var] > 1e100 {
+            for a in self.activity.iter_mut() {
+                *a *= 1e-100;
+            }
+            self.var_inc *= 1e-100;
+        }
+        self.heap.increase(var, &self.activity);
+    }
+
+    fn bump_clause(&mut self, clause: usize) {
+        // learnt clauses taking part in conflicts get the smaller distance of their literals
+        if self.clauses[clause].learnt && self.clauses[clause].lbd > 2 {
+            let mut levels: Vec<usize> = self.clauses[clause].lits.iter().map(|l| self.levels[l.var()]).collect();
+            levels.sort_unstable();
+            levels.dedup();
+            self.clauses[clause].lbd = self.clauses[clause].lbd.min(levels.len());
+        }
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.level() <= level {
+            return;
+        }
+        let start = self.trail_lim[level];
+        for k in (start..self.trail.len()).rev() {
+            let l = self.trail[k];
+            self.assigns[l.var()] = UNDEF;
+            self.reasons[l.var()] = None;
+            self.phase[l.var()] = !l.is_negated();
+            if self.is_decision(l.var()) && !self.heap.contains(l.var()) {
+                self.heap.insert(l.var(), &self.activity);
+            }
+        }
+        self.trail.truncate(start);
+        self.trail_lim.truncate(level);
+        self.qhead = start;
+    }
+
+    // deletes the half of the learnt clauses with the largest literal block distance,
+    // clauses that are the reason of an assignment and those with a distance of 2 stay
+    fn reduce(&mut self) {
+        let locked = |solver: &Solver, idx: usize| {
+            let l = solver.clauses[idx].lits[0];
+            solver.value(l) == 1 && solver.reasons[l.var()] == Some(idx)
+        };
+        let mut candidates: Vec<usize> = (0..self.clauses.len())
+            .filter(|&idx| {
+                let c = &self.clauses[idx];
+                c.learnt && !c.deleted && c.lbd > 2
+            })
+            .filter(|&idx| !locked(self, idx))
+            .collect();
+        candidates.sort_by_key(|&idx| std::cmp::Reverse(self.clauses[idx].lbd));
+        for &idx in &candidates[..candidates.len() / 2] {
+            self.clauses[idx].deleted = true;
+            self.clauses[idx].lits = Vec::new();
+            self.learnts -= 1;
+        }
+        let clauses = &self.clauses;
+        for watchers in self.watches.iter_mut() {
+            watchers.retain(|w| !clauses[w.clause].deleted);
+        }
+        self.max_learnts += self.max_learnts / 10;
+    }
+
+    fn decide(&mut self) -> Option<Lit> {
+        while let Some(var) = self.heap.pop(&self.activity) {
+            if self.assigns[var] == UNDEF {
+                return Some(Lit::new(var, !self.phase[var]));
+            }
+        }
+        None
+    }
+
+    // searches an assignment of all variables satisfying the clauses in which every
+    // assumption is true, gives up with Unknown after conflict_limit conflicts
+    pub fn solve(&mut self, assumptions: &[Lit], conflict_limit: Option<usize>) -> SatResult {
+        if !self.ok {
+            return SatResult::Unsat;
+        }
+        self.reserve(assumptions.iter().map(|l| l.var() + 1).max().unwrap_or(0));
+        let mut conflicts = 0;
+        let mut restart = 0;
+        let mut budget = luby(restart) * 100;
+        let result = loop {
+            if let Some(conflict) = self.propagate() {
+                self.conflicts += 1;
+                conflicts += 1;
+                budget = budget.saturating_sub(1);
+                if self.level() == 0 {
+                    self.ok = false;
+                    break SatResult::Unsat;
+                }
+                // conflicts below the decisions on assumptions are learnt as usual, the
+                // assumptions are decided again afterwards
+                let (learnt, level, lbd) = self.analyze(conflict);
+                self.backtrack(level);
+                if learnt.len() == 1 {
+                    self.enqueue(learnt[0], None);
+                } else {
+                    let first = learnt[0];
+                    let idx = self.attach(Clause {
+                        lits: learnt,
+                        learnt: true,
+                        lbd,
+                        deleted: false,
+                    });
+                    self.learnts += 1;
+                    self.enqueue(first, Some(idx));
+                }
+                self.var_inc /= 0.95;
+                if conflict_limit.is_some_and(|limit| conflicts >= limit) {
+                    break SatResult::Unknown;
+                }
+                continue;
+            }
+
+            if budget == 0 {
+                restart += 1;
+                budget = luby(restart) * 100;
+                self.backtrack(0);
+                continue;
+            }
+            if self.learnts >= self.max_learnts + self.trail.len() {
+                self.reduce();
+            }
+
+            // assumptions are decided first, one level each
+            let mut next = None;
+            while self.level() < assumptions.len() {
+                let a = assumptions[self.level()];
+                match self.value(a) {
+                    1 => self.trail_lim.push(self.trail.len()),
+                    0 => break,
+                    _ => {
+                        next = Some(a);
+                        break;
+                    }
+                }
+            }
+            if self.level() < assumptions.len() && next.is_none() {
+                break SatResult::Unsat;
+            }
+            let next = match next {
+                Some(a) => a,
+                None => match self.decide() {
+                    Some(l) => l,
+                    None => {
+                        self.model = self.assigns.iter().map(|&v| v == 1).collect();
+                        break SatResult::Sat;
+                    }
+                },
+            };
+            self.trail_lim.push(self.trail.len());
+            self.enqueue(next, None);
+        };
+        self.backtrack(0);
+        result
+    }
+}
+
+// the luby sequence 1 1 2 1 1 2 4 1 1 2 1 1 2 4 8 ...
+fn luby(mut i: usize) -> usize {
+    let mut size = 1;
+    let mut exponent = 0;
+    while size < i + 1 {
+        exponent += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != i {
+        size = (size - 1) >> 1;
+        exponent -= 1;
+        i %= size;
+    }
+    1 << exponent
+}
+
+// binary max heap of variables ordered by activity
+#[derive(Default)]
+struct VarHeap {
+    heap: Vec<usize>,
+    // position of every variable in heap or usize::MAX
+    positions: Vec<usize>,
+}
+
+impl VarHeap {
+    fn clear(&mut self) {
+        for &var in &self.heap {
+            self.positions[var] = usize::MAX;
+        }
+        self.heap.clear();
+    }
+
+    fn contains(&self, var: usize) -> bool {
+        self.positions.get(var).is_some_and(|&p| p != usize::MAX)
+    }
+
+    fn insert(&mut self, var: usize, activity: &[f64]) {
+        if self.positions.len() <= var {
+            self.positions.resize(var + 1, usize::MAX);
+        }
+        self.positions[var] = self.heap.len();
+        self.heap.push(var);
+        self.up(self.heap.len() - 1, activity);
+    }
+
+    fn increase(&mut self, var: usize, activity: &[f64]) {
+        if self.contains(var) {
+            self.up(self.positions[var], activity);
+        }
+    }
+
+    fn pop(&mut self, activity: &[f64]) -> Option<usize> {
+        let top = *self.heap.first()?;
+        let last = self.heap.pop().unwrap();
+        self.positions[top] = usize::MAX;
+        if !self.heap.is_empty() {
+            self.heap[0] = last;
+            self.positions[last] = 0;
+            self.down(0, activity);
+        }
+        Some(top)
+    }
+
+    fn up(&mut self, mut p: usize, activity: &[f64]) {
+        let var = self.heap[p];
+        while p > 0 {
+            let parent = (p - 1) / 2;
+            if activity[self.heap[parent]] >= activity[var] {
+                break;
+            }
+            self.heap[p] = self.heap[parent];
+            self.positions[self.heap[p]] = p;
+            p = parent;
+        }
+        self.heap[p] = var;
+        self.positions[var] = p;
+    }
+
+    fn down(&mut self, mut p: usize, activity: &[f64]) {
+        let var = self.heap[p];
+        loop {
+            let mut child = 2 * p + 1;
+            if child >= self.heap.len() {
+                break;
+            }
+            if child + 1 < self.heap.len() && activity[self.heap[child + 1]] > activity[self.heap[child]] {
+                child += 1;
+            }
+            if activity[self.heap[child]] <= activity[var] {
+                break;
+            }
+            self.heap[p] = self.heap[child];
+            self.positions[self.heap[p]] = p;
+            p = child;
+        }
+        self.heap[p] = var;
+        self.positions[var] = p;
+    }
+}
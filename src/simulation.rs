@@ -0,0 +1,114 @@
+use crate::data::{Bit, Circuit, Gate};
+
+// the patterns of a gate's output from the words of all lines
+pub fn eval_gate(gate: Gate, values: &[u64]) -> u64 {
+    match gate {
+        Gate::Not(l) => !values[l.n],
+        Gate::And(l1, l2) => values[l1.n] & values[l2.n],
+        Gate::Or(l1, l2) => values[l1.n] | values[l2.n],
+        Gate::Xor(l1, l2) => values[l1.n] ^ values[l2.n],
+    }
+}
+
+// bit parallel simulation of a circuit, every line holds a u64 so 64 input patterns
+// are evaluated at once, pattern i of a line is bit i of its word
+// registers keep their state between calls of eval, clock moves every d into its q
+pub struct Simulation<'a> {
+    circuit: &'a Circuit,
+    values: Vec<u64>,
+}
+
+impl<'a> Simulation<'a> {
+    pub fn new(circuit: &'a Circuit) -> Self {
+        Simulation {
+            circuit,
+            values: vec![0; circuit.stats.line_count],
+        }
+    }
+
+    // words[i] holds the patterns of bit i of the input, missing words are zero
+    pub fn set_input(&mut self, name: &str, words: &[u64]) {
+        let input = self
+            .circuit
+            .inputs
+            .iter()
+            .find(|io| io.name == name)
+            .unwrap_or_else(|| panic!("circuit has no input named {name}"));
+        for (idx, bit) in input.bits.iter().enumerate() {
+            if let Bit::Var(l) = bit {
+                self.values[l.n] = words.get(idx).copied().unwrap_or(0);
+            }
+        }
+    }
+
+    // evaluates all gates from the current inputs and register states
+    pub fn eval(&mut self) {
+        for wire in &self.circuit.wires {
+            self.values[wire.out.n] = eval_gate(wire.gate, &self.values);
+        }
+    }
+
+    // a rising clock edge, has to be preceded by eval
+    pub fn clock(&mut self) {
+        let reset = self.circuit.reset.map_or(0, |l| self.values[l.n]);
+        let next: Vec<u64> = self
+            .circuit
+            .registers
+            .iter()
+            .map(|r| {
+                let d = self.value(r.d);
+                if r.reset { d & !reset } else { d }
+            })
+            .collect();
+        for (register, v) in self.circuit.registers.iter().zip(next) {
+            self.values[register.q.n] = v;
+        }
+    }
+
+    pub fn value(&self, bit: Bit) -> u64 {
+        match bit {
+            Bit::Zero => 0,
+            Bit::One => u64::MAX,
+            Bit::Var(l) => self.values[l.n],
+        }
+    }
+
+    // the words of all lines indexed by line number
+    pub fn values(&self) -> &[u64] {
+        &self.values
+    }
+
+    pub fn output(&self, name: &str) -> Vec<u64> {
+        let output = self
+            .circuit
+            .outputs
+            .iter()
+            .find(|io| io.name == name)
+            .unwrap_or_else(|| panic!("circuit has no output named {name}"));
+        output.bits.iter().map(|&bit| self.value(bit)).collect()
+    }
+}
+
+// transposes up to 64 numbers into one word per bit position
+pub fn pack(numbers: &[u128], bits: usize) -> Vec<u64> {
+    (0..bits)
+        .map(|idx| {
+            numbers
+                .iter()
+                .enumerate()
+                .fold(0, |word, (pattern, &n)| word | ((((n >> idx) & 0x1) as u64) << pattern))
+        })
+        .collect()
+}
+
+// inverse of pack, returns the 64 numbers held by the words
+pub fn unpack(words: &[u64]) -> Vec<u128> {
+    (0..64)
+        .map(|pattern| {
+            words
+                .iter()
+                .enumerate()
+                .fold(0, |n, (idx, &word)| n | ((((word >> pattern) & 0x1) as u128) << idx))
+        })
+        .collect()
+}
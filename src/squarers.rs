@@ -0,0 +1,126 @@
+use crate::data::{Bit, Bit::Zero, Circuit, Adder, Truncation};
+use std::collections::VecDeque;
+
+// squarers make use of the symmetry of the and-array of m * m:
+// x_a * x_b with a != b appears twice in column a + b, so it is placed once in column a + b + 1,
+// the diagonal x_a * x_a = x_a needs no gate at all,
+// this roughly halves the number of partial products compared to a multiplier
+impl Circuit {
+    // dadda squarer with folded partial products, the highest `ignore` bits of the square
+    // are not computed at all, the result has 2 * m.len() - ignore bits
+    pub fn square_u(&mut self, m: Vec<Bit>, ignore: usize, adder: Adder) -> Vec<Bit> {
+        let width = (2 * m.len()).saturating_sub(ignore);
+        if width == 0 {
+            return vec![];
+        }
+        let summands = self.square_partial_products(&m, width);
+        let max_rows = summands.iter().map(|column| column.len()).max().unwrap_or(0);
+        let mut r = self.dadda_tree(summands, max_rows, adder);
+        r.truncate(width);
+        r
+    }
+
+    // array squarer with folded partial products, row a holds x_a in column 2a and the
+    // products x_a * x_b with b > a, the rows are accumulated in carry save form like in an
+    // array multiplier which needs about half of its full adders, the depth stays O(n)
+    pub fn array_square(&mut self, m: Vec<Bit>, ignore: usize, adder: Adder) -> Vec<Bit> {
+        let n = m.len();
+        let width = (2 * n).saturating_sub(ignore);
+        if width == 0 {
+            return vec![];
+        }
+        let mut sums = Bit::zeroes(width);
+        let mut carrys = Bit::zeroes(width + 1);
+
+        for a in 0..n {
+            // going downwards every carry gets written after its column has consumed the old one
+            for column in (2 * a..=(n + a)).rev() {
+                if column >= width {
+                    continue;
+                }
+                let partial_product = if column == 2 * a {
+                    m[a]
+                } else if column == 2 * a + 1 {
+                    Zero
+                } else {
+                    self.and(m[a], m[column - a - 1])
+                };
+                let sum = self.full_adder(partial_product, sums[column], carrys[column]);
+                sums[column] = sum.s;
+                carrys[column] = Zero;
+                carrys[column + 1] = sum.c;
+            }
+        }
+
+        carrys.truncate(width);
+        let mut r = adder.add(self, sums, carrys, Zero);
+        r.truncate(width);
+        r
+    }
+
+    // booth squarer, the radix-4 recoded partial products of m * m, their digits are not the
+    // bits of m so they cannot be folded, the highest `ignore` bits are not computed at all
+    pub fn booth_square(&mut self, m: Vec<Bit>, ignore: usize, adder: Adder) -> Vec<Bit> {
+        let width = (2 * m.len()).saturating_sub(ignore);
+        if width == 0 {
+            return vec![];
+        }
+        let summands = self.booth_partial_products(&m, &m, width);
+        let max_rows = summands.iter().map(|column| column.len()).max().unwrap_or(0);
+        let mut r = self.dadda_tree(summands, max_rows, adder);
+        r.truncate(width);
+        r
+    }
+
+    // truncated squarer with folded partial products, the highest `ignore` bits of the square
+    // are not computed at all (like in square_u) and the lowest `drop` bits are omitted like in truncated_mul
+    pub fn truncated_square(&mut self, m: Vec<Bit>, ignore: usize, drop: usize, truncation: Truncation, adder: Adder) -> Vec<Bit> {
+        if m.is_empty() {
+            return vec![];
+        }
+        let heights = Self::square_column_heights(m.len(), ignore);
+        self.truncated_product(&heights, drop, truncation, adder, |circuit, column| {
+            circuit.square_column(&m, column)
+        })
+    }
+
+    // the folded partial products of the lowest `columns` columns of m * m
+    pub fn square_partial_products(&mut self, m: &[Bit], columns: usize) -> Vec<VecDeque<Bit>> {
+        (0..columns)
+            .map(|column| VecDeque::from(self.square_column(m, column)))
+            .collect()
+    }
+
+    // the folded partial products of a single column of m * m
+    pub fn square_column(&mut self, m: &[Bit], column: usize) -> Vec<Bit> {
+        let n = m.len();
+        let mut bits = Vec::with_capacity(n / 2 + 1);
+        if column.is_multiple_of(2) && column / 2 < n {
+            bits.push(m[column / 2]);
+        }
+        // a * b with a < b and a + b + 1 = column
+        if column > 0 && n > 0 {
+            let sum = column - 1;
+            for a in sum.saturating_sub(n - 1)..sum.div_ceil(2) {
+                bits.push(self.and(m[a], m[sum - a]));
+            }
+        }
+        bits
+    }
+
+    // number of folded partial products in every column of the square of an n bit number
+    pub fn square_column_heights(n: usize, ignore: usize) -> Vec<usize> {
+        let width = (2 * n).saturating_sub(ignore);
+        (0..width)
+            .map(|c| {
+                let diagonal = usize::from(c.is_multiple_of(2) && c / 2 < n);
+                let pairs = if c == 0 {
+                    0
+                } else {
+                    (c - 1).div_ceil(2).saturating_sub((c - 1).saturating_sub(n - 1))
+                };
+                diagonal + pairs
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{Error, ErrorKind, Write};
+
+use crate::data::{Bit, Circuit, Gate, Line};
+use crate::liberty::Library;
+
+// an instance of library cell `cell`, pin i of the cell is driven by line inputs[i]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MappedCell {
+    pub cell: usize,
+    pub inputs: Vec<usize>,
+    pub output: usize,
+}
+
+// the cells replacing the gates of a circuit in the order of the gates they replace,
+// area and delay are given in the units of the library
+#[derive(Debug, PartialEq, Clone)]
+pub struct MappedNetlist {
+    pub cells: Vec<MappedCell>,
+    pub area: f64,
+    pub delay: f64,
+}
+
+impl fmt::Display for MappedNetlist {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cell count: {}, Area: {:.2}, Delay: {:.4}", self.cells.len(), self.area, self.delay)
+    }
+}
+
+impl Library {
+    // every function a cell implements with some assignment of the leaves of a cut to its
+    // pins, keyed by the number of leaves and the truth table of the cut, the value is the
+    // cell and the leaf connected to each of its pins, the smallest cell wins
+    pub fn matches(&self) -> HashMap<(usize, u64), (usize, Vec<usize>)> {
+        let mut matches: HashMap<(usize, u64), (usize, Vec<usize>)> = HashMap::new();
+        for (idx, cell) in self.cells.iter().enumerate() {
+            let n = cell.inputs.len();
+            for perm in permutations(n) {
+                let mut truth = 0;
+                for m in 0..(1_usize << n) {
+                    let index = perm.iter().enumerate().fold(0, |index, (pin, &leaf)| index | (((m >> leaf) & 0x1) << pin));
+                    truth |= ((cell.truth >> index) & 0x1) << m;
+                }
+                let better = match matches.get(&(n, truth)) {
+                    Some(&(other, _)) => cell.area < self.cells[other].area,
+                    None => true,
+                };
+                if better {
+                    matches.insert((n, truth), (idx, perm));
+                }
+            }
+        }
+        matches
+    }
+}
+
+fn permutations(n: usize) -> Vec<Vec<usize>> {
+    if n == 0 {
+        return vec![vec![]];
+    }
+    let mut r = Vec::new();
+    for perm in permutations(n - 1) {
+        for position in 0..n {
+            let mut p = perm.clone();
+            p.insert(position, n - 1);
+            r.push(p);
+        }
+    }
+    r
+}
+
+// the gate built from NAND2 cells, and INV cells if the library has them, for gates that no
+// cell of the library matches, the lines between the cells are numbered from next_line on
+fn nand_cells(gate: Gate, out: usize, nand: usize, inv: Option<usize>, next_line: &mut usize) -> Vec<MappedCell> {
+    let nand_cell = |a: usize, b: usize, output: usize| MappedCell { cell: nand, inputs: vec![a, b], output };
+    let not_cell = |a: usize, output: usize| match inv {
+        Some(inv) => MappedCell { cell: inv, inputs: vec![a], output },
+        None => nand_cell(a, a, output),
+    };
+    let (t0, t1, t2) = (*next_line, *next_line + 1, *next_line + 2);
+    match gate {
+        Gate::Not(a) => vec![not_cell(a.n, out)],
+        Gate::And(a, b) => {
+            *next_line += 1;
+            vec![nand_cell(a.n, b.n, t0), not_cell(t0, out)]
+        }
+        Gate::Or(a, b) => {
+            *next_line += 2;
+            vec![not_cell(a.n, t0), not_cell(b.n, t1), nand_cell(t0, t1, out)]
+        }
+        Gate::Xor(a, b) => {
+            *next_line += 3;
+            vec![nand_cell(a.n, b.n, t0), nand_cell(a.n, t0, t1), nand_cell(b.n, t0, t2), nand_cell(t1, t2, out)]
+        }
+    }
+}
+
+impl Circuit {
+    // covers the gates with cells of the library, every gate picks the cut matched by a cell
+    // with the least area flow, that is its area plus the area of the cells its leaves need
+    // shared among their fanouts, ties go to the faster cell, a gate without any matching cut
+    // is built from NAND2 and INV cells, libraries without a NAND2 cell give an error then
+    pub fn map_to_library(&self, library: &Library) -> std::io::Result<MappedNetlist> {
+        let matches = library.matches();
+        let nand = matches.get(&(2, 0b0111)).map(|&(cell, _)| cell);
+        let inv = matches.get(&(1, 0b01)).map(|&(cell, _)| cell);
+        let mut next_line = self.stats.line_count;
+        let k = library.cells.iter().map(|c| c.inputs.len()).max().unwrap_or(2);
+        let cuts = self.enumerate_cuts(k, 8);
+        let fanouts = self.fanouts();
+        let wire_of_line: HashMap<usize, usize> = self.wires.iter().enumerate().map(|(idx, w)| (w.out.n, idx)).collect();
+
+        // arrival time and area flow of every line driven by a gate
+        let mut arrival: HashMap<usize, f64> = HashMap::with_capacity(self.wires.len());
+        let mut flow: HashMap<usize, f64> = HashMap::with_capacity(self.wires.len());
+        let mut choices: Vec<Vec<MappedCell>> = Vec::with_capacity(self.wires.len());
+        for (idx, wire) in self.wires.iter().enumerate() {
+            let mut best: Option<(f64, f64, MappedCell)> = None;
+            for cut in &cuts[idx] {
+                let Some((cell, perm)) = matches.get(&(cut.leaves.len(), cut.truth)) else {
+                    continue;
+                };
+                let cell_arrival = cut.leaves.iter().map(|l| arrival.get(l).copied().unwrap_or(0.0)).fold(0.0, f64::max)
+                    + library.cells[*cell].delay;
+                let cell_flow = library.cells[*cell].area
+                    + cut
+                        .leaves
+                        .iter()
+                        .map(|l| flow.get(l).copied().unwrap_or(0.0) / fanouts.get(l).copied().unwrap_or(1).max(1) as f64)
+                        .sum::<f64>();
+                let better = match &best {
+                    Some((f, a, _)) => cell_flow < f - 1e-9 || (cell_flow < f + 1e-9 && cell_arrival < *a),
+                    None => true,
+                };
+                if better {
+                    let inputs = perm.iter().map(|&leaf| cut.leaves[leaf]).collect();
+                    best = Some((cell_flow, cell_arrival, MappedCell { cell: *cell, inputs, output: wire.out.n }));
+                }
+            }
+            let (cell_flow, cell_arrival, choice) = match best {
+                Some((cell_flow, cell_arrival, choice)) => (cell_flow, cell_arrival, vec![choice]),
+                None => {
+                    let Some(nand) = nand else {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            format!("library {} has no cell for the gate {:?} and no NAND2 cell to build it from", library.name, wire.gate),
+                        ));
+                    };
+                    let cells = nand_cells(wire.gate, wire.out.n, nand, inv, &mut next_line);
+                    for cell in &cells {
+                        let cell_arrival = cell.inputs.iter().map(|l| arrival.get(l).copied().unwrap_or(0.0)).fold(0.0, f64::max)
+                            + library.cells[cell.cell].delay;
+                        arrival.insert(cell.output, cell_arrival);
+                    }
+                    let leaves = match wire.gate {
+                        Gate::Not(a) => vec![a.n],
+                        Gate::And(a, b) | Gate::Or(a, b) | Gate::Xor(a, b) => vec![a.n, b.n],
+                    };
+                    let cell_flow = cells.iter().map(|c| library.cells[c.cell].area).sum::<f64>()
+                        + leaves
+                            .iter()
+                            .map(|l| flow.get(l).copied().unwrap_or(0.0) / fanouts.get(l).copied().unwrap_or(1).max(1) as f64)
+                            .sum::<f64>();
+                    (cell_flow, arrival[&wire.out.n], cells)
+                }
+            };
+            flow.insert(wire.out.n, cell_flow);
+            arrival.insert(wire.out.n, cell_arrival);
+            choices.push(choice);
+        }
+
+        // the cells needed by the outputs and registers
+        let mut used: HashSet<usize> = HashSet::new();
+        let register_inputs = self.registers.iter().map(|r| r.d);
+        let outputs = self.outputs.iter().flat_map(|o| o.bits.iter().copied());
+        let mut stack: Vec<usize> = register_inputs
+            .chain(outputs)
+            .filter_map(|bit| match bit {
+                Bit::Var(l) => wire_of_line.get(&l.n).copied(),
+                _ => None,
+            })
+            .collect();
+        let delay = stack.iter().map(|&idx| arrival[&self.wires[idx].out.n]).fold(0.0, f64::max);
+        while let Some(idx) = stack.pop() {
+            if used.insert(idx) {
+                stack.extend(choices[idx].iter().flat_map(|c| &c.inputs).filter_map(|l| wire_of_line.get(l).copied()));
+            }
+        }
+
+        let cells: Vec<MappedCell> = choices
+            .into_iter()
+            .enumerate()
+            .filter(|(idx, _)| used.contains(idx))
+            .flat_map(|(_, cells)| cells)
+            .collect();
+        let area = cells.iter().map(|c| library.cells[c.cell].area).sum();
+        Ok(MappedNetlist { cells, area, delay })
+    }
+
+    // the circuit as verilog netlist of library cells
+    pub fn mapped_verilog(&mut self, netlist: &MappedNetlist, library: &Library, name: &str) -> String {
+        let mut s = String::new();
+        s.push_str(&self.verilog_header(name));
+        s.push_str(&self.io_labels());
+        let line = |n: usize| Line { level: 0, n }.to_verilog(&self.io_lines);
+        for cell in &netlist.cells {
+            if !self.io_lines.contains_key(&cell.output) {
+                s.push_str(&format!("wire _{}_;\n", cell.output));
+            }
+        }
+        s.push_str(&self.register_labels());
+        s.push_str(&self.constant_assigns());
+        for (idx, mapped) in netlist.cells.iter().enumerate() {
+            let cell = &library.cells[mapped.cell];
+            let mut pins: Vec<String> = cell
+                .inputs
+                .iter()
+                .zip(&mapped.inputs)
+                .map(|(pin, &n)| format!(".{pin}({})", line(n)))
+                .collect();
+            pins.push(format!(".{}({})", cell.output, line(mapped.output)));
+            s.push_str(&format!("{} u{idx} ({});\n", cell.name, pins.join(", ")));
+        }
+        s.push_str(&self.register_block());
+        s.push_str(&self.output_assigns());
+        s.push_str("endmodule");
+        s
+    }
+
+    pub fn write_mapped_to_file(&mut self, netlist: &MappedNetlist, library: &Library, file_name: &str, module_name: &str) -> std::io::Result<()> {
+        let mut file = File::create(file_name)?;
+        write!(file, "{}", self.mapped_verilog(netlist, library, module_name))?;
+        Ok(())
+    }
+}
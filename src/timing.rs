@@ -0,0 +1,214 @@
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt;
+
+use crate::cost::GateCosts;
+use crate::data::{Bit, Circuit, Gate, Line};
+
+// one gate of a timing path, depth counts the gates from the start of the path up to and
+// including this one, arrival is the sum of their delays
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PathGate {
+    pub line: String,
+    pub gate: &'static str,
+    pub component: String,
+    pub depth: usize,
+    pub arrival: usize,
+}
+
+// a path through the gates from an input or a register to an output bit or a register
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TimingPath {
+    pub start: String,
+    pub end: String,
+    pub gates: Vec<PathGate>,
+}
+
+impl TimingPath {
+    pub fn depth(&self) -> usize {
+        self.gates.len()
+    }
+
+    pub fn delay(&self) -> usize {
+        self.gates.last().map_or(0, |g| g.arrival)
+    }
+}
+
+// the worst paths of a circuit sorted by decreasing delay and the arrival time of every
+// output bit, constant output bits have no arrival time
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TimingReport {
+    pub paths: Vec<TimingPath>,
+    pub arrivals: Vec<(String, Vec<Option<usize>>)>,
+}
+
+impl fmt::Display for TimingReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (idx, path) in self.paths.iter().enumerate() {
+            writeln!(f, "path {}: delay {}, depth {}, from {} to {}", idx + 1, path.delay(), path.depth(), path.start, path.end)?;
+            writeln!(f, "{:>8} {:>8}  {:<4} {:<12} component", "depth", "arrival", "gate", "line")?;
+            for gate in &path.gates {
+                writeln!(f, "{:>8} {:>8}  {:<4} {:<12} {}", gate.depth, gate.arrival, gate.gate, gate.line, gate.component)?;
+            }
+        }
+        writeln!(f, "arrival times (lowest bit first):")?;
+        for (name, bits) in &self.arrivals {
+            let bits: Vec<String> = bits.iter().map(|a| a.map_or(String::from("-"), |a| a.to_string())).collect();
+            writeln!(f, "{name}: {}", bits.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl TimingReport {
+    pub fn to_json(&self) -> String {
+        let paths: Vec<String> = self
+            .paths
+            .iter()
+            .map(|path| {
+                let gates: Vec<String> = path
+                    .gates
+                    .iter()
+                    .map(|g| {
+                        format!(
+                            "{{\"depth\": {}, \"arrival\": {}, \"gate\": \"{}\", \"line\": \"{}\", \"component\": \"{}\"}}",
+                            g.depth, g.arrival, g.gate, g.line, g.component
+                        )
+                    })
+                    .collect();
+                format!(
+                    "    {{\"start\": \"{}\", \"end\": \"{}\", \"delay\": {}, \"depth\": {}, \"gates\": [\n      {}\n    ]}}",
+                    path.start,
+                    path.end,
+                    path.delay(),
+                    path.depth(),
+                    gates.join(",\n      ")
+                )
+            })
+            .collect();
+        let arrivals: Vec<String> = self
+            .arrivals
+            .iter()
+            .map(|(name, bits)| {
+                let bits: Vec<String> = bits.iter().map(|a| a.map_or(String::from("null"), |a| a.to_string())).collect();
+                format!("    \"{name}\": [{}]", bits.join(", "))
+            })
+            .collect();
+        format!(
+            "{{\n  \"paths\": [\n{}\n  ],\n  \"arrivals\": {{\n{}\n  }}\n}}\n",
+            paths.join(",\n"),
+            arrivals.join(",\n")
+        )
+    }
+}
+
+impl Circuit {
+    // the k slowest paths ending in an output bit or a register and the arrival times of
+    // all output bits, paths are searched backwards from their ends and always the partial
+    // path which can still become the slowest one is extended, as the arrival time of a line
+    // is the delay of the slowest path leading to it complete paths are found in order
+    pub fn timing_report(&self, k: usize, costs: &GateCosts) -> TimingReport {
+        let wire_of_line: HashMap<usize, usize> = self.wires.iter().enumerate().map(|(idx, w)| (w.out.n, idx)).collect();
+        let arrival = self.arrival_times(costs);
+        let delays = self.gate_delays(costs);
+        let components = self.component_paths();
+        let registers: HashSet<usize> = self.registers.iter().map(|r| r.q.n).collect();
+        let name = |l: Line| match registers.contains(&l.n) {
+            true => format!("reg {}", l.to_verilog(&self.io_lines)),
+            false => l.to_verilog(&self.io_lines),
+        };
+
+        let mut ends: Vec<(String, usize)> = Vec::new();
+        for register in &self.registers {
+            if let Bit::Var(l) = register.d {
+                ends.push((format!("reg {}", register.q.to_verilog(&self.io_lines)), l.n));
+            }
+        }
+        for output in &self.outputs {
+            for (idx, bit) in output.bits.iter().enumerate() {
+                if let Bit::Var(l) = bit {
+                    ends.push((format!("{}[{}]", output.name, idx), l.n));
+                }
+            }
+        }
+
+        // partial paths as a linked list from their first gate to their end
+        // (wire, delay of the following gates up to the end, next node, end)
+        let mut nodes: Vec<(usize, usize, Option<usize>, usize)> = Vec::new();
+        // (bound on the depth, search order, node, start line of a complete path), on equal bounds the
+        // newest partial path gets extended first, so every complete path is found in a single descent
+        let mut queue: BinaryHeap<(usize, usize, usize, Option<usize>)> = BinaryHeap::new();
+        for (end, (_, n)) in ends.iter().enumerate() {
+            if let Some(&wire) = wire_of_line.get(n) {
+                nodes.push((wire, 0, None, end));
+                queue.push((arrival[n], queue.len(), nodes.len() - 1, None));
+            }
+        }
+
+        let mut paths = Vec::new();
+        let mut order = queue.len();
+        while paths.len() < k {
+            let Some((_, _, node, start)) = queue.pop() else {
+                break;
+            };
+            let (wire, after, _, end) = nodes[node];
+            if let Some(start) = start {
+                let mut gates: Vec<PathGate> = Vec::new();
+                let mut current = Some(node);
+                while let Some(idx) = current {
+                    let wire = &self.wires[nodes[idx].0];
+                    gates.push(PathGate {
+                        line: name(wire.out),
+                        gate: gate_name(&wire.gate),
+                        component: components[wire.scope].clone(),
+                        depth: gates.len() + 1,
+                        arrival: gates.last().map_or(0, |g| g.arrival) + delays[nodes[idx].0],
+                    });
+                    current = nodes[idx].2;
+                }
+                paths.push(TimingPath {
+                    start: name(Line { level: 0, n: start }),
+                    end: ends[end].0.clone(),
+                    gates,
+                });
+                continue;
+            }
+            let after = after + delays[wire];
+            for l in self.wires[wire].gate.inputs() {
+                order += 1;
+                match wire_of_line.get(&l.n) {
+                    Some(&input) => {
+                        nodes.push((input, after, Some(node), end));
+                        queue.push((after + arrival[&l.n], order, nodes.len() - 1, None));
+                    }
+                    None => queue.push((after, order, node, Some(l.n))),
+                }
+            }
+        }
+
+        let arrivals = self
+            .outputs
+            .iter()
+            .map(|output| {
+                let bits = output
+                    .bits
+                    .iter()
+                    .map(|bit| match bit {
+                        Bit::Var(l) => Some(arrival.get(&l.n).copied().unwrap_or(0)),
+                        _ => None,
+                    })
+                    .collect();
+                (output.name.clone(), bits)
+            })
+            .collect();
+        TimingReport { paths, arrivals }
+    }
+}
+
+fn gate_name(gate: &Gate) -> &'static str {
+    match gate {
+        Gate::Not(_) => "not",
+        Gate::And(_, _) => "and",
+        Gate::Or(_, _) => "or",
+        Gate::Xor(_, _) => "xor",
+    }
+}
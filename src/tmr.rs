@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::data::{Bit, Circuit, Gate};
+
+// size of a circuit before and after triplication, voters counts the majority gates of three
+// inputs, voted_lines the lines of the cut points and registers voted in every copy
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TmrReport {
+    pub gates_before: usize,
+    pub gates_after: usize,
+    pub registers_before: usize,
+    pub registers_after: usize,
+    pub depth_before: usize,
+    pub depth_after: usize,
+    pub voters: usize,
+    pub voted_lines: usize,
+}
+
+impl fmt::Display for TmrReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "gate count: {} -> {} ({:.2}x), register count: {} -> {}, depth: {} -> {}, {} voters for {} voted lines and the outputs",
+            self.gates_before,
+            self.gates_after,
+            self.gates_after as f64 / self.gates_before.max(1) as f64,
+            self.registers_before,
+            self.registers_after,
+            self.depth_before,
+            self.depth_after,
+            self.voters,
+            self.voted_lines
+        )
+    }
+}
+
+impl Circuit {
+    // the majority of three bits, (a & b) | (c & (a | b))
+    pub fn majority(&mut self, a: Bit, b: Bit, c: Bit) -> Bit {
+        let both = self.and(a, b);
+        let either = self.or(a, b);
+        let third = self.and(c, either);
+        self.or(both, third)
+    }
+
+    // true if scope is the component or one of its subcomponents
+    fn within(&self, mut scope: usize, component: usize) -> bool {
+        while scope != component && scope != 0 {
+            scope = self.scopes[scope].parent;
+        }
+        scope == component
+    }
+
+    // the lines driven inside a component named like one of vote_after, by name like
+    // newton_iteration or by path like newton_iteration[1], and read outside of it
+    fn cut_lines(&self, vote_after: &[String]) -> Vec<bool> {
+        let paths = self.component_paths();
+        let names: Vec<String> = paths.iter().map(|path| path.rsplit('/').next().unwrap().to_string()).collect();
+        let matches = |scope: usize, component: &String| {
+            scope != 0 && (*component == self.scopes[scope].name || *component == paths[scope] || *component == names[scope])
+        };
+        for component in vote_after {
+            if !(0..self.scopes.len()).any(|scope| matches(scope, component)) {
+                panic!("circuit has no component {component}");
+            }
+        }
+        let selected: Vec<bool> = (0..self.scopes.len()).map(|scope| vote_after.iter().any(|c| matches(scope, c))).collect();
+        // the innermost selected component every gate lies in
+        let mut cut_scope = vec![None; self.scopes.len()];
+        for scope in 1..self.scopes.len() {
+            cut_scope[scope] = if selected[scope] { Some(scope) } else { cut_scope[self.scopes[scope].parent] };
+        }
+        let mut component_of_line = vec![None; self.stats.line_count];
+        for wire in &self.wires {
+            component_of_line[wire.out.n] = cut_scope[wire.scope];
+        }
+        let mut cut = vec![false; self.stats.line_count];
+        for wire in &self.wires {
+            for l in wire.gate.inputs() {
+                if let Some(component) = component_of_line[l.n] {
+                    cut[l.n] |= !self.within(wire.scope, component);
+                }
+            }
+        }
+        cut
+    }
+
+    // triple modular redundancy, the gates and registers are built three times, majority voters
+    // combine the copies at the outputs, where one voter drives every output bit, behind the
+    // registers and at the lines leaving the components of vote_after, where every copy gets
+    // its own voter so no single voter can break more than one copy, inputs are shared
+    pub fn triplicate(&mut self, vote_after: &[String]) -> TmrReport {
+        self.update_stats();
+        let (gates_before, registers_before, depth_before) = (self.stats.gatter_count, self.registers.len(), self.stats.level_count);
+        let cut = self.cut_lines(vote_after);
+        let wires = std::mem::take(&mut self.wires);
+        let registers = std::mem::take(&mut self.registers);
+
+        // the bit of every line in each copy, inputs are the same in all of them
+        let mut copies: [HashMap<usize, Bit>; 3] = Default::default();
+        for register in &registers {
+            copies[0].insert(register.q.n, Bit::Var(register.q));
+            for copy in copies.iter_mut().skip(1) {
+                let q = self.new_register_lines(1)[0];
+                copy.insert(register.q.n, q);
+            }
+        }
+        let lookup = |copy: &HashMap<usize, Bit>, bit: Bit| match bit {
+            Bit::Var(l) => copy.get(&l.n).copied().unwrap_or(bit),
+            _ => bit,
+        };
+        // the voters of a cut point belong to a component in the component of the cut
+        let mut voter_scopes: HashMap<usize, usize> = HashMap::new();
+        let mut voter_scope = |circuit: &mut Circuit, parent: usize| {
+            *voter_scopes.entry(parent).or_insert_with(|| {
+                circuit.scope = parent;
+                circuit.enter_scope("tmr_voter");
+                circuit.scope
+            })
+        };
+        let mut voters = 0;
+        let mut voted_lines = 0;
+        let mut vote = |circuit: &mut Circuit, bits: [Bit; 3], scope: usize| {
+            circuit.scope = scope;
+            let [a, b, c] = bits;
+            voters += 1;
+            circuit.majority(a, b, c)
+        };
+
+        for wire in &wires {
+            let mut outs = [Bit::Zero; 3];
+            for (copy, out) in copies.iter().zip(outs.iter_mut()) {
+                self.scope = wire.scope;
+                let get = |l| lookup(copy, Bit::Var(l));
+                *out = match wire.gate {
+                    Gate::Not(l) => self.not(get(l)),
+                    Gate::And(l1, l2) => self.and(get(l1), get(l2)),
+                    Gate::Or(l1, l2) => self.or(get(l1), get(l2)),
+                    Gate::Xor(l1, l2) => self.xor(get(l1), get(l2)),
+                };
+            }
+            if cut[wire.out.n] {
+                let scope = voter_scope(self, wire.scope);
+                for copy in copies.iter_mut() {
+                    let voted = vote(self, outs, scope);
+                    copy.insert(wire.out.n, voted);
+                }
+                voted_lines += 1;
+            } else {
+                for (copy, out) in copies.iter_mut().zip(outs) {
+                    copy.insert(wire.out.n, out);
+                }
+            }
+        }
+
+        // a register loads the vote of the three copies, so an upset is corrected a cycle later
+        let top_voters = voter_scope(self, 0);
+        for register in &registers {
+            let ds = [0, 1, 2].map(|copy| lookup(&copies[copy], register.d));
+            for copy in &copies {
+                let d = vote(self, ds, top_voters);
+                self.add_registers(&[lookup(copy, Bit::Var(register.q))], &[d], register.reset);
+            }
+            voted_lines += 1;
+        }
+        for idx in 0..self.outputs.len() {
+            let bits = self.outputs[idx].bits.clone();
+            self.outputs[idx].bits = bits.iter().map(|&bit| vote(self, [0, 1, 2].map(|copy| lookup(&copies[copy], bit)), top_voters)).collect();
+        }
+        self.scope = 0;
+        self.rename_outputs();
+        self.update_stats();
+        TmrReport {
+            gates_before,
+            gates_after: self.stats.gatter_count,
+            registers_before,
+            registers_after: self.registers.len(),
+            depth_before,
+            depth_after: self.stats.level_count,
+            voters,
+            voted_lines,
+        }
+    }
+}